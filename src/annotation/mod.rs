@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use fontdue::Font;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::{Mutex, Notify};
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+
+use crate::annotation::overlay::{Color, Label, Shape};
+use crate::auth::ViewerManager;
+use crate::output::ChannelHandler;
+
+mod overlay;
+
+pub use overlay::{Canvas, Pixels};
+
+/// How long a pointer stays visible after it last moved.
+const POINTER_TIMEOUT: Duration = Duration::from_secs(3);
+/// Strokes kept per viewer, older ones are removed first.
+const MAX_STROKES: usize = 64;
+/// Points kept per stroke, further points are ignored.
+const MAX_STROKE_POINTS: usize = 4096;
+/// Colors assigned to viewers in the order they join.
+const PALETTE: [[u8; 3]; 8] = [
+    [239, 68, 68],
+    [59, 130, 246],
+    [34, 197, 94],
+    [234, 179, 8],
+    [168, 85, 247],
+    [236, 72, 153],
+    [20, 184, 166],
+    [249, 115, 22],
+];
+
+/// Wait until the annotations of `overlay` change, forever if there is none.
+pub async fn changed(overlay: Option<&AnnotationManager>) {
+    match overlay {
+        Some(overlay) => overlay.changed().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Messages of the `annotation` data channel. Coordinates are relative to the
+/// shared display, from 0 to 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnnotationMessage {
+    /// The viewer moved its laser pointer.
+    Pointer {
+        x: f32,
+        y: f32,
+    },
+    /// The viewer's pointer left the display.
+    HidePointer,
+    StrokeStart {
+        x: f32,
+        y: f32,
+    },
+    StrokePoint {
+        x: f32,
+        y: f32,
+    },
+    StrokeEnd,
+    /// Sent by a viewer to remove its own strokes, and by the sharer when all
+    /// annotations were cleared.
+    Clear,
+    /// Sent by the sharer when a viewer connects and whenever annotations are
+    /// enabled or disabled, along with the color assigned to the viewer.
+    State {
+        enabled: bool,
+        color: String,
+    },
+}
+
+struct ViewerAnnotations {
+    color: [u8; 3],
+    name: String,
+    /// The name rasterized at the size last drawn.
+    label: Option<Label>,
+    pointer: Option<((f32, f32), Instant)>,
+    /// The outer and inner circles of the pointer, rasterized at the scale last drawn.
+    pointer_shapes: Option<(f32, Shape, Shape)>,
+    strokes: Vec<Stroke>,
+    drawing: bool,
+}
+
+struct Stroke {
+    points: Vec<(f32, f32)>,
+    /// The segments rasterized so far, or a dot while there is a single point.
+    shapes: Vec<Shape>,
+    /// The number of points rasterized and the frame size they were rasterized for.
+    rasterized: usize,
+    size: (usize, usize),
+}
+
+impl Stroke {
+    fn new(point: (f32, f32)) -> Self {
+        Self {
+            points: vec![point],
+            shapes: Vec::new(),
+            rasterized: 0,
+            size: (0, 0),
+        }
+    }
+
+    /// The stroke rasterized for a `width` x `height` frame, only rasterizing the
+    /// segments added since it was last drawn.
+    fn shapes(&mut self, width: usize, height: usize) -> &[Shape] {
+        // the dot is replaced by the first segment once there is one
+        if self.size != (width, height) || (self.rasterized == 1 && self.points.len() > 1) {
+            self.shapes.clear();
+            self.rasterized = 0;
+            self.size = (width, height);
+        }
+        if self.rasterized < self.points.len() {
+            let (w, h) = (width as f32, height as f32);
+            let line_width = 5. * w.min(h) / 1080.;
+            let points: Vec<_> = self.points.iter().map(|(x, y)| (x * w, y * h)).collect();
+            if let [point] = points[..] {
+                self.shapes.push(Shape::polyline(&[point], line_width));
+            }
+            for segment in points[self.rasterized.saturating_sub(1)..].windows(2) {
+                self.shapes.push(Shape::polyline(segment, line_width));
+            }
+            self.rasterized = self.points.len();
+        }
+        &self.shapes
+    }
+}
+
+/// Keeps the pointers and strokes of all viewers, which are drawn over the video
+/// so every participant sees them.
+pub struct AnnotationManager {
+    viewer_manager: Arc<ViewerManager>,
+    enabled: AtomicBool,
+    font: Font,
+    next_color: AtomicUsize,
+    colors: std::sync::Mutex<HashMap<String, [u8; 3]>>,
+    viewers: std::sync::Mutex<HashMap<String, ViewerAnnotations>>,
+    peers: Mutex<HashMap<String, Sender<String>>>,
+    changed: Notify,
+}
+
+impl AnnotationManager {
+    pub fn new(viewer_manager: Arc<ViewerManager>, enabled: bool) -> Self {
+        Self {
+            viewer_manager,
+            enabled: AtomicBool::new(enabled),
+            font: overlay::load_font(),
+            next_color: AtomicUsize::new(0),
+            colors: std::sync::Mutex::new(HashMap::new()),
+            viewers: std::sync::Mutex::new(HashMap::new()),
+            peers: Mutex::new(HashMap::new()),
+            changed: Notify::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable annotations. While disabled, nothing is drawn and
+    /// annotations sent by viewers are dropped.
+    pub async fn set_enabled(&self, enabled: bool) {
+        if self.enabled.swap(enabled, Ordering::Relaxed) == enabled {
+            return;
+        }
+        if !enabled {
+            self.remove_all();
+        }
+        let peers: Vec<_> = self
+            .peers
+            .lock()
+            .await
+            .iter()
+            .map(|(uuid, peer)| (uuid.clone(), peer.clone()))
+            .collect();
+        for (uuid, peer) in peers {
+            peer.send(self.state_message(&uuid)).await.ok();
+        }
+        self.changed.notify_one();
+    }
+
+    /// Remove the annotations of all viewers.
+    pub async fn clear_annotations(&self) {
+        self.remove_all();
+        let data = serde_json::to_string(&AnnotationMessage::Clear).unwrap();
+        let peers: Vec<_> = self.peers.lock().await.values().cloned().collect();
+        for peer in peers {
+            peer.send(data.clone()).await.ok();
+        }
+        self.changed.notify_one();
+    }
+
+    /// Wait until the annotations change, including when a pointer times out.
+    pub async fn changed(&self) {
+        let expiry = self
+            .viewers
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|v| v.pointer.map(|(_, moved)| moved + POINTER_TIMEOUT))
+            .min();
+        match expiry {
+            Some(expiry) => {
+                tokio::select! {
+                    _ = self.changed.notified() => {}
+                    _ = tokio::time::sleep_until(expiry.into()) => {}
+                }
+            }
+            None => self.changed.notified().await,
+        }
+    }
+
+    /// Draw all annotations onto a frame.
+    pub fn draw(&self, canvas: &mut Canvas) {
+        if !self.is_enabled() {
+            return;
+        }
+        let (width, height) = (canvas.width as f32, canvas.height as f32);
+        let scale = width.min(height) / 1080.;
+        let to_pixels = |(x, y): (f32, f32)| (x * width, y * height);
+        let mut viewers = self.viewers.lock().unwrap();
+        for viewer in viewers.values_mut() {
+            let color = Color::from_rgb(viewer.color);
+            for stroke in &mut viewer.strokes {
+                for shape in stroke.shapes(canvas.width, canvas.height) {
+                    canvas.shape(0., 0., shape, color, 1.);
+                }
+            }
+
+            if matches!(viewer.pointer, Some((_, moved)) if moved.elapsed() > POINTER_TIMEOUT) {
+                viewer.pointer = None;
+            }
+            if let Some((position, _)) = viewer.pointer {
+                let (x, y) = to_pixels(position);
+                if viewer.pointer_shapes.as_ref().map(|(s, _, _)| *s) != Some(scale) {
+                    let outer = Shape::circle(14. * scale);
+                    let inner = Shape::circle(6. * scale);
+                    viewer.pointer_shapes = Some((scale, outer, inner));
+                }
+                let (_, outer, inner) = viewer.pointer_shapes.as_ref().unwrap();
+                canvas.shape(x, y, outer, color, 0.35);
+                canvas.shape(x, y, inner, color, 1.);
+
+                let size = (18. * scale).round().max(10.);
+                if viewer.label.as_ref().map(|l| l.size) != Some(size) {
+                    viewer
+                        .label
+                        .replace(Label::rasterize(&self.font, &viewer.name, size));
+                }
+                let label = viewer.label.as_ref().unwrap();
+                let padding = (4. * scale).round();
+                let (left, top) = (x + 12. * scale, y + 12. * scale);
+                canvas.fill_rect(
+                    left,
+                    top,
+                    label.width as f32 + padding * 2.,
+                    label.height as f32 + padding,
+                    color,
+                );
+                canvas.text(left + padding, top + padding / 2., label, Color::WHITE);
+            }
+        }
+    }
+
+    fn remove_all(&self) {
+        self.viewers.lock().unwrap().clear();
+    }
+
+    fn state_message(&self, uuid: &str) -> String {
+        let color = self
+            .colors
+            .lock()
+            .unwrap()
+            .get(uuid)
+            .copied()
+            .unwrap_or(PALETTE[0]);
+        serde_json::to_string(&AnnotationMessage::State {
+            enabled: self.is_enabled(),
+            color: format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]),
+        })
+        .unwrap()
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for AnnotationManager {
+    type Message = String;
+
+    /// Register a peer, assign it a color and tell it whether annotations are enabled.
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        let color = PALETTE[self.next_color.fetch_add(1, Ordering::Relaxed) % PALETTE.len()];
+        self.colors.lock().unwrap().insert(uuid.clone(), color);
+        sender.send(self.state_message(&uuid)).await.ok();
+        self.peers.lock().await.insert(uuid, sender);
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().await.remove(uuid);
+        self.colors.lock().unwrap().remove(uuid);
+        if self.viewers.lock().unwrap().remove(uuid).is_some() {
+            self.changed.notify_one();
+        }
+    }
+
+    /// Forget all peers and annotations at the end of a session.
+    async fn clear(&self) {
+        self.peers.lock().await.clear();
+        self.colors.lock().unwrap().clear();
+        self.remove_all();
+        self.next_color.store(0, Ordering::Relaxed);
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let message = match serde_json::from_slice::<AnnotationMessage>(&message.data) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Invalid annotation message: {}", e);
+                return;
+            }
+        };
+        if !self.is_enabled() {
+            return;
+        }
+        let known = self.viewers.lock().unwrap().contains_key(uuid);
+        if !known {
+            let viewer = self
+                .viewer_manager
+                .get_viewing_viewers()
+                .await
+                .into_iter()
+                .find(|v| v.uuid == uuid);
+            let color = self.colors.lock().unwrap().get(uuid).copied();
+            let (viewer, color) = match (viewer, color) {
+                (Some(viewer), Some(color)) => (viewer, color),
+                // Only viewers that were let in may annotate.
+                _ => {
+                    warn!("Ignored annotation from unknown viewer {}", uuid);
+                    return;
+                }
+            };
+            let annotations = ViewerAnnotations {
+                color,
+                name: viewer.name,
+                label: None,
+                pointer: None,
+                pointer_shapes: None,
+                strokes: Vec::new(),
+                drawing: false,
+            };
+            self.viewers
+                .lock()
+                .unwrap()
+                .entry(uuid.to_string())
+                .or_insert(annotations);
+        }
+
+        let mut viewers = self.viewers.lock().unwrap();
+        let viewer = match viewers.get_mut(uuid) {
+            Some(viewer) => viewer,
+            None => return,
+        };
+        let clamp = |x: f32, y: f32| (x.clamp(0., 1.), y.clamp(0., 1.));
+        match message {
+            AnnotationMessage::Pointer { x, y } => {
+                viewer.pointer = Some((clamp(x, y), Instant::now()));
+            }
+            AnnotationMessage::HidePointer => viewer.pointer = None,
+            AnnotationMessage::StrokeStart { x, y } => {
+                if viewer.strokes.len() >= MAX_STROKES {
+                    viewer.strokes.remove(0);
+                }
+                viewer.strokes.push(Stroke::new(clamp(x, y)));
+                viewer.drawing = true;
+            }
+            AnnotationMessage::StrokePoint { x, y } => match viewer.strokes.last_mut() {
+                Some(stroke) if viewer.drawing && stroke.points.len() < MAX_STROKE_POINTS => {
+                    stroke.points.push(clamp(x, y));
+                }
+                _ => return,
+            },
+            AnnotationMessage::StrokeEnd => viewer.drawing = false,
+            AnnotationMessage::Clear => viewer.strokes.clear(),
+            AnnotationMessage::State { .. } => {
+                warn!("Unexpected annotation message: {:?}", message);
+                return;
+            }
+        }
+        drop(viewers);
+        self.changed.notify_one();
+    }
+}
@@ -0,0 +1,282 @@
+use fontdue::{Font, FontSettings};
+
+/// A color, with its limited range BT.709 Y'CbCr values for NV12 frames.
+#[derive(Debug, Copy, Clone)]
+pub struct Color {
+    rgb: [u8; 3],
+    y: u8,
+    u: u8,
+    v: u8,
+}
+
+impl Color {
+    pub const WHITE: Color = Color {
+        rgb: [255, 255, 255],
+        y: 235,
+        u: 128,
+        v: 128,
+    };
+
+    pub fn from_rgb(rgb: [u8; 3]) -> Self {
+        let (r, g, b) = (rgb[0] as f32, rgb[1] as f32, rgb[2] as f32);
+        Self {
+            rgb,
+            y: (16. + 0.1826 * r + 0.6142 * g + 0.0620 * b).round() as u8,
+            u: (128. - 0.1006 * r - 0.3386 * g + 0.4392 * b).round() as u8,
+            v: (128. + 0.4392 * r - 0.3989 * g - 0.0403 * b).round() as u8,
+        }
+    }
+}
+
+/// A rasterized text label, as coverage from 0 to 255 per pixel.
+pub struct Label {
+    pub size: f32,
+    pub width: usize,
+    pub height: usize,
+    coverage: Vec<u8>,
+}
+
+impl Label {
+    pub fn rasterize(font: &Font, text: &str, size: f32) -> Self {
+        let line = font
+            .horizontal_line_metrics(size)
+            .map(|m| (m.ascent, m.descent))
+            .unwrap_or((size, 0.));
+        let glyphs: Vec<_> = text.chars().map(|c| font.rasterize(c, size)).collect();
+        let width = glyphs
+            .iter()
+            .map(|(metrics, _)| metrics.advance_width)
+            .sum::<f32>()
+            .ceil() as usize;
+        let height = (line.0 - line.1).ceil() as usize;
+
+        let mut coverage = vec![0u8; width * height];
+        let mut pen = 0f32;
+        for (metrics, bitmap) in glyphs {
+            let left = pen.round() as i32 + metrics.xmin;
+            let top = line.0.round() as i32 - metrics.height as i32 - metrics.ymin;
+            for row in 0..metrics.height {
+                for col in 0..metrics.width {
+                    let (x, y) = (left + col as i32, top + row as i32);
+                    if x < 0 || y < 0 || x as usize >= width || y as usize >= height {
+                        continue;
+                    }
+                    let target = &mut coverage[y as usize * width + x as usize];
+                    *target = (*target).max(bitmap[row * metrics.width + col]);
+                }
+            }
+            pen += metrics.advance_width;
+        }
+
+        Self {
+            size,
+            width,
+            height,
+            coverage,
+        }
+    }
+}
+
+/// A shape rasterized once as coverage from 0 to 255 per pixel, so drawing it on
+/// every frame is only blending. `left` and `top` are relative to where it is drawn.
+pub struct Shape {
+    left: i64,
+    top: i64,
+    width: usize,
+    height: usize,
+    coverage: Vec<u8>,
+}
+
+impl Shape {
+    /// A circle centered on (0, 0).
+    pub fn circle(radius: f32) -> Self {
+        Self::polyline(&[(0., 0.)], radius * 2.)
+    }
+
+    /// A line through `points` with round joins and caps, a dot for a single point.
+    pub fn polyline(points: &[(f32, f32)], width: f32) -> Self {
+        let radius = width / 2.;
+        let min_x = points.iter().map(|p| p.0).fold(f32::MAX, f32::min);
+        let min_y = points.iter().map(|p| p.1).fold(f32::MAX, f32::min);
+        let max_x = points.iter().map(|p| p.0).fold(f32::MIN, f32::max);
+        let max_y = points.iter().map(|p| p.1).fold(f32::MIN, f32::max);
+        let left = (min_x - radius).floor() as i64;
+        let top = (min_y - radius).floor() as i64;
+        let right = (max_x + radius).ceil() as i64 + 1;
+        let bottom = (max_y + radius).ceil() as i64 + 1;
+        let (width, height) = (
+            (right - left).max(0) as usize,
+            (bottom - top).max(0) as usize,
+        );
+
+        let mut coverage = vec![0u8; width * height];
+        let segments: Vec<_> = match points {
+            [point] => vec![(*point, *point)],
+            _ => points.windows(2).map(|s| (s[0], s[1])).collect(),
+        };
+        for (from, to) in segments {
+            let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+            let length_squared = (dx * dx + dy * dy).max(f32::EPSILON);
+            // only the pixels around the segment can be covered by it
+            let x0 = ((from.0.min(to.0) - radius).floor() as i64 - left).max(0) as usize;
+            let y0 = ((from.1.min(to.1) - radius).floor() as i64 - top).max(0) as usize;
+            let x1 = (((from.0.max(to.0) + radius).ceil() as i64 + 1 - left) as usize).min(width);
+            let y1 = (((from.1.max(to.1) + radius).ceil() as i64 + 1 - top) as usize).min(height);
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let px = (x as i64 + left) as f32 - from.0;
+                    let py = (y as i64 + top) as f32 - from.1;
+                    let t = ((px * dx + py * dy) / length_squared).clamp(0., 1.);
+                    let distance = ((px - t * dx).powi(2) + (py - t * dy).powi(2)).sqrt();
+                    let value = ((radius - distance + 0.5).clamp(0., 1.) * 255.) as u8;
+                    let target = &mut coverage[y * width + x];
+                    *target = (*target).max(value);
+                }
+            }
+        }
+
+        Self {
+            left,
+            top,
+            width,
+            height,
+            coverage,
+        }
+    }
+}
+
+pub fn load_font() -> Font {
+    Font::from_bytes(
+        include_bytes!("../../resources/Barlow-Bold.ttf") as &[u8],
+        FontSettings::default(),
+    )
+    .unwrap()
+}
+
+/// The pixels of a frame to draw on.
+pub enum Pixels<'a> {
+    /// The luma and interleaved chroma planes of an NV12 frame.
+    Nv12 {
+        luma: &'a mut [u8],
+        chroma: &'a mut [u8],
+    },
+    /// Packed 4 byte BGRA pixels.
+    Bgra(&'a mut [u8]),
+}
+
+/// A frame to draw on.
+pub struct Canvas<'a> {
+    pub pixels: Pixels<'a>,
+    /// Bytes per row, of both planes for NV12.
+    pub stride: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl<'a> Canvas<'a> {
+    /// Blend `color` over the pixel at (x, y). NV12 chroma is shared by 2x2 pixels, so
+    /// it is only blended for the top left pixel of each block.
+    fn blend(&mut self, x: usize, y: usize, color: Color, alpha: f32) {
+        if x >= self.width || y >= self.height || alpha <= 0. {
+            return;
+        }
+        let mix = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * alpha) as u8;
+        match &mut self.pixels {
+            Pixels::Nv12 { luma, chroma } => {
+                let luma = &mut luma[y * self.stride + x];
+                *luma = mix(*luma, color.y);
+                if x % 2 == 0 && y % 2 == 0 {
+                    let index = y / 2 * self.stride + x;
+                    if index + 1 < chroma.len() {
+                        chroma[index] = mix(chroma[index], color.u);
+                        chroma[index + 1] = mix(chroma[index + 1], color.v);
+                    }
+                }
+            }
+            Pixels::Bgra(pixels) => {
+                let index = y * self.stride + x * 4;
+                if index + 2 < pixels.len() {
+                    for (channel, value) in [2, 1, 0].into_iter().zip(color.rgb) {
+                        pixels[index + channel] = mix(pixels[index + channel], value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clamp a rectangle to the canvas, returning its pixel ranges.
+    fn clip(
+        &self,
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+    ) -> (std::ops::Range<usize>, std::ops::Range<usize>) {
+        let x0 = left.floor().max(0.) as usize;
+        let y0 = top.floor().max(0.) as usize;
+        let x1 = (right.ceil().max(0.) as usize).min(self.width);
+        let y1 = (bottom.ceil().max(0.) as usize).min(self.height);
+        (x0..x1.max(x0), y0..y1.max(y0))
+    }
+
+    /// Draw `shape` offset by (x, y).
+    pub fn shape(&mut self, x: f32, y: f32, shape: &Shape, color: Color, alpha: f32) {
+        let (x, y) = (x.round() as i64 + shape.left, y.round() as i64 + shape.top);
+        self.coverage(
+            x,
+            y,
+            shape.width,
+            shape.height,
+            &shape.coverage,
+            color,
+            alpha,
+        );
+    }
+
+    pub fn fill_rect(&mut self, left: f32, top: f32, width: f32, height: f32, color: Color) {
+        let (xs, ys) = self.clip(left, top, left + width, top + height);
+        for y in ys {
+            for x in xs.clone() {
+                self.blend(x, y, color, 1.);
+            }
+        }
+    }
+
+    /// Draw `label` with its top left corner at (left, top).
+    pub fn text(&mut self, left: f32, top: f32, label: &Label, color: Color) {
+        let (left, top) = (left.round() as i64, top.round() as i64);
+        self.coverage(
+            left,
+            top,
+            label.width,
+            label.height,
+            &label.coverage,
+            color,
+            1.,
+        );
+    }
+
+    /// Blend `color` through a coverage bitmap with its top left corner at (left, top).
+    #[allow(clippy::too_many_arguments)]
+    fn coverage(
+        &mut self,
+        left: i64,
+        top: i64,
+        width: usize,
+        height: usize,
+        coverage: &[u8],
+        color: Color,
+        alpha: f32,
+    ) {
+        for row in 0..height {
+            for col in 0..width {
+                let (x, y) = (left + col as i64, top + row as i64);
+                let value = coverage[row * width + col];
+                if x < 0 || y < 0 || value == 0 {
+                    continue;
+                }
+                self.blend(x as usize, y as usize, color, alpha * value as f32 / 255.);
+            }
+        }
+    }
+}
@@ -9,6 +9,7 @@ use tokio::sync::mpsc::Sender;
 use tokio::sync::Mutex;
 
 use crate::output::WebRTCOutput;
+use crate::session_report::SessionRecorder;
 use crate::signaller::{AuthenticationPayload, DeclineReason};
 use crate::Result;
 
@@ -94,16 +95,21 @@ pub struct ViewerManager {
     auth_result_senders: Mutex<HashMap<String, Sender<bool>>>,
     notify_update: Arc<dyn Fn() + Send + Sync>,
     webrtc_output: Mutex<Option<Arc<Mutex<WebRTCOutput>>>>,
+    session_recorder: Arc<SessionRecorder>,
 }
 
 impl ViewerManager {
-    pub fn new(notify_update: Arc<dyn Fn() + Send + Sync>) -> ViewerManager {
+    pub fn new(
+        notify_update: Arc<dyn Fn() + Send + Sync>,
+        session_recorder: Arc<SessionRecorder>,
+    ) -> ViewerManager {
         ViewerManager {
             viewing_viewers: Mutex::new(Vec::new()),
             pending_viewers: Mutex::new(Vec::new()),
             auth_result_senders: Mutex::new(HashMap::new()),
             notify_update,
             webrtc_output: Mutex::new(None),
+            session_recorder,
         }
     }
     pub async fn get_viewing_viewers(&self) -> Vec<ViewerIdentifier> {
@@ -145,6 +151,7 @@ impl ViewerManager {
     }
 
     pub async fn viewer_left(&self, viewer_uuid: &String) {
+        self.session_recorder.viewer_left(viewer_uuid);
         self.viewing_viewers
             .lock()
             .await
@@ -187,6 +194,7 @@ impl Authenticator for ViewerManager {
         self.pending_viewers.lock().await.retain(|v| v.uuid != uuid);
         info!("{} got authentication decision: {}", uuid, decision);
         if decision {
+            self.session_recorder.viewer_joined(&viewer);
             self.viewing_viewers.lock().await.push(viewer);
             None
         } else {
@@ -0,0 +1,80 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// How much of the peak level is kept per encoded frame.
+const PEAK_DECAY: f32 = 0.95;
+/// Weight of the newest frame in the smoothed RMS level.
+const RMS_SMOOTHING: f32 = 0.3;
+
+/// Peak and RMS level of the encoded audio, in linear amplitude from 0 to 1.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct AudioLevel {
+    pub peak: f32,
+    pub rms: f32,
+}
+
+impl AudioLevel {
+    /// The RMS level mapped from -60 dBFS..0 dBFS to 0..1, for displaying in a meter.
+    pub fn meter_value(&self) -> f32 {
+        if self.rms <= 0. {
+            return 0.;
+        }
+        ((20. * self.rms.log10() + 60.) / 60.).clamp(0., 1.)
+    }
+
+    /// Whether the audio is at or close to full scale.
+    pub fn is_clipping(&self) -> bool {
+        self.peak >= 0.99
+    }
+}
+
+/// Runtime controls of the audio capture, shared with the GUI.
+///
+/// Muting replaces the system audio with silence instead of stopping the track, so
+/// viewers keep receiving (or, with DTX, barely receiving) packets and the microphone
+/// stays audible.
+#[derive(Debug, Default)]
+pub struct AudioControl {
+    system_muted: AtomicBool,
+    peak: AtomicU32,
+    rms: AtomicU32,
+}
+
+impl AudioControl {
+    pub fn set_system_muted(&self, muted: bool) {
+        info!("System audio {}", if muted { "muted" } else { "unmuted" });
+        self.system_muted.store(muted, Ordering::Relaxed);
+    }
+
+    pub fn is_system_muted(&self) -> bool {
+        self.system_muted.load(Ordering::Relaxed)
+    }
+
+    pub fn level(&self) -> AudioLevel {
+        AudioLevel {
+            peak: f32::from_bits(self.peak.load(Ordering::Relaxed)),
+            rms: f32::from_bits(self.rms.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Update the level with the next frame of encoded samples.
+    pub(super) fn update_level(&self, samples: &[f32]) {
+        if samples.is_empty() {
+            return;
+        }
+        let last = self.level();
+        let peak = samples.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+        let mean_square = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+        let rms = (RMS_SMOOTHING * mean_square + (1. - RMS_SMOOTHING) * last.rms * last.rms).sqrt();
+        self.peak.store(
+            peak.max(last.peak * PEAK_DECAY).to_bits(),
+            Ordering::Relaxed,
+        );
+        self.rms.store(rms.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Reset the level to silence, e.g. when the capture stops.
+    pub(super) fn reset_level(&self) {
+        self.peak.store(0f32.to_bits(), Ordering::Relaxed);
+        self.rms.store(0f32.to_bits(), Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::capture::audio::resampler::Resampler;
+
+/// The sample rate audio is encoded at.
+pub const OPUS_SAMPLE_RATE: u32 = 48_000;
+/// The number of channels audio is encoded with.
+pub const OPUS_CHANNELS: usize = 2;
+
+/// Gain of the center and surround channels when they are folded into left and right.
+const MINUS_3DB: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Converts interleaved audio of one source into interleaved stereo at
+/// [`OPUS_SAMPLE_RATE`], keeping the resampling state between buffers.
+pub struct Converter {
+    channels: usize,
+    sample_rate: u32,
+    channel_mixer: ChannelMixer,
+    resampler: Resampler,
+}
+
+impl Converter {
+    pub fn new(channels: usize, sample_rate: u32) -> Self {
+        info!(
+            "Converting audio from {} channels at {} Hz",
+            channels, sample_rate
+        );
+        Self {
+            channels,
+            sample_rate,
+            channel_mixer: ChannelMixer::new(channels),
+            resampler: Resampler::new(sample_rate),
+        }
+    }
+
+    /// Whether this converter handles audio in the given format.
+    pub fn accepts(&self, channels: usize, sample_rate: u32) -> bool {
+        self.channels == channels && self.sample_rate == sample_rate
+    }
+
+    pub fn convert(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.resampler.process(&self.channel_mixer.mix(samples))
+    }
+}
+
+/// Mixes interleaved audio of any channel layout down (or up) to stereo.
+///
+/// Layouts follow the WAVE channel order (FL, FR, FC, LFE, BL, BR, SL, SR). The
+/// coefficients of each output channel are normalized so the mix never clips.
+pub struct ChannelMixer {
+    channels: usize,
+    left: Vec<f32>,
+    right: Vec<f32>,
+}
+
+impl ChannelMixer {
+    pub fn new(channels: usize) -> Self {
+        let (left, right) = match channels {
+            1 => (vec![1.], vec![1.]),
+            2 => (vec![1., 0.], vec![0., 1.]),
+            // FL FR FC
+            3 => (vec![1., 0., MINUS_3DB], vec![0., 1., MINUS_3DB]),
+            // FL FR BL BR
+            4 => (vec![1., 0., MINUS_3DB, 0.], vec![0., 1., 0., MINUS_3DB]),
+            // FL FR FC SL SR
+            5 => (
+                vec![1., 0., MINUS_3DB, MINUS_3DB, 0.],
+                vec![0., 1., MINUS_3DB, 0., MINUS_3DB],
+            ),
+            // FL FR FC LFE BL BR
+            6 => (
+                vec![1., 0., MINUS_3DB, 0., MINUS_3DB, 0.],
+                vec![0., 1., MINUS_3DB, 0., 0., MINUS_3DB],
+            ),
+            // FL FR FC LFE BL BR SL SR
+            8 => (
+                vec![1., 0., MINUS_3DB, 0., MINUS_3DB, 0., MINUS_3DB, 0.],
+                vec![0., 1., MINUS_3DB, 0., 0., MINUS_3DB, 0., MINUS_3DB],
+            ),
+            // Unknown layout, alternate the channels between left and right.
+            _ => (
+                (0..channels).map(|i| (i % 2 == 0) as u8 as f32).collect(),
+                (0..channels).map(|i| (i % 2 == 1) as u8 as f32).collect(),
+            ),
+        };
+        Self {
+            channels,
+            left: normalize(left),
+            right: normalize(right),
+        }
+    }
+
+    pub fn mix(&self, input: &[f32]) -> Vec<f32> {
+        if self.channels == OPUS_CHANNELS {
+            return input.to_vec();
+        }
+        let mut ret = Vec::with_capacity(input.len() / self.channels * OPUS_CHANNELS);
+        for frame in input.chunks_exact(self.channels) {
+            let dot = |coefficients: &[f32]| {
+                frame
+                    .iter()
+                    .zip(coefficients)
+                    .map(|(sample, coefficient)| sample * coefficient)
+                    .sum::<f32>()
+            };
+            ret.push(dot(&self.left));
+            ret.push(dot(&self.right));
+        }
+        ret
+    }
+}
+
+fn normalize(coefficients: Vec<f32>) -> Vec<f32> {
+    let sum: f32 = coefficients.iter().sum();
+    if sum <= 1. {
+        return coefficients;
+    }
+    coefficients.into_iter().map(|c| c / sum).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_stereo() {
+        let input = [0.1, -0.2, 0.3, -0.4];
+        assert_eq!(ChannelMixer::new(2).mix(&input), input);
+    }
+
+    #[test]
+    fn copies_mono_to_both_channels() {
+        assert_eq!(
+            ChannelMixer::new(1).mix(&[0.5, -0.25]),
+            [0.5, 0.5, -0.25, -0.25]
+        );
+    }
+
+    #[test]
+    fn folds_the_center_into_both_channels() {
+        // FL FR FC LFE BL BR, center only
+        let mixed = ChannelMixer::new(6).mix(&[0., 0., 1., 0., 0., 0.]);
+        assert_eq!(mixed.len(), 2);
+        assert!(mixed[0] > 0. && mixed[0] == mixed[1]);
+    }
+
+    #[test]
+    fn never_clips() {
+        for channels in 1..=8 {
+            let mixed = ChannelMixer::new(channels).mix(&vec![1.; channels * 10]);
+            assert_eq!(mixed.len(), 20);
+            assert!(
+                mixed.iter().all(|sample| *sample <= 1. + f32::EPSILON),
+                "{} channels: {:?}",
+                channels,
+                mixed
+            );
+        }
+    }
+
+    #[test]
+    fn converts_across_buffers() {
+        let mut converter = Converter::new(1, 24_000);
+        let output: usize = (0..10).map(|_| converter.convert(&[0.; 240]).len()).sum();
+        // up to an input frame behind
+        assert!(output.abs_diff(4800 * OPUS_CHANNELS) <= 3 * OPUS_CHANNELS);
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::anyhow;
+use cpal::traits::{DeviceTrait, HostTrait};
+use cpal::{Device, Host, SampleRate, SupportedStreamConfig};
+
+use crate::capture::audio::convert::OPUS_SAMPLE_RATE;
+use crate::Result;
+
+#[cfg(target_os = "linux")]
+const MONITOR_SUFFIX: &str = ".monitor";
+
+/// Finds the device to capture system audio from.
+///
+/// If `name` is given, the device with that name is used. Otherwise on Windows the
+/// default output device is opened in loopback mode, and elsewhere the default input
+/// device is used.
+pub fn find_system_device(host: &Host, name: Option<&str>) -> Result<Device> {
+    if let Some(name) = name {
+        return find_by_name(host, name).ok_or_else(|| anyhow!("No audio device named {}", name));
+    }
+
+    if cfg!(target_os = "windows") {
+        host.default_output_device()
+    } else {
+        host.default_input_device()
+    }
+    .ok_or_else(|| anyhow!("No default audio device is available"))
+}
+
+/// Finds the PulseAudio/PipeWire monitor source to capture system audio from: the one
+/// named `name` if it is a monitor source, or that of the default sink if no device is
+/// given.
+#[cfg(target_os = "linux")]
+pub fn find_monitor_source(name: Option<&str>) -> Option<String> {
+    match name {
+        Some(name) if name.ends_with(MONITOR_SUFFIX) => Some(name.to_string()),
+        Some(_) => None,
+        None => default_monitor_source(),
+    }
+}
+
+/// Finds the microphone named `name`, or the default input device if it is not given.
+pub fn find_microphone(host: &Host, name: Option<&str>) -> Result<Device> {
+    match name {
+        Some(name) => {
+            find_by_name(host, name).ok_or_else(|| anyhow!("No audio device named {}", name))
+        }
+        None => host
+            .default_input_device()
+            .ok_or_else(|| anyhow!("No default input device is available")),
+    }
+}
+
+/// Get the names of the devices system audio can be captured from.
+pub fn system_devices() -> Vec<String> {
+    #[cfg(target_os = "windows")]
+    return device_names(cpal::default_host().output_devices());
+    #[cfg(target_os = "linux")]
+    return monitor_sources();
+    // System audio is captured along with the screen on macOS.
+    #[cfg(target_os = "macos")]
+    return Vec::new();
+}
+
+/// Get the names of the devices microphone audio can be captured from.
+pub fn microphone_devices() -> Vec<String> {
+    device_names(cpal::default_host().input_devices())
+}
+
+#[allow(dead_code)]
+fn device_names<I: Iterator<Item = Device>>(
+    devices: std::result::Result<I, cpal::DevicesError>,
+) -> Vec<String> {
+    match devices {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            error!("Failed to list audio devices: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Get the config the stream should be opened with, preferring the sample rate audio
+/// is encoded at so that no resampling is needed.
+pub fn stream_config(device: &Device) -> Result<SupportedStreamConfig> {
+    let default_config = if is_input_device(device) {
+        device.default_input_config()?
+    } else {
+        device.default_output_config()?
+    };
+    if default_config.sample_rate().0 == OPUS_SAMPLE_RATE {
+        return Ok(default_config);
+    }
+
+    let supported = if is_input_device(device) {
+        device.supported_input_configs()?.collect::<Vec<_>>()
+    } else {
+        device.supported_output_configs()?.collect::<Vec<_>>()
+    };
+    Ok(supported
+        .into_iter()
+        .filter(|range| range.sample_format() == default_config.sample_format())
+        .find_map(|range| range.try_with_sample_rate(SampleRate(OPUS_SAMPLE_RATE)))
+        .unwrap_or(default_config))
+}
+
+fn is_input_device(device: &Device) -> bool {
+    device
+        .supported_input_configs()
+        .map(|mut configs| configs.next().is_some())
+        .unwrap_or(false)
+}
+
+fn find_by_name(host: &Host, name: &str) -> Option<Device> {
+    host.devices()
+        .ok()?
+        .find(|device| device.name().map(|n| n == name).unwrap_or(false))
+}
+
+#[cfg(target_os = "linux")]
+fn monitor_sources() -> Vec<String> {
+    let output = match std::process::Command::new("pactl")
+        .args(["list", "short", "sources"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => {
+            warn!("Failed to list monitor sources");
+            return Vec::new();
+        }
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .filter(|name| name.ends_with(MONITOR_SUFFIX))
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn default_monitor_source() -> Option<String> {
+    let output = std::process::Command::new("pactl")
+        .arg("get-default-sink")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        warn!("Failed to query the default sink, falling back to the default input");
+        return None;
+    }
+    let sink = String::from_utf8(output.stdout).ok()?;
+    Some(format!("{}{}", sink.trim(), MONITOR_SUFFIX))
+}
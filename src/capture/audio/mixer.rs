@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+
+/// The number of samples the secondary source may run ahead of the primary one before
+/// the primary is considered stalled, and the secondary is output alone (50 ms of
+/// stereo audio). WASAPI loopback delivers nothing while no sound is playing, and a
+/// source may fail to open at all.
+const PRIMARY_STALL_SAMPLES: usize = 48_000 * 2 / 20;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AudioSource {
+    System,
+    Microphone,
+}
+
+/// Mixes two audio sources with independent gains.
+///
+/// The primary source drives the output: whenever it delivers samples, the same
+/// number of samples is taken from the secondary source, which is treated as
+/// silence if it has fallen behind. When the primary source stalls, the secondary
+/// one is output with the primary treated as silence, a little behind so that the
+/// two line up again once the primary resumes.
+pub struct AudioMixer {
+    primary: AudioSource,
+    primary_gain: f32,
+    secondary_gain: f32,
+    secondary_buffer: VecDeque<f32>,
+}
+
+impl AudioMixer {
+    pub fn new(primary: AudioSource, primary_gain: f32, secondary_gain: f32) -> Self {
+        Self {
+            primary,
+            primary_gain,
+            secondary_gain,
+            secondary_buffer: VecDeque::with_capacity(PRIMARY_STALL_SAMPLES * 2),
+        }
+    }
+
+    /// Push interleaved stereo samples from `source`.
+    /// Returns the mixed samples if `source` is the primary source, or if it is the
+    /// secondary source and the primary one has stalled.
+    pub fn push(&mut self, source: AudioSource, samples: &[f32]) -> Option<Vec<f32>> {
+        if source != self.primary {
+            let gain = self.secondary_gain;
+            self.secondary_buffer
+                .extend(samples.iter().map(|sample| sample * gain));
+            if self.secondary_buffer.len() <= PRIMARY_STALL_SAMPLES {
+                return None;
+            }
+            let excess = self.secondary_buffer.len() - PRIMARY_STALL_SAMPLES;
+            let mixed = self
+                .secondary_buffer
+                .drain(..excess)
+                .map(|sample| sample.clamp(-1., 1.))
+                .collect();
+            return Some(mixed);
+        }
+
+        let mut mixed: Vec<f32> = samples
+            .iter()
+            .map(|sample| sample * self.primary_gain)
+            .collect();
+        let available = mixed.len().min(self.secondary_buffer.len());
+        for (sample, secondary) in mixed
+            .iter_mut()
+            .zip(self.secondary_buffer.drain(..available))
+        {
+            *sample += secondary;
+        }
+        mixed
+            .iter_mut()
+            .for_each(|sample| *sample = sample.clamp(-1., 1.));
+        Some(mixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixes_the_secondary_source_into_the_primary_one() {
+        let mut mixer = AudioMixer::new(AudioSource::System, 1., 0.5);
+        assert_eq!(mixer.push(AudioSource::Microphone, &[0.4, 0.4]), None);
+        assert_eq!(
+            mixer.push(AudioSource::System, &[0.1, 0.1, 0.1, 0.1]),
+            Some(vec![0.3, 0.3, 0.1, 0.1])
+        );
+    }
+
+    #[test]
+    fn clamps_the_mix() {
+        let mut mixer = AudioMixer::new(AudioSource::System, 1., 1.);
+        mixer.push(AudioSource::Microphone, &[0.8, -0.8]);
+        assert_eq!(
+            mixer.push(AudioSource::System, &[0.8, -0.8]),
+            Some(vec![1., -1.])
+        );
+    }
+
+    #[test]
+    fn outputs_the_secondary_source_while_the_primary_one_stalls() {
+        let mut mixer = AudioMixer::new(AudioSource::System, 1., 1.);
+        let buffer = vec![0.25; 960];
+        let mut output = Vec::new();
+        for _ in 0..10 {
+            if let Some(mixed) = mixer.push(AudioSource::Microphone, &buffer) {
+                output.extend(mixed);
+            }
+        }
+        assert_eq!(output.len(), 10 * 960 - PRIMARY_STALL_SAMPLES);
+        assert!(output.iter().all(|sample| *sample == 0.25));
+
+        // what is still buffered lines up with the primary source once it resumes
+        let mixed = mixer.push(AudioSource::System, &buffer).unwrap();
+        assert!(mixed.iter().all(|sample| *sample == 0.5));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
 use std::thread;
@@ -8,155 +9,370 @@ use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut, ChannelLayout};
 use ac_ffmpeg::codec::Encoder;
 use anyhow::anyhow;
 use bytes::Bytes;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::SampleFormat;
+use cpal::traits::{DeviceTrait, StreamTrait};
+use cpal::{
+    BufferSize, Device, FromSample, Sample, SampleFormat, SizedSample, StreamConfig,
+    SupportedBufferSize,
+};
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
+use crate::capture::audio::convert::{Converter, OPUS_CHANNELS, OPUS_SAMPLE_RATE};
+use crate::capture::audio::rechunker::Rechunker;
+use crate::config::{AudioConfig, OPUS_FRAME_DURATIONS};
 use crate::output::OutputSink;
 use crate::Result;
 
+pub use control::{AudioControl, AudioLevel};
+pub use device::{microphone_devices, system_devices};
+pub use mixer::AudioSource;
+
+mod control;
+mod convert;
+mod device;
+mod mixer;
+#[cfg(target_os = "linux")]
+mod pulse;
+mod rechunker;
+mod resampler;
+
+/// The number of mixed samples that may be waiting for a complete frame before the
+/// oldest ones are dropped (1 s of stereo audio).
+const MAX_PENDING_SAMPLES: usize = OPUS_SAMPLE_RATE as usize * OPUS_CHANNELS;
+
+/// Whether system audio is provided by the screen capture instead of an audio device.
+const SYSTEM_AUDIO_FROM_SCREEN_CAPTURE: bool = cfg!(target_os = "macos");
+/// Whether the screen capture starts capturing system audio from an audio device when
+/// it starts, instead of it being started along with the microphone.
+const SYSTEM_AUDIO_STARTED_BY_SCREEN_CAPTURE: bool = cfg!(target_os = "windows");
+
+/// Mixes the captured audio sources, encodes them and writes them to the output.
+#[derive(Clone)]
 pub struct AudioCapture {
+    pipeline: Arc<std::sync::Mutex<AudioPipeline>>,
+    captures_system_audio: bool,
+    system_device: Option<String>,
+}
+
+struct AudioPipeline {
     encoder: AudioEncoder,
+    converters: HashMap<AudioSource, Converter>,
+    mixer: mixer::AudioMixer,
+    rechunker: Rechunker,
+    control: Arc<AudioControl>,
     sender: Sender<Bytes>,
+    /// Whether the output stopped taking audio, after which it is dropped.
+    closed: bool,
 }
 
-fn convert_sample_format(format: SampleFormat) -> ac_ffmpeg::codec::audio::SampleFormat {
-    get_sample_format(match format {
-        SampleFormat::F32 => "flt",
-        SampleFormat::I16 => "s16",
-        SampleFormat::I32 => "s32",
-        _ => {
-            panic!("Unsupported sample format: {:?}", format);
+impl AudioPipeline {
+    fn push(&mut self, source: AudioSource, samples: &[f32], channels: usize, sample_rate: u32) {
+        if self.closed {
+            return;
+        }
+        // Sources may change their format, e.g. when the default device is switched.
+        let converter = self
+            .converters
+            .entry(source)
+            .and_modify(|converter| {
+                if !converter.accepts(channels, sample_rate) {
+                    *converter = Converter::new(channels, sample_rate);
+                }
+            })
+            .or_insert_with(|| Converter::new(channels, sample_rate));
+        let mut samples = converter.convert(samples);
+        if source == AudioSource::System && self.control.is_system_muted() {
+            samples.fill(0.);
         }
-    })
-}
 
-#[allow(dead_code)]
-impl AudioCapture {
-    fn write_input_data<T>(&mut self, input: &[T])
-    where
-        T: cpal::Sample,
-    {
-        let sample_size = self.encoder.samples_per_frame().unwrap();
+        let mixed = match self.mixer.push(source, &samples) {
+            Some(mixed) => mixed,
+            None => return,
+        };
+        self.rechunker.push(&mixed);
 
-        let mut frame = AudioFrameMut::silence(
-            self.encoder.codec_parameters().channel_layout(),
-            self.encoder.codec_parameters().sample_format(),
-            self.encoder.codec_parameters().sample_rate(),
-            sample_size,
-        );
+        let sample_size = self.encoder.samples_per_frame().unwrap();
+        while let Some(pending) = self.rechunker.pop_frame() {
+            self.control.update_level(&pending);
+            let mut frame = AudioFrameMut::silence(
+                self.encoder.codec_parameters().channel_layout(),
+                self.encoder.codec_parameters().sample_format(),
+                self.encoder.codec_parameters().sample_rate(),
+                sample_size,
+            );
 
-        let plane = &mut frame.planes_mut()[0];
-        let data = plane.data_mut();
-        let samples: &mut [T] = unsafe {
-            std::slice::from_raw_parts_mut(
-                data.as_mut_ptr() as *mut T,
-                data.len() / std::mem::size_of::<T>(),
-            )
-        };
+            let plane = &mut frame.planes_mut()[0];
+            let data = plane.data_mut();
+            let samples: &mut [f32] = unsafe {
+                std::slice::from_raw_parts_mut(
+                    data.as_mut_ptr() as *mut f32,
+                    data.len() / std::mem::size_of::<f32>(),
+                )
+            };
 
-        // copy from input to ffmpeg buffer
-        samples[..input.len()].copy_from_slice(input);
+            // copy from the pending samples to ffmpeg buffer
+            samples[..pending.len()].copy_from_slice(&pending);
 
-        self.encoder.push(frame.freeze()).unwrap();
+            // this runs on the audio callback thread, which must not panic
+            if let Err(e) = self.encoder.push(frame.freeze()) {
+                error!("Failed to encode audio frame: {}", e);
+                continue;
+            }
 
-        let mut ret = Vec::new();
+            let mut ret = Vec::new();
+            loop {
+                match self.encoder.take() {
+                    Ok(Some(packet)) => ret.extend(packet.data()),
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!("Failed to take encoded audio: {}", e);
+                        break;
+                    }
+                }
+            }
 
-        while let Some(packet) = self.encoder.take().unwrap() {
-            ret.extend(packet.data());
+            if self.sender.send(Bytes::from(ret)).is_err() {
+                info!("Audio output closed, dropping captured audio");
+                self.closed = true;
+                return;
+            }
         }
-
-        self.sender.send(Bytes::from(ret)).unwrap();
     }
+}
 
-    pub fn capture(
+impl AudioCapture {
+    /// Start capturing the sources enabled in `config` and write the encoded mix to
+    /// `output` until `cancel` is triggered. `control` mutes the system audio and
+    /// receives the level of the mix.
+    pub fn start(
         output: Arc<Mutex<dyn OutputSink + Send>>,
+        config: &AudioConfig,
+        control: Arc<AudioControl>,
         cancel: CancellationToken,
-    ) -> Result<()> {
-        let host = cpal::default_host();
-
-        let device = host
-            .default_output_device()
-            .expect("Failed to get default output device");
+    ) -> Result<Self> {
+        let encoder = build_encoder(config)?;
+        let frame_duration = Duration::from_secs_f32(config.frame_duration / 1000.);
+        let frame_len = encoder.samples_per_frame().unwrap() * OPUS_CHANNELS;
 
-        let config = device
-            .default_output_config()
-            .expect("Failed to get default output config");
-
-        info!("Audio config: {:?}", config);
-
-        let encoder = AudioEncoder::builder("libopus")
-            .unwrap()
-            .sample_rate(config.sample_rate().0 as _)
-            .channel_layout(ChannelLayout::from_channels(2).unwrap())
-            .sample_format(convert_sample_format(config.sample_format()))
-            .set_option("frame_duration", "10")
-            .build()
-            .unwrap();
-
-        info!("Begin recording audio");
+        // System audio drives the mix when it is captured, since the microphone is
+        // usually the one with gaps.
+        let mixer = if config.system.enabled {
+            mixer::AudioMixer::new(
+                AudioSource::System,
+                config.system.gain,
+                config.microphone.gain,
+            )
+        } else {
+            mixer::AudioMixer::new(
+                AudioSource::Microphone,
+                config.microphone.gain,
+                config.system.gain,
+            )
+        };
 
         let (sender, receiver) = std::sync::mpsc::channel();
 
+        let task_control = control.clone();
         tokio::spawn(async move {
             loop {
                 let data = receiver.recv().map_or_else(|_| None, Some);
                 if data.is_none() {
                     info!("Audio capture stopped");
+                    task_control.reset_level();
                     break;
                 }
                 let mut output = output.lock().await;
                 output
-                    .write_audio(data.unwrap(), Duration::from_millis(10))
+                    .write_audio(data.unwrap(), frame_duration)
                     .await
                     .unwrap();
             }
         });
 
+        let capture = Self {
+            pipeline: Arc::new(std::sync::Mutex::new(AudioPipeline {
+                encoder,
+                converters: HashMap::new(),
+                mixer,
+                rechunker: Rechunker::new(frame_len, MAX_PENDING_SAMPLES),
+                control,
+                sender,
+                closed: false,
+            })),
+            captures_system_audio: config.system.enabled,
+            system_device: config.system.device.clone(),
+        };
+
+        if config.system.enabled
+            && !SYSTEM_AUDIO_FROM_SCREEN_CAPTURE
+            && !SYSTEM_AUDIO_STARTED_BY_SCREEN_CAPTURE
+        {
+            capture.start_system_audio(cancel.clone());
+        }
+        let host = cpal::default_host();
+        if config.microphone.enabled {
+            match device::find_microphone(&host, config.microphone.device.as_deref()) {
+                Ok(device) => capture.spawn_stream(AudioSource::Microphone, device, cancel),
+                Err(e) => error!("Failed to capture microphone: {}", e),
+            }
+        }
+
+        Ok(capture)
+    }
+
+    /// Whether system audio should be captured, asked by the screen captures that
+    /// provide or start it.
+    #[cfg_attr(not(any(target_os = "windows", target_os = "macos")), allow(dead_code))]
+    pub fn captures_system_audio(&self) -> bool {
+        self.captures_system_audio
+    }
+
+    /// Capture system audio from the configured device until `cancel` is triggered.
+    pub fn start_system_audio(&self, cancel: CancellationToken) {
+        let name = self.system_device.as_deref();
+        #[cfg(target_os = "linux")]
+        if let Some(monitor) = device::find_monitor_source(name) {
+            pulse::spawn_source(self.clone(), AudioSource::System, monitor, cancel);
+            return;
+        }
+        match device::find_system_device(&cpal::default_host(), name) {
+            Ok(device) => self.spawn_stream(AudioSource::System, device, cancel),
+            Err(e) => error!("Failed to capture system audio: {}", e),
+        }
+    }
+
+    /// Push interleaved samples from `source` in any channel layout and at any sample
+    /// rate, converting them to the format they are encoded in.
+    pub fn push<T>(&self, source: AudioSource, samples: &[T], channels: usize, sample_rate: u32)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        if channels == 0 {
+            return;
+        }
+        let samples: Vec<f32> = samples.iter().map(|sample| sample.to_sample()).collect();
+        match self.pipeline.lock() {
+            Ok(mut pipeline) => pipeline.push(source, &samples, channels, sample_rate),
+            Err(e) => error!("Failed to lock audio pipeline: {}", e),
+        }
+    }
+
+    fn spawn_stream(&self, source: AudioSource, device: Device, cancel: CancellationToken) {
+        let capture = self.clone();
         let handle = tokio::runtime::Handle::current();
         thread::spawn(move || {
-            let mut capturer = AudioCapture { encoder, sender };
-            let err_fn = |err| error!("an error occurred on audio stream: {}", err);
-
-            let stream = match config.sample_format() {
-                SampleFormat::I8 => device.build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| capturer.write_input_data::<i8>(data),
-                    err_fn,
-                    None,
-                )?,
-                SampleFormat::I16 => device.build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| capturer.write_input_data::<i16>(data),
-                    err_fn,
-                    None,
-                )?,
-                SampleFormat::I32 => device.build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| capturer.write_input_data::<i32>(data),
-                    err_fn,
-                    None,
-                )?,
-                SampleFormat::F32 => device.build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| capturer.write_input_data::<f32>(data),
-                    err_fn,
-                    None,
-                )?,
-                _ => {
-                    return Err(anyhow!("unsupported sample format"));
-                }
-            };
+            if let Err(e) = Self::run_stream(source, device, capture, handle, cancel) {
+                error!("Failed to capture {:?} audio: {}", source, e);
+            }
+        });
+    }
+
+    /// Capture from `device` until `cancel` is triggered.
+    fn run_stream(
+        source: AudioSource,
+        device: Device,
+        capture: AudioCapture,
+        handle: tokio::runtime::Handle,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let config = device::stream_config(&device)?;
+        info!(
+            "{:?} audio device: {}, config: {:?}",
+            source,
+            device.name().unwrap_or_default(),
+            config
+        );
 
-            stream.play()?;
+        let sample_rate = config.sample_rate().0;
+        let sample_format = config.sample_format();
+        // Ask for 10ms callbacks to keep latency low where the backend allows it,
+        // buffers of any size are rechunked into frames anyway.
+        let frame_size = sample_rate / 100;
+        let buffer_size = match config.buffer_size() {
+            SupportedBufferSize::Range { min, max } if (*min..=*max).contains(&frame_size) => {
+                BufferSize::Fixed(frame_size)
+            }
+            _ => BufferSize::Default,
+        };
+        let config = StreamConfig {
+            buffer_size,
+            ..config.config()
+        };
 
-            tokio::task::block_in_place(move || {
-                handle.block_on(async move { cancel.cancelled().await });
-            });
-            stream.pause()?;
-            Ok(())
+        let stream = match sample_format {
+            SampleFormat::I8 => build_stream::<i8>(&device, &config, source, capture)?,
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, source, capture)?,
+            SampleFormat::I32 => build_stream::<i32>(&device, &config, source, capture)?,
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, source, capture)?,
+            _ => {
+                return Err(anyhow!("unsupported sample format"));
+            }
+        };
+
+        stream.play()?;
+
+        tokio::task::block_in_place(move || {
+            handle.block_on(async move { cancel.cancelled().await });
         });
+        stream.pause()?;
         Ok(())
     }
 }
+
+/// Build the Opus encoder with the settings in `config`.
+fn build_encoder(config: &AudioConfig) -> Result<AudioEncoder> {
+    if !OPUS_FRAME_DURATIONS.contains(&config.frame_duration) {
+        return Err(anyhow!(
+            "unsupported audio frame duration {}ms, expected one of {:?}",
+            config.frame_duration,
+            OPUS_FRAME_DURATIONS
+        ));
+    }
+    info!(
+        "Audio encoder: {} bps, {}ms frames, complexity {}, fec {} ({}% loss), dtx {}, {:?}",
+        config.bitrate,
+        config.frame_duration,
+        config.complexity,
+        config.fec,
+        config.packet_loss,
+        config.dtx,
+        config.application
+    );
+
+    Ok(AudioEncoder::builder("libopus")
+        .unwrap()
+        .sample_rate(OPUS_SAMPLE_RATE as _)
+        .channel_layout(ChannelLayout::from_channels(OPUS_CHANNELS as u32).unwrap())
+        .sample_format(get_sample_format("flt"))
+        .set_option("b", config.bitrate)
+        .set_option("frame_duration", config.frame_duration)
+        .set_option("compression_level", config.complexity.min(10))
+        .set_option("application", config.application.as_option())
+        .set_option("fec", config.fec as u8)
+        .set_option("packet_loss", config.packet_loss.min(100))
+        .set_option("dtx", config.dtx as u8)
+        .build()?)
+}
+
+/// Open an input stream on `device` that pushes the captured audio to `capture`.
+fn build_stream<T>(
+    device: &Device,
+    config: &StreamConfig,
+    source: AudioSource,
+    capture: AudioCapture,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+    let err_fn = |err| error!("an error occurred on audio stream: {}", err);
+    Ok(device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| capture.push(source, data, channels, sample_rate),
+        err_fn,
+        None,
+    )?)
+}
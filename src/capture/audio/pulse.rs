@@ -0,0 +1,61 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::audio::convert::{OPUS_CHANNELS, OPUS_SAMPLE_RATE};
+use crate::capture::audio::{AudioCapture, AudioSource};
+use crate::Result;
+
+/// Bytes read at a time, 10ms of 32 bit float audio.
+const CHUNK_SIZE: usize = OPUS_SAMPLE_RATE as usize / 100 * OPUS_CHANNELS * 4;
+
+/// Capture the PulseAudio/PipeWire source named `source` with `parec`, since monitor
+/// sources are not listed by ALSA, pushing it to `capture` as `source_type` until
+/// `cancel` is triggered.
+pub fn spawn_source(
+    capture: AudioCapture,
+    source_type: AudioSource,
+    source: String,
+    cancel: CancellationToken,
+) {
+    let handle = tokio::runtime::Handle::current();
+    thread::spawn(move || {
+        let result = (|| -> Result<()> {
+            let mut child = Command::new("parec")
+                .arg(format!("--device={}", source))
+                .arg(format!("--rate={}", OPUS_SAMPLE_RATE))
+                .arg(format!("--channels={}", OPUS_CHANNELS))
+                .args(["--format=float32le", "--raw", "--latency-msec=10"])
+                .stdout(Stdio::piped())
+                .spawn()?;
+            info!("{:?} audio source: {}", source_type, source);
+
+            let mut stdout = child.stdout.take().unwrap();
+            // reading blocks until the process is killed
+            let reader = thread::spawn(move || {
+                let mut buffer = [0u8; CHUNK_SIZE];
+                while stdout.read_exact(&mut buffer).is_ok() {
+                    let samples: Vec<f32> = buffer
+                        .chunks_exact(4)
+                        .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                        .collect();
+                    capture.push(source_type, &samples, OPUS_CHANNELS, OPUS_SAMPLE_RATE);
+                }
+            });
+
+            handle.block_on(cancel.cancelled());
+            child.kill().ok();
+            child.wait()?;
+            reader.join().ok();
+            Ok(())
+        })();
+        if let Err(e) = result {
+            error!(
+                "Failed to capture {:?} audio from {}: {}",
+                source_type, source, e
+            );
+        }
+    });
+}
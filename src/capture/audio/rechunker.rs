@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How often dropped samples are logged at most, as overflows tend to come in bursts.
+const OVERFLOW_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Buffers samples in a bounded ring buffer and splits them into frames of a fixed
+/// length, independent of the size of the buffers they arrive in.
+pub struct Rechunker {
+    buffer: VecDeque<f32>,
+    frame_len: usize,
+    capacity: usize,
+    /// Samples dropped since the last overflow was logged.
+    dropped: usize,
+    last_logged: Option<Instant>,
+}
+
+impl Rechunker {
+    pub fn new(frame_len: usize, capacity: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(capacity),
+            frame_len,
+            capacity,
+            dropped: 0,
+            last_logged: None,
+        }
+    }
+
+    /// Append samples, dropping the oldest ones if the buffer is full.
+    pub fn push(&mut self, samples: &[f32]) {
+        self.buffer.extend(samples);
+        if self.buffer.len() > self.capacity {
+            let excess = self.buffer.len() - self.capacity;
+            self.buffer.drain(..excess);
+            self.dropped += excess;
+            let now = Instant::now();
+            let log_due = match self.last_logged {
+                Some(last) => now - last >= OVERFLOW_LOG_INTERVAL,
+                None => true,
+            };
+            if log_due {
+                warn!("Audio buffer overflow, dropped {} samples", self.dropped);
+                self.dropped = 0;
+                self.last_logged = Some(now);
+            }
+        }
+    }
+
+    /// Take the next complete frame, if there is one.
+    pub fn pop_frame(&mut self) -> Option<Vec<f32>> {
+        if self.buffer.len() < self.frame_len {
+            return None;
+        }
+        Some(self.buffer.drain(..self.frame_len).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_buffers_into_frames() {
+        let mut rechunker = Rechunker::new(4, 100);
+        rechunker.push(&[0., 1., 2.]);
+        assert_eq!(rechunker.pop_frame(), None);
+        rechunker.push(&[3., 4., 5., 6., 7., 8., 9.]);
+        assert_eq!(rechunker.pop_frame(), Some(vec![0., 1., 2., 3.]));
+        assert_eq!(rechunker.pop_frame(), Some(vec![4., 5., 6., 7.]));
+        assert_eq!(rechunker.pop_frame(), None);
+        rechunker.push(&[10., 11.]);
+        assert_eq!(rechunker.pop_frame(), Some(vec![8., 9., 10., 11.]));
+    }
+
+    #[test]
+    fn drops_the_oldest_samples_when_full() {
+        let mut rechunker = Rechunker::new(2, 4);
+        rechunker.push(&[0., 1., 2., 3., 4., 5.]);
+        assert_eq!(rechunker.pop_frame(), Some(vec![2., 3.]));
+        assert_eq!(rechunker.pop_frame(), Some(vec![4., 5.]));
+        assert_eq!(rechunker.pop_frame(), None);
+    }
+}
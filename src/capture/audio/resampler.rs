@@ -0,0 +1,226 @@
+use crate::capture::audio::convert::{OPUS_CHANNELS, OPUS_SAMPLE_RATE};
+
+/// Taps of the low-pass filter applied before downsampling.
+const LOW_PASS_TAPS: usize = 64;
+/// Cutoff of the low-pass filter, relative to the output Nyquist frequency.
+const LOW_PASS_CUTOFF: f64 = 0.9;
+
+/// Resamples interleaved stereo audio to [`OPUS_SAMPLE_RATE`] with linear interpolation.
+///
+/// The interpolation position and the last input frame are carried over between
+/// calls, so consecutive buffers join without gaps or clicks regardless of their size.
+/// When downsampling, the input is low-passed first so that frequencies above the
+/// output Nyquist frequency do not alias.
+pub struct Resampler {
+    /// Input frames advanced per output frame.
+    step: f64,
+    /// Position of the next output frame, relative to the first frame of the next
+    /// input buffer. `-1` refers to the last frame of the previous buffer.
+    position: f64,
+    last_frame: [f32; OPUS_CHANNELS],
+    low_pass: Option<LowPass>,
+}
+
+impl Resampler {
+    pub fn new(sample_rate: u32) -> Self {
+        let step = sample_rate as f64 / OPUS_SAMPLE_RATE as f64;
+        Self {
+            step,
+            position: 0.,
+            last_frame: [0.; OPUS_CHANNELS],
+            low_pass: (step > 1.).then(|| LowPass::new(LOW_PASS_CUTOFF / step)),
+        }
+    }
+
+    pub fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        if self.step == 1. {
+            return input.to_vec();
+        }
+        let frames = input.len() / OPUS_CHANNELS;
+        if frames == 0 {
+            return Vec::new();
+        }
+        let filtered;
+        let input = match &mut self.low_pass {
+            Some(low_pass) => {
+                filtered = low_pass.process(&input[..frames * OPUS_CHANNELS]);
+                &filtered[..]
+            }
+            None => input,
+        };
+
+        let frame_at = |index: isize| -> &[f32] {
+            if index < 0 {
+                &self.last_frame
+            } else {
+                let index = index as usize * OPUS_CHANNELS;
+                &input[index..index + OPUS_CHANNELS]
+            }
+        };
+
+        let mut ret =
+            Vec::with_capacity(((frames as f64 / self.step) as usize + 1) * OPUS_CHANNELS);
+        let mut position = self.position;
+        while position < (frames - 1) as f64 {
+            let index = position.floor();
+            let fraction = (position - index) as f32;
+            let a = frame_at(index as isize);
+            let b = frame_at(index as isize + 1);
+            for channel in 0..OPUS_CHANNELS {
+                ret.push(a[channel] + (b[channel] - a[channel]) * fraction);
+            }
+            position += self.step;
+        }
+
+        self.position = position - frames as f64;
+        self.last_frame
+            .copy_from_slice(&input[(frames - 1) * OPUS_CHANNELS..frames * OPUS_CHANNELS]);
+        ret
+    }
+}
+
+/// A windowed-sinc FIR low-pass filter over interleaved stereo audio, keeping the
+/// last input frames between buffers.
+struct LowPass {
+    taps: Vec<f32>,
+    /// The last `taps.len() - 1` input frames, interleaved.
+    history: Vec<f32>,
+}
+
+impl LowPass {
+    /// `cutoff` is relative to the input Nyquist frequency.
+    fn new(cutoff: f64) -> Self {
+        let center = (LOW_PASS_TAPS - 1) as f64 / 2.;
+        let taps: Vec<f64> = (0..LOW_PASS_TAPS)
+            .map(|i| {
+                let x = i as f64 - center;
+                let sinc = if x == 0. {
+                    cutoff
+                } else {
+                    (std::f64::consts::PI * cutoff * x).sin() / (std::f64::consts::PI * x)
+                };
+                // Blackman window
+                let phase = 2. * std::f64::consts::PI * i as f64 / (LOW_PASS_TAPS - 1) as f64;
+                sinc * (0.42 - 0.5 * phase.cos() + 0.08 * (2. * phase).cos())
+            })
+            .collect();
+        // unity gain at DC
+        let sum: f64 = taps.iter().sum();
+        Self {
+            taps: taps.into_iter().map(|tap| (tap / sum) as f32).collect(),
+            history: vec![0.; (LOW_PASS_TAPS - 1) * OPUS_CHANNELS],
+        }
+    }
+
+    fn process(&mut self, input: &[f32]) -> Vec<f32> {
+        let mut samples = std::mem::take(&mut self.history);
+        samples.extend_from_slice(input);
+        let frames = samples.len() / OPUS_CHANNELS - (self.taps.len() - 1);
+        let mut ret = Vec::with_capacity(frames * OPUS_CHANNELS);
+        for frame in 0..frames {
+            for channel in 0..OPUS_CHANNELS {
+                let sum = self
+                    .taps
+                    .iter()
+                    .enumerate()
+                    .map(|(i, tap)| tap * samples[(frame + i) * OPUS_CHANNELS + channel])
+                    .sum();
+                ret.push(sum);
+            }
+        }
+        samples.drain(..frames * OPUS_CHANNELS);
+        self.history = samples;
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Interleaved stereo sine at `frequency`, both channels alike.
+    fn sine(frequency: f64, sample_rate: u32, frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let sample = (2. * std::f64::consts::PI * frequency * i as f64 / sample_rate as f64)
+                    .sin() as f32;
+                [sample; OPUS_CHANNELS]
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn passes_through_at_the_output_rate() {
+        let input = sine(440., OPUS_SAMPLE_RATE, 480);
+        assert_eq!(Resampler::new(OPUS_SAMPLE_RATE).process(&input), input);
+    }
+
+    #[test]
+    fn converts_the_number_of_frames() {
+        for sample_rate in [8_000, 44_100, 96_000] {
+            let mut resampler = Resampler::new(sample_rate);
+            let output: usize = (0..100)
+                .map(|_| {
+                    resampler
+                        .process(&vec![0.; sample_rate as usize / 100 * 2])
+                        .len()
+                })
+                .sum();
+            let expected = OPUS_SAMPLE_RATE as usize * OPUS_CHANNELS;
+            // the last input frame is only interpolated from once the next one arrives
+            let lag = (OPUS_SAMPLE_RATE / sample_rate + 1) as usize * OPUS_CHANNELS;
+            assert!(
+                output.abs_diff(expected) <= lag,
+                "{} Hz: {} samples instead of {}",
+                sample_rate,
+                output,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn joins_buffers_of_any_size() {
+        for sample_rate in [44_100, 96_000] {
+            let input = sine(440., sample_rate, 4410);
+            let whole = Resampler::new(sample_rate).process(&input);
+
+            let mut resampler = Resampler::new(sample_rate);
+            let mut split = Vec::new();
+            for chunk in input.chunks(37 * OPUS_CHANNELS) {
+                split.extend(resampler.process(chunk));
+            }
+
+            assert_eq!(whole.len(), split.len());
+            for (a, b) in whole.iter().zip(&split) {
+                assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_the_level_of_audible_frequencies() {
+        for sample_rate in [44_100, 96_000] {
+            let output = Resampler::new(sample_rate).process(&sine(1000., sample_rate, 9600));
+            // skip the filter warming up
+            let level = rms(&output[1000..]);
+            assert!(
+                (level - 0.707).abs() < 0.02,
+                "{} Hz: rms {}",
+                sample_rate,
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn filters_frequencies_above_nyquist_when_downsampling() {
+        let output = Resampler::new(96_000).process(&sine(30_000., 96_000, 9600));
+        let level = rms(&output[1000..]);
+        assert!(level < 0.01, "aliased rms {}", level);
+    }
+}
@@ -1,20 +1,31 @@
+use std::path::Path;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use clap::Parser;
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
+use crate::annotation::AnnotationManager;
 use crate::auth::{ComplexAuthenticator, PasswordAuthenticator, ViewerIdentifier, ViewerManager};
-#[allow(unused_imports)]
-use crate::capture::audio::AudioCapture;
+use crate::capture::audio::{AudioCapture, AudioControl, AudioLevel};
 use crate::capture::display::DisplaySelector;
 use crate::capture::{ScreenCapture, ScreenCaptureImpl};
+use crate::chat::ChatManager;
+use crate::clipboard::ClipboardHandler;
 use crate::config::Config;
+use crate::cursor::CursorManager;
 use crate::encoder;
+use crate::file_transfer::FileTransferManager;
+use crate::frame_trace::FrameTrace;
 use crate::inputs::InputHandler;
-use crate::output::{FileOutput, OutputSink, WebRTCOutput};
-use crate::performance_profiler::PerformanceProfiler;
+use crate::latency::LatencyManager;
+use crate::metrics::Metrics;
+use crate::output::{ChannelHandler, FileOutput, OutputSink, SessionServices, WebRTCOutput};
+use crate::performance_profiler::{PerformanceProfiler, PerformanceSnapshot, PerformanceStats};
+use crate::session_report::SessionRecorder;
 use crate::signaller::{Signaller, WebSocketSignaller};
+use crate::stats::StatsManager;
 
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -34,6 +45,19 @@ pub struct Args {
     /// Disable remote control
     #[arg(long, default_value = "false")]
     disable_control: bool,
+    /// Name of the device to capture system audio from, overrides the config
+    #[arg(long)]
+    audio_device: Option<String>,
+    /// Write the timings of every frame to this file, as a Chrome trace (e.g. for
+    /// Perfetto) or as CSV if it ends with .csv
+    #[arg(long)]
+    trace_frames: Option<String>,
+    /// Record the input of viewers to this file
+    #[arg(long)]
+    record_input: Option<String>,
+    /// Replay input recorded with --record-input once sharing starts
+    #[arg(long)]
+    replay_input: Option<String>,
 }
 
 pub struct Capturer {
@@ -45,19 +69,77 @@ pub struct Capturer {
     capture: Arc<Mutex<ScreenCaptureImpl>>,
     room_password: String,
     viewer_manager: Arc<ViewerManager>,
+    audio_control: Arc<AudioControl>,
+    clipboard_handler: Arc<ClipboardHandler>,
+    file_transfer_manager: Arc<FileTransferManager>,
+    chat_manager: Arc<ChatManager>,
+    annotation_manager: Arc<AnnotationManager>,
+    cursor_manager: Arc<CursorManager>,
+    input_handler: Arc<InputHandler>,
+    latency_manager: Arc<LatencyManager>,
+    stats_manager: Arc<StatsManager>,
+    metrics: Arc<Metrics>,
+    performance_stats: Arc<PerformanceStats>,
+    session_recorder: Arc<SessionRecorder>,
 }
 
 impl Capturer {
     pub fn new(args: Args, config: Config, notify_update: Arc<dyn Fn() + Send + Sync>) -> Self {
+        let session_recorder = Arc::new(SessionRecorder::default());
+        let viewer_manager = Arc::new(ViewerManager::new(
+            notify_update.clone(),
+            session_recorder.clone(),
+        ));
+        let input_handler = Arc::new(InputHandler::new(
+            args.disable_control,
+            &config.input,
+            notify_update.clone(),
+        ));
+        let signaller = Arc::new(Mutex::new(None));
+        let latency_manager = Arc::new(LatencyManager::default());
+        let stats_manager = Arc::new(StatsManager::default());
+        let performance_stats = Arc::new(PerformanceStats::default());
+        let metrics = Arc::new(Metrics::new(
+            performance_stats.clone(),
+            viewer_manager.clone(),
+            signaller.clone(),
+            stats_manager.clone(),
+            latency_manager.clone(),
+        ));
+        if config.metrics.enabled {
+            metrics.serve(config.metrics.port);
+        }
         Self {
             args,
             config: config.clone(),
             shutdown_token_opt: None,
-            signaller: Arc::new(Mutex::new(None)),
+            signaller,
             notify_update: notify_update.clone(),
             capture: Arc::new(Mutex::new(ScreenCaptureImpl::new(config.clone()).unwrap())),
             room_password: "".to_string(),
-            viewer_manager: Arc::new(ViewerManager::new(notify_update)),
+            file_transfer_manager: Arc::new(FileTransferManager::new(
+                config.file_transfer.clone(),
+                viewer_manager.clone(),
+                notify_update.clone(),
+            )),
+            chat_manager: Arc::new(ChatManager::new(
+                viewer_manager.clone(),
+                notify_update.clone(),
+            )),
+            annotation_manager: Arc::new(AnnotationManager::new(
+                viewer_manager.clone(),
+                config.annotations.enabled,
+            )),
+            cursor_manager: Arc::new(CursorManager::new(config.cursor.enabled)),
+            input_handler,
+            latency_manager,
+            stats_manager,
+            metrics,
+            performance_stats,
+            session_recorder,
+            viewer_manager,
+            audio_control: Arc::new(AudioControl::default()),
+            clipboard_handler: Arc::new(ClipboardHandler::new(&config.clipboard)),
         }
     }
 
@@ -65,6 +147,35 @@ impl Capturer {
         self.viewer_manager.clone()
     }
 
+    pub fn get_file_transfer_manager(&self) -> Arc<FileTransferManager> {
+        self.file_transfer_manager.clone()
+    }
+
+    pub fn get_chat_manager(&self) -> Arc<ChatManager> {
+        self.chat_manager.clone()
+    }
+
+    pub fn get_annotation_manager(&self) -> Arc<AnnotationManager> {
+        self.annotation_manager.clone()
+    }
+
+    pub fn get_input_handler(&self) -> Arc<InputHandler> {
+        self.input_handler.clone()
+    }
+
+    pub fn get_latency_manager(&self) -> Arc<LatencyManager> {
+        self.latency_manager.clone()
+    }
+
+    pub fn get_stats_manager(&self) -> Arc<StatsManager> {
+        self.stats_manager.clone()
+    }
+
+    /// The frame rate, bitrate and frame times of the stream.
+    pub fn performance(&self) -> PerformanceSnapshot {
+        self.performance_stats.snapshot()
+    }
+
     pub async fn kick_viewer(&self, id: ViewerIdentifier) -> () {
         match self.signaller.try_lock() {
             Ok(signaller) => {
@@ -78,12 +189,39 @@ impl Capturer {
         }
     }
 
+    /// Mute or unmute the system audio of the running session. Viewers receive
+    /// silence while muted, the microphone is not affected.
+    pub fn set_system_audio_muted(&self, muted: bool) {
+        self.audio_control.set_system_muted(muted);
+    }
+
+    pub fn is_system_audio_muted(&self) -> bool {
+        self.audio_control.is_system_muted()
+    }
+
+    /// The current level of the audio sent to viewers.
+    pub fn audio_level(&self) -> AudioLevel {
+        self.audio_control.level()
+    }
+
+    /// Allow or deny viewers access to the clipboard for the rest of the session.
+    pub fn set_clipboard_enabled(&self, enabled: bool) {
+        self.clipboard_handler.set_enabled(enabled);
+    }
+
+    pub fn is_clipboard_enabled(&self) -> bool {
+        self.clipboard_handler.is_enabled()
+    }
+
     pub fn run(&mut self) {
         let args = self.args.clone();
         let config = self.config.clone();
 
         let shutdown_token = CancellationToken::new();
         self.shutdown_token_opt.replace(shutdown_token.clone());
+        self.audio_control.set_system_muted(false);
+        self.clipboard_handler
+            .set_enabled(self.config.clipboard.enabled);
         self.capture(args, config, shutdown_token.clone());
     }
 
@@ -174,17 +312,48 @@ impl Capturer {
     }
 
     fn capture(&mut self, args: Args, config: Config, shutdown_token: CancellationToken) {
-        let profiler = PerformanceProfiler::new(args.profiler, config.max_fps);
+        self.performance_stats.reset();
+        let mut profiler = PerformanceProfiler::new(
+            args.profiler,
+            config.max_fps,
+            self.performance_stats.clone(),
+        );
+        profiler.set_latency_manager(self.latency_manager.clone());
+        profiler.set_metrics(self.metrics.clone());
+        if let Some(path) = &args.trace_frames {
+            match FrameTrace::create(Path::new(path)) {
+                Ok(trace) => profiler.set_trace(trace),
+                Err(e) => error!("Failed to create frame trace {}: {}", path, e),
+            }
+        }
         let signaller_opt = self.signaller.clone();
         let notify_update = self.notify_update.clone();
         let capture = self.capture.clone();
+        let audio_control = self.audio_control.clone();
+        let clipboard_handler = self.clipboard_handler.clone();
+        let file_transfer_manager = self.file_transfer_manager.clone();
+        let chat_manager = self.chat_manager.clone();
+        let annotation_manager = self.annotation_manager.clone();
+        let annotations_enabled = config.annotations.enabled;
+        let cursor_manager = self.cursor_manager.clone();
+        let input_handler = self.input_handler.clone();
+        let latency_manager = self.latency_manager.clone();
+        let stats_manager = self.stats_manager.clone();
+        let performance_stats = self.performance_stats.clone();
+        let session_recorder = self.session_recorder.clone();
+        session_recorder.start(
+            &config.encoder,
+            performance_stats.clone(),
+            stats_manager.clone(),
+            shutdown_token.clone(),
+        );
 
         let password_auth = Arc::new(PasswordAuthenticator::random().unwrap());
         let viewer_manager = self.viewer_manager.clone();
         self.room_password = password_auth.password();
 
         tokio::spawn(async move {
-            {
+            let forced_keyframes = {
                 let mut capture = capture.lock().await;
                 let signaller_url = config.signaller_url.clone();
                 let signaller = Arc::new(
@@ -203,10 +372,21 @@ impl Capturer {
                 let resolution = capture.display().resolution();
                 let mut encoder =
                     encoder::FfmpegEncoder::new(resolution.0, resolution.1, &config.encoder);
-                let input_handler = Arc::new(InputHandler::new(
-                    args.disable_control,
-                    capture.display().dpi_conversion_factor(),
-                ));
+                annotation_manager.set_enabled(annotations_enabled).await;
+                encoder.set_overlay(annotation_manager.clone());
+                input_handler.set_display(capture.display()).await;
+                if let Some(path) = &args.record_input {
+                    if let Err(e) = input_handler.start_recording(Path::new(path)).await {
+                        error!("Failed to record input to {}: {}", path, e);
+                    }
+                }
+                if let Some(path) = &args.replay_input {
+                    if let Err(e) = input_handler.replay(Path::new(path), shutdown_token.clone()) {
+                        error!("Failed to replay input from {}: {}", path, e);
+                    }
+                }
+                cursor_manager.start(capture.display(), shutdown_token.clone());
+                latency_manager.start(shutdown_token.clone());
 
                 let output: Arc<Mutex<dyn OutputSink + Send>> = if let Some(path) = args.file {
                     Arc::new(Mutex::new(FileOutput::new(&path)))
@@ -218,7 +398,16 @@ impl Capturer {
                             viewer_manager.clone(),
                         ])),
                         &mut encoder.force_idr,
-                        input_handler.clone(),
+                        SessionServices {
+                            input_handler: input_handler.clone(),
+                            clipboard_handler: clipboard_handler.clone(),
+                            file_transfer_manager: file_transfer_manager.clone(),
+                            chat_manager: chat_manager.clone(),
+                            annotation_manager: annotation_manager.clone(),
+                            cursor_manager: cursor_manager.clone(),
+                            latency_manager: latency_manager.clone(),
+                            stats_manager: stats_manager.clone(),
+                        },
                         &config,
                     )
                     .await
@@ -227,14 +416,33 @@ impl Capturer {
                     webrtc
                 };
 
-                #[cfg(target_os = "windows")]
-                AudioCapture::capture(output.clone(), shutdown_token.clone()).unwrap();
+                let mut audio_config = config.audio.clone();
+                if let Some(audio_device) = args.audio_device {
+                    audio_config.system.device = Some(audio_device);
+                }
+                let audio = match AudioCapture::start(
+                    output.clone(),
+                    &audio_config,
+                    audio_control,
+                    shutdown_token.clone(),
+                ) {
+                    Ok(audio) => Some(audio),
+                    Err(e) => {
+                        error!(
+                            "Failed to start audio capture, sharing without audio: {}",
+                            e
+                        );
+                        None
+                    }
+                };
 
+                let forced_keyframes = encoder.forced_keyframes();
                 capture
-                    .start_capture(encoder, output, profiler, shutdown_token.clone())
+                    .start_capture(encoder, audio, output, profiler, shutdown_token.clone())
                     .await
                     .unwrap();
-            }
+                forced_keyframes
+            };
             notify_update(); // Update when capture starts
 
             shutdown_token.cancelled().await;
@@ -246,7 +454,26 @@ impl Capturer {
                 signaller.close().await;
             }
 
+            let report = session_recorder.finish(
+                &performance_stats.snapshot(),
+                forced_keyframes.load(Ordering::Relaxed),
+            );
+            if let Some(report) = report.filter(|_| config.report.enabled) {
+                match report.save(&config.report.dir()) {
+                    Ok(path) => info!("Session report saved to {}", path.display()),
+                    Err(e) => error!("Failed to save the session report: {}", e),
+                }
+            }
+
             viewer_manager.clear().await;
+            file_transfer_manager.clear().await;
+            chat_manager.clear().await;
+            clipboard_handler.clear().await;
+            annotation_manager.clear().await;
+            cursor_manager.clear().await;
+            input_handler.clear().await;
+            latency_manager.clear().await;
+            stats_manager.clear().await;
 
             notify_update(); // Update when capture stops
         });
@@ -1,6 +1,7 @@
 use std::ffi::c_void;
 use std::ops::Deref;
 use std::ptr::null_mut;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Barrier, Once};
 use std::{alloc, mem, slice};
 
@@ -69,9 +70,14 @@ impl CaptureEngine {
         filter: SCContentFilter,
         video_tx: Sender<YUVFrame>,
         audio_tx: Option<Sender<PCMBuffer>>,
+        dropped_frames: Arc<AtomicU64>,
     ) {
         let mut output = StreamOutput(StreamOutput::alloc().init());
-        output.set_on_output_frame(OutputHandler { video_tx, audio_tx });
+        output.set_on_output_frame(OutputHandler {
+            video_tx,
+            audio_tx,
+            dropped_frames,
+        });
 
         let stream = SCStream(SCStream::alloc().initWithFilter_configuration_delegate_(
             filter,
@@ -177,6 +183,8 @@ pub struct StreamOutput(pub id);
 pub struct OutputHandler {
     pub video_tx: Sender<YUVFrame>,
     pub audio_tx: Option<Sender<PCMBuffer>>,
+    /// Incremented when a frame arrives while the previous one is still encoded.
+    pub dropped_frames: Arc<AtomicU64>,
 }
 
 pub struct ErrorHandler {
@@ -262,7 +270,9 @@ extern "C" fn stream_output(this: &mut Object, _cmd: Sel, _stream: id, sample: i
         match of_type as i64 {
             SCStreamOutputType_SCStreamOutputTypeScreen => {
                 if let Some(frame) = create_yuv_frame(sample_buffer_ref) {
-                    handler.video_tx.try_send(frame).unwrap_or(());
+                    if handler.video_tx.try_send(frame).is_err() {
+                        handler.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                    }
                 }
             }
             SCStreamOutputType_SCStreamOutputTypeAudio => {
@@ -57,6 +57,13 @@ impl DisplayInfo for Display {
     fn dpi_conversion_factor(&self) -> f64 {
         self.scale_factor as f64
     }
+
+    fn origin(&self) -> (i32, i32) {
+        unsafe {
+            let frame = self.sc_display.frame();
+            (frame.origin.x as i32, frame.origin.y as i32)
+        }
+    }
 }
 
 unsafe fn try_get_ns_screen(display: SCDisplay) -> Option<NSScreen> {
@@ -1,14 +1,13 @@
 use std::sync::Arc;
 use std::time::Duration;
 
-use ac_ffmpeg::codec::audio::{AudioEncoder, AudioFrameMut, ChannelLayout};
-use ac_ffmpeg::codec::Encoder;
 use async_trait::async_trait;
-use bytes::Bytes;
 use tokio::select;
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
+use crate::annotation;
+use crate::capture::audio::{AudioCapture, AudioSource};
 use crate::capture::display::DisplaySelector;
 use crate::capture::macos::pcm_buffer::PCMBuffer;
 use crate::capture::macos::screen_recorder::ScreenRecorder;
@@ -31,6 +30,7 @@ impl ScreenCapture for MacOSCapture {
 
         let mut recorder = ScreenRecorder::new();
         recorder.set_max_fps(config.max_fps as u8);
+        recorder.set_shows_cursor(!config.cursor.enabled);
         recorder.monitor_available_content();
 
         Ok(Self { config, recorder })
@@ -43,54 +43,28 @@ impl ScreenCapture for MacOSCapture {
     async fn start_capture(
         &mut self,
         mut encoder: FfmpegEncoder,
+        audio: Option<AudioCapture>,
         output: Arc<Mutex<impl OutputSink + Send + ?Sized>>,
         mut profiler: PerformanceProfiler,
         shutdown_token: CancellationToken,
     ) -> Result<()> {
+        self.recorder.set_audio_capture_enabled(
+            audio
+                .as_ref()
+                .map_or(false, |audio| audio.captures_system_audio()),
+        );
+
         let (video_tx, mut video_rx) = tokio::sync::mpsc::channel::<YUVFrame>(1);
         let (audio_tx, mut audio_rx) = tokio::sync::mpsc::channel::<PCMBuffer>(1);
 
-        let output_audio = output.clone();
         let cancel_audio = shutdown_token.clone();
         tokio::spawn(async move {
-            let mut audio_encoder_opt = None;
             loop {
                 select! {
                     Some(pcm_buffer) = audio_rx.recv() => {
-                        let audio_encoder = audio_encoder_opt.get_or_insert_with(|| {
-                            AudioEncoder::builder("libopus")
-                                .unwrap()
-                                .sample_rate(pcm_buffer.sample_rate as _)
-                                .channel_layout(
-                                    ChannelLayout::from_channels(pcm_buffer.channels as u32).unwrap(),
-                                )
-                                .sample_format(pcm_buffer.sample_format())
-                                .set_option("frame_duration", pcm_buffer.frame_duration)
-                                .build()
-                                .unwrap()
-                        });
-                        let mut audio_frame = AudioFrameMut::silence(
-                            audio_encoder.codec_parameters().channel_layout(),
-                            audio_encoder.codec_parameters().sample_format(),
-                            audio_encoder.codec_parameters().sample_rate(),
-                            pcm_buffer.sample_size,
-                        );
-                        pcm_buffer.write_samples_into(&mut audio_frame);
-                        audio_encoder.push(audio_frame.freeze()).unwrap();
-
-                        let mut ret: Vec<u8> = Vec::new();
-                        while let Some(packet) = audio_encoder.take().unwrap() {
-                            ret.extend(packet.data());
+                        if let Some(audio) = &audio {
+                            pcm_buffer.push_into(audio, AudioSource::System);
                         }
-                        output_audio
-                            .lock()
-                            .await
-                            .write_audio(
-                                Bytes::from(ret),
-                                Duration::from_millis(pcm_buffer.frame_duration as u64),
-                            )
-                            .await
-                            .unwrap();
                     }
                     _ = cancel_audio.cancelled() => {
                         break;
@@ -99,25 +73,34 @@ impl ScreenCapture for MacOSCapture {
             }
         });
 
+        let dropped_frames = profiler.dropped_frames();
         let cancel_video = shutdown_token.clone();
         let max_fps = self.config.max_fps;
         tokio::spawn(async move {
             let mut ticker = tokio::time::interval(Duration::from_millis((1000 / max_fps) as u64));
+            let overlay = encoder.overlay();
+            let mut last_frame = None;
             loop {
                 select! {
                     Some(frame) = video_rx.recv() => {
-                        let frame_time = frame.display_time as f64;
-                        profiler.accept_frame(frame_time as i64);
+                        profiler.accept_frame(frame.display_time as i64);
                         profiler.done_preprocessing();
-                        let encoded = encoder
-                            .encode(FrameData::NV12(&frame), frame_time as i64)
-                            .unwrap();
+                        let encoded = encoder.encode(FrameData::NV12(&frame)).unwrap();
                         let encoded_len = encoded.len();
-                        profiler.done_encoding();
+                        profiler.done_encoding(encoder.is_keyframe());
                         output.lock().await.write(encoded).await.unwrap();
                         profiler.done_processing(encoded_len);
+                        last_frame.replace(frame);
                         ticker.tick().await;
                     }
+                    _ = annotation::changed(overlay.as_deref()) => {
+                        // redraw the annotations even if the screen did not change
+                        if let Some(frame) = &last_frame {
+                            let encoded = encoder.encode(FrameData::NV12(frame)).unwrap();
+                            output.lock().await.write(encoded).await.unwrap();
+                            ticker.tick().await;
+                        }
+                    }
                     _ = cancel_video.cancelled() => {
                         break;
                     }
@@ -125,7 +108,7 @@ impl ScreenCapture for MacOSCapture {
             }
         });
 
-        self.recorder.start(video_tx, audio_tx);
+        self.recorder.start(video_tx, audio_tx, dropped_frames);
 
         Ok(())
     }
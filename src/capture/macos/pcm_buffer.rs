@@ -1,9 +1,9 @@
-use ac_ffmpeg::codec::audio::frame::get_sample_format;
-use ac_ffmpeg::codec::audio::{AudioFrameMut, SampleFormat};
 use apple_sys::AVFAudio::{
     AVAudioPCMBuffer, IAVAudioBuffer, IAVAudioFormat, IAVAudioPCMBuffer, PNSObject,
 };
 
+use crate::capture::audio::{AudioCapture, AudioSource};
+
 pub enum PCMData {
     F32(Vec<f32>),
     I16(Vec<i16>),
@@ -93,39 +93,12 @@ impl PCMBuffer {
         }
     }
 
-    pub fn sample_format(&self) -> SampleFormat {
-        get_sample_format(match self.data {
-            PCMData::F32(_) => "flt",
-            PCMData::I16(_) => "s16",
-            PCMData::I32(_) => "s32",
-        })
-    }
-
-    fn get_to_sample_slice<T>(&self, data: &[u8]) -> &mut [T] {
-        unsafe {
-            std::slice::from_raw_parts_mut(
-                data.as_ptr() as *mut T,
-                data.len() / std::mem::size_of::<T>(),
-            )
-        }
-    }
-
-    pub fn write_samples_into(&self, frame: &mut AudioFrameMut) {
-        let plane = &mut frame.planes_mut()[0];
-        let data = plane.data_mut();
+    pub fn push_into(&self, audio: &AudioCapture, source: AudioSource) {
+        let sample_rate = self.sample_rate as u32;
         match &self.data {
-            PCMData::F32(from_samples) => {
-                let to_samples: &mut [f32] = self.get_to_sample_slice(data);
-                to_samples[..from_samples.len()].copy_from_slice(from_samples);
-            }
-            PCMData::I16(from_samples) => {
-                let to_samples: &mut [i16] = self.get_to_sample_slice(data);
-                to_samples[..from_samples.len()].copy_from_slice(from_samples);
-            }
-            PCMData::I32(from_samples) => {
-                let to_samples: &mut [i32] = self.get_to_sample_slice(data);
-                to_samples[..from_samples.len()].copy_from_slice(from_samples);
-            }
+            PCMData::F32(samples) => audio.push(source, samples, self.channels, sample_rate),
+            PCMData::I16(samples) => audio.push(source, samples, self.channels, sample_rate),
+            PCMData::I32(samples) => audio.push(source, samples, self.channels, sample_rate),
         }
     }
 }
@@ -1,6 +1,8 @@
 extern crate libc;
 
 use std::ffi::c_void;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use apple_sys::ScreenCaptureKit::{
@@ -38,6 +40,7 @@ pub struct ScreenRecorder {
     is_app_excluded: bool,
     content_size: CGSize,
     max_fps: u8,
+    shows_cursor: bool,
     available_content: Option<SCShareableContent>,
     available_apps: Vec<SCRunningApplication>,
     available_displays: Vec<Display>,
@@ -77,6 +80,7 @@ impl ScreenRecorder {
                 height: 1.,
             },
             max_fps: 60,
+            shows_cursor: true,
             available_content: None,
             available_apps: Vec::new(),
             available_displays: Vec::new(),
@@ -92,6 +96,14 @@ impl ScreenRecorder {
         self.max_fps = fps;
     }
 
+    pub fn set_shows_cursor(&mut self, shows_cursor: bool) {
+        self.shows_cursor = shows_cursor;
+    }
+
+    pub fn set_audio_capture_enabled(&mut self, enabled: bool) {
+        self.is_audio_capture_enabled = enabled;
+    }
+
     pub fn can_record() -> bool {
         let (tx, rx) = std::sync::mpsc::channel::<bool>();
         unsafe {
@@ -129,6 +141,7 @@ impl ScreenRecorder {
         &mut self,
         video_tx: tokio::sync::mpsc::Sender<YUVFrame>,
         audio_tx: tokio::sync::mpsc::Sender<PCMBuffer>,
+        dropped_frames: Arc<AtomicU64>,
     ) {
         // Exit early if already running.
         if self.is_running {
@@ -153,6 +166,7 @@ impl ScreenRecorder {
                 } else {
                     None
                 },
+                dropped_frames,
             );
         }
     }
@@ -239,8 +253,15 @@ impl ScreenRecorder {
             #[cfg(target_arch = "aarch64")]
             let is_app_audio_excluded = self.is_app_audio_excluded;
 
+            #[cfg(target_arch = "x86_64")]
+            let shows_cursor = if self.shows_cursor { 1 } else { 0 };
+            #[cfg(target_arch = "aarch64")]
+            let shows_cursor = self.shows_cursor;
+
             config.setCapturesAudio_(is_audio_capture_enabled);
             config.setExcludesCurrentProcessAudio_(is_app_audio_excluded);
+            // The cursor is drawn by viewers when it is sent separately.
+            config.setShowsCursor_(shows_cursor);
 
             let (width, height) = self.resolution();
             config.setWidth_(width as _);
@@ -434,6 +455,20 @@ impl DisplayInfo for ScreenRecorder {
             CaptureType::Window => 2.0,
         }
     }
+
+    fn origin(&self) -> (i32, i32) {
+        // the corner of the main display until something is selected
+        match self.capture_type {
+            CaptureType::Display => self
+                .selected_display
+                .as_ref()
+                .map_or((0, 0), |display| display.origin()),
+            CaptureType::Window => self.selected_window.map_or((0, 0), |window| unsafe {
+                let frame = window.frame();
+                (frame.origin.x as i32, frame.origin.y as i32)
+            }),
+        }
+    }
 }
 
 impl DisplaySelector for ScreenRecorder {
@@ -10,9 +10,12 @@ pub trait ScreenCapture {
 
     fn display(&self) -> &dyn DisplayInfo;
 
+    /// Start capturing the display. Platforms where the screen capture also provides
+    /// system audio push it to `audio`, `None` if audio could not be started.
     async fn start_capture(
         &mut self,
         encoder: FfmpegEncoder,
+        audio: Option<AudioCapture>,
         output: Arc<Mutex<impl OutputSink + Send + ?Sized>>,
         profiler: PerformanceProfiler,
         shutdown_token: CancellationToken,
@@ -26,8 +29,12 @@ pub trait DisplayInfo {
     fn resolution(&self) -> (u32, u32);
     /// Get the DPI factor for input handling
     fn dpi_conversion_factor(&self) -> f64;
+    /// Get the position of the top left corner on the desktop, in the coordinates
+    /// the system uses for the cursor (pixels on Windows, points on macOS)
+    fn origin(&self) -> (i32, i32);
 }
 
+use crate::capture::audio::AudioCapture;
 use crate::encoder::FfmpegEncoder;
 use crate::performance_profiler::PerformanceProfiler;
 
@@ -45,7 +52,7 @@ pub use frame::YUVFrame;
 #[cfg(target_os = "macos")]
 pub use macos::MacOSCapture as ScreenCaptureImpl;
 
-mod audio;
+pub mod audio;
 pub mod display;
 mod yuv_convert;
 
@@ -14,7 +14,6 @@ use windows::Win32::Graphics::Gdi::{
 };
 use windows::Win32::System::WinRT::Graphics::Capture::IGraphicsCaptureItemInterop;
 
-use crate::capture::DisplayInfo;
 use crate::result::Result;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -39,6 +38,16 @@ impl Display {
         })
     }
 
+    /// The position of the display on the virtual desktop.
+    pub fn origin(&self) -> (i32, i32) {
+        let mut info = MONITORINFO {
+            cbSize: size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        unsafe { GetMonitorInfoA(self.handle, &mut info) };
+        (info.rcMonitor.left, info.rcMonitor.top)
+    }
+
     pub fn select(&self) -> Result<GraphicsCaptureItem> {
         let interop = windows::core::factory::<GraphicsCaptureItem, IGraphicsCaptureItemInterop>()?;
         Ok(unsafe { interop.CreateForMonitor(self.handle) }?)
@@ -161,15 +170,3 @@ extern "system" fn enum_monitor(monitor: HMONITOR, _: HDC, _: *mut RECT, state:
     }
     true.into()
 }
-
-impl DisplayInfo for GraphicsCaptureItem {
-    fn resolution(&self) -> (u32, u32) {
-        (
-            self.Size().unwrap().Width as u32,
-            self.Size().unwrap().Height as u32,
-        )
-    }
-    fn dpi_conversion_factor(&self) -> f64 {
-        1.0
-    }
-}
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::select;
@@ -10,6 +11,8 @@ use windows::Graphics::Capture::{
 
 use windows::Graphics::DirectX::DirectXPixelFormat;
 
+use crate::annotation;
+use crate::capture::audio::AudioCapture;
 use crate::capture::display::DisplaySelector;
 use crate::capture::wgc::d3d;
 use crate::capture::wgc::display::Display;
@@ -76,12 +79,13 @@ impl ScreenCapture for WGCScreenCapture {
     }
 
     fn display(&self) -> &dyn DisplayInfo {
-        &self.item
+        self
     }
 
     async fn start_capture(
         &mut self,
         mut encoder: FfmpegEncoder,
+        audio: Option<AudioCapture>,
         output: Arc<Mutex<impl OutputSink + Send + ?Sized>>,
         mut profiler: PerformanceProfiler,
         shutdown_token: CancellationToken,
@@ -89,8 +93,16 @@ impl ScreenCapture for WGCScreenCapture {
         let engine = CaptureEngine::new(&self.item);
 
         let session = engine.frame_pool.CreateCaptureSession(&self.item)?;
+        // The cursor is drawn by viewers when it is sent separately.
+        session.SetIsCursorCaptureEnabled(!self.config.cursor.enabled)?;
+        // System audio is captured from the output device in loopback mode, alongside
+        // the screen.
+        if let Some(audio) = audio.filter(|audio| audio.captures_system_audio()) {
+            audio.start_system_audio(shutdown_token.clone());
+        }
 
         let (sender, mut receiver) = tokio::sync::mpsc::channel::<Direct3D11CaptureFrame>(1);
+        let dropped_frames = profiler.dropped_frames();
 
         engine.frame_pool.FrameArrived(&TypedEventHandler::<
             Direct3D11CaptureFramePool,
@@ -99,7 +111,10 @@ impl ScreenCapture for WGCScreenCapture {
             move |frame_pool, _| {
                 let frame_pool = frame_pool.as_ref().unwrap();
                 let frame = frame_pool.TryGetNextFrame()?;
-                let _ = sender.try_send(frame);
+                // the previous frame is still being encoded
+                if sender.try_send(frame).is_err() {
+                    dropped_frames.fetch_add(1, Ordering::Relaxed);
+                }
                 Ok(())
             }
         }))?;
@@ -111,27 +126,35 @@ impl ScreenCapture for WGCScreenCapture {
 
         let mut ticker =
             tokio::time::interval(Duration::from_millis((1000 / self.config.max_fps) as u64));
+        let overlay = encoder.overlay();
 
         tokio::spawn(async move {
+            let mut last_frame = None;
             loop {
                 select! {
                     Some(frame) = receiver.recv() => {
-                        let frame_time = frame.SystemRelativeTime().unwrap().Duration;
                         profiler.accept_frame(frame.SystemRelativeTime().unwrap().Duration);
                         let yuv_frame = {
                             duplicator
                                 .capture(d3d::get_d3d_interface_from_object(&frame.Surface().unwrap()).unwrap()).unwrap()
                         };
                         profiler.done_preprocessing();
-                        let encoded = encoder
-                            .encode(FrameData::NV12(&yuv_frame), frame_time)
-                            .unwrap();
+                        let encoded = encoder.encode(FrameData::NV12(&yuv_frame)).unwrap();
                         let encoded_len = encoded.len();
-                        profiler.done_encoding();
+                        profiler.done_encoding(encoder.is_keyframe());
                         output.lock().await.write(encoded).await.unwrap();
                         profiler.done_processing(encoded_len);
+                        last_frame.replace(yuv_frame);
                         ticker.tick().await;
                     }
+                    _ = annotation::changed(overlay.as_deref()) => {
+                        // redraw the annotations even if the screen did not change
+                        if let Some(frame) = &last_frame {
+                            let encoded = encoder.encode(FrameData::NV12(frame)).unwrap();
+                            output.lock().await.write(encoded).await.unwrap();
+                            ticker.tick().await;
+                        }
+                    }
                     _ = shutdown_token.cancelled() => {
                         break;
                     }
@@ -153,6 +176,21 @@ impl ScreenCapture for WGCScreenCapture {
     }
 }
 
+impl DisplayInfo for WGCScreenCapture {
+    fn resolution(&self) -> (u32, u32) {
+        let size = self.item.Size().unwrap();
+        (size.Width as u32, size.Height as u32)
+    }
+
+    fn dpi_conversion_factor(&self) -> f64 {
+        1.0
+    }
+
+    fn origin(&self) -> (i32, i32) {
+        self.selected_display.origin()
+    }
+}
+
 impl DisplaySelector for WGCScreenCapture {
     type Display = Display;
 
@@ -161,7 +199,8 @@ impl DisplaySelector for WGCScreenCapture {
     }
 
     fn select_display(&mut self, display: &Display) -> Result<()> {
-        self.engine = Some(CaptureEngine::new(&display.select()?));
+        self.item = display.select()?;
+        self.engine = Some(CaptureEngine::new(&self.item));
         self.selected_display = display.clone();
         Ok(())
     }
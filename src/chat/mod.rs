@@ -0,0 +1,172 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+
+use crate::auth::ViewerManager;
+use crate::output::ChannelHandler;
+
+/// Longest chat message accepted, in characters.
+const MAX_MESSAGE_LENGTH: usize = 4096;
+/// Messages kept for the GUI and for viewers joining later, older ones are dropped.
+const MAX_HISTORY: usize = 500;
+/// Name shown for messages sent by the sharer.
+const SHARER_NAME: &str = "Sharer";
+
+/// Messages of the `chat` data channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ChatProtocolMessage {
+    /// A viewer sends a message to everyone.
+    Send { text: String },
+    /// A message sent by someone in the session, `timestamp` in milliseconds since
+    /// the Unix epoch.
+    Message {
+        sender: String,
+        from_sharer: bool,
+        text: String,
+        timestamp: i64,
+    },
+    /// The messages sent before the viewer joined.
+    History { messages: Vec<ChatProtocolMessage> },
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub from_sharer: bool,
+    pub text: String,
+    pub time: DateTime<Local>,
+}
+
+impl ChatMessage {
+    fn to_protocol(&self) -> ChatProtocolMessage {
+        ChatProtocolMessage::Message {
+            sender: self.sender.clone(),
+            from_sharer: self.from_sharer,
+            text: self.text.clone(),
+            timestamp: self.time.timestamp_millis(),
+        }
+    }
+}
+
+/// Relays chat messages between the sharer and all viewers of a session.
+pub struct ChatManager {
+    viewer_manager: Arc<ViewerManager>,
+    notify_update: Arc<dyn Fn() + Send + Sync>,
+    peers: Mutex<HashMap<String, Sender<String>>>,
+    messages: Mutex<VecDeque<ChatMessage>>,
+}
+
+impl ChatManager {
+    pub fn new(
+        viewer_manager: Arc<ViewerManager>,
+        notify_update: Arc<dyn Fn() + Send + Sync>,
+    ) -> Self {
+        Self {
+            viewer_manager,
+            notify_update,
+            peers: Mutex::new(HashMap::new()),
+            messages: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub async fn get_messages(&self) -> Vec<ChatMessage> {
+        self.messages.lock().await.iter().cloned().collect()
+    }
+
+    /// Send a message from the sharer to all viewers.
+    pub async fn send(&self, text: String) {
+        self.broadcast(SHARER_NAME.to_string(), true, text).await;
+    }
+
+    async fn broadcast(&self, sender: String, from_sharer: bool, text: String) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let message = ChatMessage {
+            sender,
+            from_sharer,
+            text: text.chars().take(MAX_MESSAGE_LENGTH).collect(),
+            time: Local::now(),
+        };
+        let data = serde_json::to_string(&message.to_protocol()).unwrap();
+        let mut messages = self.messages.lock().await;
+        if messages.len() == MAX_HISTORY {
+            messages.pop_front();
+        }
+        messages.push_back(message);
+        drop(messages);
+
+        // a viewer that stopped reading must not hold up the others
+        for (uuid, peer) in self.peers.lock().await.iter() {
+            peer.try_send(data.clone())
+                .unwrap_or_else(|e| warn!("Failed to send chat message to {}: {}", uuid, e));
+        }
+        (self.notify_update)();
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for ChatManager {
+    type Message = String;
+
+    /// Register a peer and send it the messages so far.
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        let history = ChatProtocolMessage::History {
+            messages: self
+                .messages
+                .lock()
+                .await
+                .iter()
+                .map(ChatMessage::to_protocol)
+                .collect(),
+        };
+        sender
+            .send(serde_json::to_string(&history).unwrap())
+            .await
+            .ok();
+        self.peers.lock().await.insert(uuid, sender);
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().await.remove(uuid);
+    }
+
+    async fn clear(&self) {
+        self.peers.lock().await.clear();
+        self.messages.lock().await.clear();
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let text = match serde_json::from_slice::<ChatProtocolMessage>(&message.data) {
+            Ok(ChatProtocolMessage::Send { text }) => text,
+            Ok(message) => {
+                warn!("Unexpected chat message: {:?}", message);
+                return;
+            }
+            Err(e) => {
+                warn!("Invalid chat message: {}", e);
+                return;
+            }
+        };
+        let sender = self
+            .viewer_manager
+            .get_viewing_viewers()
+            .await
+            .into_iter()
+            .find(|v| v.uuid == uuid)
+            .map(|v| v.name);
+        match sender {
+            Some(sender) => self.broadcast(sender, false, text).await,
+            // Only viewers that were let in may chat.
+            None => warn!("Ignored chat message from unknown viewer {}", uuid),
+        }
+    }
+}
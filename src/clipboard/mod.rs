@@ -0,0 +1,361 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::{oneshot, Mutex};
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+
+use crate::config::ClipboardConfig;
+use crate::output::ChannelHandler;
+use crate::Result;
+
+/// How often the clipboard is checked for changes to send to viewers.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Messages of the `clipboard` data channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClipboardMessage {
+    /// Text to put on the receiver's clipboard.
+    Text { text: String },
+    /// An RGBA image to put on the receiver's clipboard, base64 encoded.
+    Image {
+        width: usize,
+        height: usize,
+        data: String,
+    },
+    /// Ask the sharer for its current clipboard (viewer to sharer only).
+    Request,
+    /// The last message could not be handled (sharer to viewer only).
+    Rejected { reason: RejectReason },
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectReason {
+    /// The sharer has not allowed clipboard access in this session.
+    Disabled,
+    /// The content exceeds the size limit.
+    TooLarge,
+    /// The message could not be parsed or the clipboard is not available.
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ClipboardContent {
+    Text(String),
+    Image {
+        width: usize,
+        height: usize,
+        bytes: Vec<u8>,
+    },
+}
+
+impl ClipboardContent {
+    fn read(clipboard: &mut arboard::Clipboard) -> Option<Self> {
+        if let Ok(text) = clipboard.get_text() {
+            return Some(ClipboardContent::Text(text));
+        }
+        clipboard
+            .get_image()
+            .ok()
+            .map(|image| ClipboardContent::Image {
+                width: image.width,
+                height: image.height,
+                bytes: image.bytes.into_owned(),
+            })
+    }
+
+    fn write(self, clipboard: &mut arboard::Clipboard) -> Result<()> {
+        match self {
+            ClipboardContent::Text(text) => clipboard.set_text(text)?,
+            ClipboardContent::Image {
+                width,
+                height,
+                bytes,
+            } => clipboard.set_image(arboard::ImageData {
+                width,
+                height,
+                bytes: bytes.into(),
+            })?,
+        }
+        Ok(())
+    }
+
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn into_message(self) -> ClipboardMessage {
+        match self {
+            ClipboardContent::Text(text) => ClipboardMessage::Text { text },
+            ClipboardContent::Image {
+                width,
+                height,
+                bytes,
+            } => ClipboardMessage::Image {
+                width,
+                height,
+                data: BASE64.encode(bytes),
+            },
+        }
+    }
+
+    fn from_message(message: ClipboardMessage) -> Option<Self> {
+        match message {
+            ClipboardMessage::Text { text } => Some(ClipboardContent::Text(text)),
+            ClipboardMessage::Image {
+                width,
+                height,
+                data,
+            } => {
+                let bytes = BASE64.decode(data).ok()?;
+                (bytes.len() == width * height * 4).then_some(ClipboardContent::Image {
+                    width,
+                    height,
+                    bytes,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+enum Command {
+    Set(ClipboardContent),
+    Get(oneshot::Sender<Option<ClipboardContent>>),
+}
+
+/// Synchronizes the sharer's clipboard with the viewers.
+///
+/// The clipboard is owned by a dedicated thread. Changes made on the sharer's machine
+/// are sent to every peer, and content sent by viewers is put on the clipboard, both
+/// only while the sharer allows clipboard access.
+pub struct ClipboardHandler {
+    enabled: Arc<AtomicBool>,
+    max_size: usize,
+    sender: std::sync::mpsc::Sender<Command>,
+    peers: Arc<Mutex<HashMap<String, Sender<String>>>>,
+}
+
+impl ClipboardHandler {
+    pub fn new(config: &ClipboardConfig) -> Self {
+        let enabled = Arc::new(AtomicBool::new(config.enabled));
+        let max_size = config.max_size;
+        let (sender, receiver) = std::sync::mpsc::channel::<Command>();
+        let peers = Arc::new(Mutex::new(HashMap::<String, Sender<String>>::new()));
+
+        let thread_enabled = enabled.clone();
+        let thread_peers = peers.clone();
+        std::thread::spawn(move || {
+            let mut clipboard = match arboard::Clipboard::new() {
+                Ok(clipboard) => clipboard,
+                Err(e) => {
+                    error!("Failed to open clipboard: {}", e);
+                    return;
+                }
+            };
+            let mut last_fingerprint = None;
+            let mut last_change_count = None;
+            let mut was_enabled = false;
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(Command::Set(content)) => {
+                        last_fingerprint = Some(content.fingerprint());
+                        if let Err(e) = content.write(&mut clipboard) {
+                            warn!("Failed to write clipboard: {}", e);
+                        }
+                    }
+                    Ok(Command::Get(reply)) => {
+                        reply.send(ClipboardContent::read(&mut clipboard)).ok();
+                    }
+                    Err(RecvTimeoutError::Timeout) => {
+                        let enabled = thread_enabled.load(Ordering::Relaxed);
+                        if !enabled || thread_peers.blocking_lock().is_empty() {
+                            was_enabled = enabled;
+                            continue;
+                        }
+                        // reading an image decodes it, so the clipboard is only read
+                        // when the system tells it changed, where it can
+                        let change_count = platform::change_count();
+                        if was_enabled
+                            && change_count.is_some()
+                            && change_count == last_change_count
+                        {
+                            continue;
+                        }
+                        last_change_count = change_count;
+                        let content = ClipboardContent::read(&mut clipboard);
+                        let fingerprint = content.as_ref().map(ClipboardContent::fingerprint);
+                        // Only changes made after access was allowed are sent, not
+                        // whatever happened to be on the clipboard before.
+                        let changed = was_enabled && fingerprint != last_fingerprint;
+                        was_enabled = true;
+                        last_fingerprint = fingerprint;
+                        if !changed {
+                            continue;
+                        }
+                        if let Some(content) = content {
+                            match encode(content.into_message(), max_size) {
+                                Ok(message) => {
+                                    for peer in thread_peers.blocking_lock().values() {
+                                        peer.try_send(message.clone()).ok();
+                                    }
+                                }
+                                Err(_) => info!("Clipboard content is too large to send"),
+                            }
+                        }
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        });
+
+        Self {
+            enabled,
+            max_size,
+            sender,
+            peers,
+        }
+    }
+
+    /// Allow or deny viewers access to the clipboard.
+    pub fn set_enabled(&self, enabled: bool) {
+        info!(
+            "Clipboard sync {}",
+            if enabled { "enabled" } else { "disabled" }
+        );
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Handle a message received from a viewer, returning the serialized reply if any.
+    async fn reply_to(&self, data: &[u8]) -> Option<String> {
+        if !self.is_enabled() {
+            return Some(rejected(RejectReason::Disabled));
+        }
+        if data.len() > self.max_size {
+            return Some(rejected(RejectReason::TooLarge));
+        }
+        let message = match serde_json::from_slice::<ClipboardMessage>(data) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Invalid clipboard message: {}", e);
+                return Some(rejected(RejectReason::Invalid));
+            }
+        };
+        if let ClipboardMessage::Request = message {
+            let (reply, content) = oneshot::channel();
+            self.sender.send(Command::Get(reply)).ok();
+            return match content.await.ok().flatten() {
+                Some(content) => {
+                    Some(encode(content.into_message(), self.max_size).unwrap_or_else(rejected))
+                }
+                None => Some(rejected(RejectReason::Invalid)),
+            };
+        }
+
+        match ClipboardContent::from_message(message) {
+            Some(content) => {
+                self.sender.send(Command::Set(content)).ok();
+                None
+            }
+            None => Some(rejected(RejectReason::Invalid)),
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for ClipboardHandler {
+    type Message = String;
+
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        self.peers.lock().await.insert(uuid, sender);
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let reply = match self.reply_to(&message.data).await {
+            Some(reply) => reply,
+            None => return,
+        };
+        if let Some(peer) = self.peers.lock().await.get(uuid) {
+            if let Err(e) = peer.send(reply).await {
+                warn!("Failed to send clipboard reply: {}", e);
+            }
+        }
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().await.remove(uuid);
+    }
+
+    async fn clear(&self) {
+        self.peers.lock().await.clear();
+    }
+}
+
+fn encode(message: ClipboardMessage, max_size: usize) -> std::result::Result<String, RejectReason> {
+    let message = serde_json::to_string(&message).map_err(|_| RejectReason::Invalid)?;
+    if message.len() > max_size {
+        return Err(RejectReason::TooLarge);
+    }
+    Ok(message)
+}
+
+fn rejected(reason: RejectReason) -> String {
+    serde_json::to_string(&ClipboardMessage::Rejected { reason }).unwrap()
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::System::DataExchange::GetClipboardSequenceNumber;
+
+    /// A number the system changes whenever the clipboard does.
+    pub fn change_count() -> Option<u64> {
+        match unsafe { GetClipboardSequenceNumber() } {
+            // not available to this process, e.g. in another window station
+            0 => None,
+            count => Some(count as u64),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc::runtime::Object;
+    use objc::{class, msg_send, sel, sel_impl};
+
+    /// A number the system changes whenever the clipboard does.
+    pub fn change_count() -> Option<u64> {
+        unsafe {
+            let pasteboard: *mut Object = msg_send![class!(NSPasteboard), generalPasteboard];
+            if pasteboard.is_null() {
+                return None;
+            }
+            let count: isize = msg_send![pasteboard, changeCount];
+            Some(count as u64)
+        }
+    }
+}
+
+/// X11 has no change counter, the clipboard is read at each poll.
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn change_count() -> Option<u64> {
+        None
+    }
+}
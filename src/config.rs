@@ -1,19 +1,24 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use base64::Engine;
+use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use twilio::TwilioAuthentication;
 use webrtc::ice_transport::ice_credential_type::RTCIceCredentialType;
 use webrtc::ice_transport::ice_server::RTCIceServer;
 
+use crate::inputs::ControlPermission;
 use crate::signaller::{Signaller, SignallerIceServer};
 use crate::Result;
 
+/// The audio frame durations in milliseconds supported by Opus.
+pub const OPUS_FRAME_DURATIONS: [f32; 6] = [2.5, 5., 10., 20., 40., 60.];
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(default = "default_signaller")]
@@ -30,6 +35,30 @@ pub struct Config {
 
     #[serde(default = "libx264")]
     pub encoder: EncoderConfig,
+
+    #[serde(default)]
+    pub audio: AudioConfig,
+
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+
+    #[serde(default)]
+    pub file_transfer: FileTransferConfig,
+
+    #[serde(default)]
+    pub annotations: AnnotationConfig,
+
+    #[serde(default)]
+    pub cursor: CursorConfig,
+
+    #[serde(default)]
+    pub input: InputConfig,
+
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+
+    #[serde(default)]
+    pub report: ReportConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -40,6 +69,266 @@ pub struct EncoderConfig {
     pub options: HashMap<String, String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioConfig {
+    #[serde(default = "default_system_audio")]
+    pub system: AudioSourceConfig,
+    #[serde(default)]
+    pub microphone: AudioSourceConfig,
+    /// Target bitrate in bits per second.
+    #[serde(default = "default_audio_bitrate")]
+    pub bitrate: u32,
+    /// Duration of each encoded frame in milliseconds, one of 2.5, 5, 10, 20, 40 or 60.
+    #[serde(default = "default_audio_frame_duration")]
+    pub frame_duration: f32,
+    /// Encoder complexity from 0 (fastest) to 10 (best quality).
+    #[serde(default = "default_audio_complexity")]
+    pub complexity: u8,
+    /// Add in-band forward error correction so lost packets can be partially recovered.
+    #[serde(default = "default_true")]
+    pub fec: bool,
+    /// Expected packet loss in percent, which determines how much FEC data is sent.
+    #[serde(default = "default_audio_packet_loss")]
+    pub packet_loss: u8,
+    /// Discontinuous transmission, sending almost nothing during silence.
+    #[serde(default)]
+    pub dtx: bool,
+    #[serde(default)]
+    pub application: OpusApplication,
+}
+
+/// What the audio encoder is tuned for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OpusApplication {
+    /// Speech intelligibility.
+    Voip,
+    /// Faithful reproduction of music and other non-speech audio.
+    #[default]
+    Audio,
+    /// The lowest possible latency, disabling speech-optimized modes.
+    LowDelay,
+}
+
+impl OpusApplication {
+    /// The value of the libopus `application` option.
+    pub fn as_option(&self) -> &'static str {
+        match self {
+            OpusApplication::Voip => "voip",
+            OpusApplication::Audio => "audio",
+            OpusApplication::LowDelay => "lowdelay",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioSourceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Name of the device to capture from, or the default device if not set.
+    pub device: Option<String>,
+    #[serde(default = "default_gain")]
+    pub gain: f32,
+}
+
+impl AudioConfig {
+    /// Replace settings the encoder does not support with their defaults.
+    fn validate(&mut self) {
+        if !OPUS_FRAME_DURATIONS.contains(&self.frame_duration) {
+            warn!(
+                "Unsupported audio frame duration {}ms, expected one of {:?}, using {}ms",
+                self.frame_duration,
+                OPUS_FRAME_DURATIONS,
+                default_audio_frame_duration()
+            );
+            self.frame_duration = default_audio_frame_duration();
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            system: default_system_audio(),
+            microphone: Default::default(),
+            bitrate: default_audio_bitrate(),
+            frame_duration: default_audio_frame_duration(),
+            complexity: default_audio_complexity(),
+            fec: default_true(),
+            packet_loss: default_audio_packet_loss(),
+            dtx: false,
+            application: Default::default(),
+        }
+    }
+}
+
+impl Default for AudioSourceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: None,
+            gain: default_gain(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClipboardConfig {
+    /// Whether viewers may read and write the clipboard when a session starts.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Maximum size in bytes of a clipboard message, in either direction.
+    #[serde(default = "default_clipboard_max_size")]
+    pub max_size: usize,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size: default_clipboard_max_size(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileTransferConfig {
+    /// Whether viewers may offer files to the sharer. Off unless enabled, files dropped
+    /// onto the window are sent to viewers either way.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory received files are saved to, the user's downloads directory if not set.
+    pub download_dir: Option<String>,
+    /// Maximum size in bytes of a received file.
+    #[serde(default = "default_file_transfer_max_size")]
+    pub max_size: u64,
+}
+
+impl Default for FileTransferConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            download_dir: None,
+            max_size: default_file_transfer_max_size(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnnotationConfig {
+    /// Whether viewer pointers and drawings are shown when a session starts.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for AnnotationConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CursorConfig {
+    /// Send the cursor position and shape to viewers, which draw it themselves,
+    /// instead of capturing it as part of the screen. Off by default, as viewers that
+    /// do not draw it would not see the cursor at all.
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+impl Default for CursorConfig {
+    fn default() -> Self {
+        Self { enabled: false }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InputConfig {
+    /// What viewers may control when they join, they still need to be granted
+    /// control before their input is applied.
+    #[serde(default = "default_control_permission")]
+    pub default_permission: ControlPermission,
+    /// Shortcuts viewers may not press, as KeyboardEvent codes joined by `+`, where
+    /// `Control`, `Alt`, `Shift` and `Meta` stand for the modifier on either side,
+    /// e.g. `Meta+KeyL`.
+    #[serde(default = "default_blocked_shortcuts")]
+    pub blocked_shortcuts: Vec<String>,
+    /// Ignore keys while the sharer's own window is focused, so viewers can't
+    /// operate or quit it.
+    #[serde(default = "default_true")]
+    pub block_sharer_app: bool,
+    /// Maximum input events per second of each viewer, releases of keys and mouse
+    /// buttons are never dropped.
+    #[serde(default = "default_max_input_rate")]
+    pub max_events_per_second: u32,
+    /// Spread the scrolling of mouse wheels over a few frames, like native smooth
+    /// scrolling does.
+    #[serde(default = "default_true")]
+    pub smooth_scrolling: bool,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            default_permission: default_control_permission(),
+            blocked_shortcuts: default_blocked_shortcuts(),
+            block_sharer_app: true,
+            max_events_per_second: default_max_input_rate(),
+            smooth_scrolling: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricsConfig {
+    /// Serve Prometheus metrics at `http://127.0.0.1:<port>/metrics`.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_metrics_port")]
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_metrics_port(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReportConfig {
+    /// Save a summary of each session when it ends, including the names of viewers
+    /// and the errors logged. Off by default.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Directory reports are saved to, a `reports` directory in the application's
+    /// data directory if not set.
+    pub dir: Option<String>,
+}
+
+impl ReportConfig {
+    pub fn dir(&self) -> PathBuf {
+        self.dir
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| {
+                ProjectDirs::from("", "", "Mira Sharer").map(|dirs| dirs.data_dir().join("reports"))
+            })
+            .unwrap_or_else(|| PathBuf::from("reports"))
+    }
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum IceCredentialType {
     Unspecified,
@@ -135,17 +424,24 @@ impl From<IceServer> for RTCIceServer {
 pub fn load(path: &Path) -> Result<Config> {
     // create a new file if it does not exist
     if !path.exists() {
-        let mut file = File::create(path)?;
         let config = toml::from_str::<Config>("")?;
-        file.write_all("# for more sample configs, see https://github.com/mira-screen-share/sharer/tree/main/configs\n".as_bytes())?;
-        file.write_all(toml::to_string(&config)?.as_ref())?;
+        save(path, &config)?;
         return Ok(config);
     }
 
     let mut file = File::open(path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    Ok(toml::from_str(&contents)?)
+    let mut config: Config = toml::from_str(&contents)?;
+    config.audio.validate();
+    Ok(config)
+}
+
+pub fn save(path: &Path, config: &Config) -> Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all("# for more sample configs, see https://github.com/mira-screen-share/sharer/tree/main/configs\n".as_bytes())?;
+    file.write_all(toml::to_string(config)?.as_ref())?;
+    Ok(())
 }
 
 fn libx264() -> EncoderConfig {
@@ -186,6 +482,78 @@ fn default_ice_servers() -> Vec<IceServer> {
     ]
 }
 
+fn default_system_audio() -> AudioSourceConfig {
+    AudioSourceConfig {
+        enabled: true,
+        ..Default::default()
+    }
+}
+
+fn default_gain() -> f32 {
+    1.0
+}
+
+fn default_audio_bitrate() -> u32 {
+    96_000
+}
+
+fn default_audio_frame_duration() -> f32 {
+    10.
+}
+
+fn default_audio_complexity() -> u8 {
+    10
+}
+
+fn default_audio_packet_loss() -> u8 {
+    10
+}
+
+fn default_clipboard_max_size() -> usize {
+    // the largest message a data channel accepts by default
+    64 * 1024
+}
+
+fn default_file_transfer_max_size() -> u64 {
+    4 * 1024 * 1024 * 1024
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_control_permission() -> ControlPermission {
+    ControlPermission::Full
+}
+
+fn default_blocked_shortcuts() -> Vec<String> {
+    let shortcuts: &[&str] = if cfg!(target_os = "macos") {
+        // lock screen, log out
+        &[
+            "Control+Meta+KeyQ",
+            "Shift+Meta+KeyQ",
+            "Alt+Shift+Meta+KeyQ",
+        ]
+    } else {
+        // lock screen, security screen
+        &[
+            "Meta+KeyL",
+            "Control+Alt+Delete",
+            "Control+Alt+NumpadDecimal",
+        ]
+    };
+    shortcuts.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_max_input_rate() -> u32 {
+    // well above what a mouse and a fast typist produce
+    1000
+}
+
+fn default_metrics_port() -> u16 {
+    9464
+}
+
 async fn get_twilio_ice_servers(s: IceServer) -> Vec<IceServer> {
     if s.credential_type != IceCredentialType::Twilio {
         return vec![];
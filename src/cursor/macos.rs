@@ -0,0 +1,131 @@
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::c_void;
+use std::hash::{Hash, Hasher};
+use std::slice;
+use std::sync::Mutex;
+
+use objc::runtime::Object;
+use objc::{class, msg_send, sel, sel_impl};
+
+use crate::cursor::CursorShape;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CGPoint {
+    x: f64,
+    y: f64,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CGSize {
+    width: f64,
+    height: f64,
+}
+
+/// `NSBitmapImageFileTypePNG`
+const PNG_FILE_TYPE: usize = 4;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventCreate(source: *const c_void) -> *const c_void;
+    fn CGEventGetLocation(event: *const c_void) -> CGPoint;
+    fn CGCursorIsVisible() -> u32;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: *const c_void);
+}
+
+/// The cursor position in global display coordinates, if it is shown.
+pub fn position() -> Option<(f64, f64)> {
+    unsafe {
+        if CGCursorIsVisible() == 0 {
+            return None;
+        }
+        let event = CGEventCreate(std::ptr::null());
+        if event.is_null() {
+            return None;
+        }
+        let location = CGEventGetLocation(event);
+        CFRelease(event);
+        Some((location.x, location.y))
+    }
+}
+
+/// The last cursor whose shape was hashed, retained so its address is not reused by
+/// another cursor, along with its key.
+static LAST_CURSOR: Mutex<Option<(usize, u64)>> = Mutex::new(None);
+
+/// Identifies the current cursor shape by its image and hotspot. The image is only
+/// hashed when the system returns a different cursor object than the last time.
+pub fn shape_key() -> Option<u64> {
+    with_cursor(|cursor| unsafe {
+        let mut last = LAST_CURSOR.lock().unwrap();
+        if let Some((pointer, key)) = *last {
+            if pointer == cursor as usize {
+                return Some(key);
+            }
+        }
+        let tiff = data_bytes(msg_send![image(cursor), TIFFRepresentation])?;
+        let hotspot: CGPoint = msg_send![cursor, hotSpot];
+        let mut hasher = DefaultHasher::new();
+        tiff.hash(&mut hasher);
+        hotspot.x.to_bits().hash(&mut hasher);
+        hotspot.y.to_bits().hash(&mut hasher);
+        let key = hasher.finish();
+        let _: *mut Object = msg_send![cursor, retain];
+        if let Some((pointer, _)) = last.replace((cursor as usize, key)) {
+            let _: () = msg_send![pointer as *mut Object, release];
+        }
+        Some(key)
+    })
+}
+
+pub fn shape() -> Option<CursorShape> {
+    with_cursor(|cursor| unsafe {
+        let image = image(cursor);
+        let size: CGSize = msg_send![image, size];
+        let hotspot: CGPoint = msg_send![cursor, hotSpot];
+        let tiff: *mut Object = msg_send![image, TIFFRepresentation];
+        let bitmap: *mut Object = msg_send![class!(NSBitmapImageRep), imageRepWithData: tiff];
+        if bitmap.is_null() {
+            return None;
+        }
+        let properties: *mut Object = msg_send![class!(NSDictionary), dictionary];
+        let png = data_bytes(
+            msg_send![bitmap, representationUsingType: PNG_FILE_TYPE properties: properties],
+        )?;
+        Some(CursorShape {
+            width: size.width,
+            height: size.height,
+            hotspot: (hotspot.x, hotspot.y),
+            png,
+        })
+    })
+}
+
+/// Run `f` with the current system cursor, releasing the objects it creates after.
+fn with_cursor<T>(f: impl FnOnce(*mut Object) -> Option<T>) -> Option<T> {
+    unsafe {
+        let pool: *mut Object = msg_send![class!(NSAutoreleasePool), new];
+        let cursor: *mut Object = msg_send![class!(NSCursor), currentSystemCursor];
+        let ret = if cursor.is_null() { None } else { f(cursor) };
+        let _: () = msg_send![pool, drain];
+        ret
+    }
+}
+
+unsafe fn image(cursor: *mut Object) -> *mut Object {
+    msg_send![cursor, image]
+}
+
+unsafe fn data_bytes(data: *mut Object) -> Option<Vec<u8>> {
+    if data.is_null() {
+        return None;
+    }
+    let length: usize = msg_send![data, length];
+    let bytes: *const u8 = msg_send![data, bytes];
+    Some(slice::from_raw_parts(bytes, length).to_vec())
+}
@@ -0,0 +1,263 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::capture::DisplayInfo;
+use crate::output::ChannelHandler;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+
+/// How often the cursor position is checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(8);
+/// The shape is checked every this many position polls.
+const SHAPE_POLL_TICKS: u64 = 4;
+
+/// Messages of the `cursor` data channel, sent by the sharer only. Sizes are in
+/// pixels of the video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CursorMessage {
+    /// A cursor shape, sent once per viewer before it is first used. `data` is a
+    /// base64 encoded PNG.
+    Shape {
+        id: u32,
+        width: u32,
+        height: u32,
+        hotspot_x: u32,
+        hotspot_y: u32,
+        data: String,
+    },
+    /// The cursor moved or changed its shape. Coordinates are relative to the shared
+    /// display, from 0 to 1.
+    Position {
+        x: f32,
+        y: f32,
+        visible: bool,
+        shape: Option<u32>,
+    },
+}
+
+/// The image of a cursor, in the units of the cursor coordinates.
+pub struct CursorShape {
+    pub width: f64,
+    pub height: f64,
+    pub hotspot: (f64, f64),
+    pub png: Vec<u8>,
+}
+
+/// The captured area, in the coordinate space of the system cursor.
+#[derive(Debug, Copy, Clone)]
+struct CursorArea {
+    origin: (f64, f64),
+    size: (f64, f64),
+    /// Video pixels per unit of the cursor coordinates.
+    scale: f64,
+}
+
+impl CursorArea {
+    fn of(display: &dyn DisplayInfo) -> Self {
+        let (x, y) = display.origin();
+        let (width, height) = display.resolution();
+        let scale = display.dpi_conversion_factor();
+        Self {
+            origin: (x as f64, y as f64),
+            size: (width as f64 / scale, height as f64 / scale),
+            scale,
+        }
+    }
+
+    /// The position relative to the area, if it is inside.
+    fn normalize(&self, (x, y): (f64, f64)) -> Option<(f32, f32)> {
+        let x = (x - self.origin.0) / self.size.0;
+        let y = (y - self.origin.1) / self.size.1;
+        ((0. ..1.).contains(&x) && (0. ..1.).contains(&y)).then_some((x as f32, y as f32))
+    }
+}
+
+struct Peer {
+    sender: Sender<String>,
+    /// The shapes the peer has received.
+    shapes: HashSet<u32>,
+}
+
+#[derive(Default)]
+struct CursorState {
+    /// Shape ids by the platform's key of the shape.
+    shape_ids: HashMap<u64, u32>,
+    /// Shape messages by id.
+    shapes: HashMap<u32, String>,
+    next_shape: u32,
+    shape: Option<u32>,
+    position: Option<(f32, f32)>,
+}
+
+impl CursorState {
+    fn position_message(&self) -> String {
+        let (x, y) = self.position.unwrap_or_default();
+        serde_json::to_string(&CursorMessage::Position {
+            x,
+            y,
+            visible: self.position.is_some(),
+            shape: self.shape,
+        })
+        .unwrap()
+    }
+
+    fn shape_message(&self, id: u32) -> Option<&String> {
+        self.shapes.get(&id)
+    }
+}
+
+/// Streams the position and shape of the sharer's cursor to viewers, which draw it
+/// themselves instead of it being part of the video.
+pub struct CursorManager {
+    enabled: bool,
+    peers: Mutex<HashMap<String, Peer>>,
+    state: Mutex<CursorState>,
+}
+
+impl CursorManager {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            peers: Mutex::new(HashMap::new()),
+            state: Mutex::new(CursorState::default()),
+        }
+    }
+
+    /// Start following the cursor over `display` until `cancel` is cancelled.
+    pub fn start(self: &Arc<Self>, display: &dyn DisplayInfo, cancel: CancellationToken) {
+        if !self.enabled {
+            return;
+        }
+        let area = CursorArea::of(display);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(POLL_INTERVAL);
+            let mut shape_key = None;
+            for tick in 0.. {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancel.cancelled() => break,
+                }
+                let mut changed = false;
+                if tick % SHAPE_POLL_TICKS == 0 {
+                    let key = platform::shape_key();
+                    if key != shape_key {
+                        shape_key = key;
+                        changed |= manager.shape_changed(key, area.scale).await;
+                    }
+                }
+                let position = platform::position().and_then(|p| area.normalize(p));
+                {
+                    let mut state = manager.state.lock().await;
+                    if state.position != position {
+                        state.position = position;
+                        changed = true;
+                    }
+                }
+                if changed {
+                    manager.send_position().await;
+                }
+            }
+        });
+    }
+
+    /// Switch to the shape identified by `key`, reading it if it was not seen before.
+    /// Returns whether the shape changed.
+    async fn shape_changed(&self, key: Option<u64>, scale: f64) -> bool {
+        let mut state = self.state.lock().await;
+        let id = match key {
+            Some(key) => match state.shape_ids.get(&key) {
+                Some(id) => Some(*id),
+                None => match platform::shape() {
+                    Some(shape) => {
+                        let id = state.next_shape;
+                        state.next_shape += 1;
+                        let scaled = |value: f64| (value * scale).round() as u32;
+                        let message = serde_json::to_string(&CursorMessage::Shape {
+                            id,
+                            width: scaled(shape.width),
+                            height: scaled(shape.height),
+                            hotspot_x: scaled(shape.hotspot.0),
+                            hotspot_y: scaled(shape.hotspot.1),
+                            data: BASE64.encode(shape.png),
+                        })
+                        .unwrap();
+                        state.shape_ids.insert(key, id);
+                        state.shapes.insert(id, message);
+                        Some(id)
+                    }
+                    None => None,
+                },
+            },
+            None => None,
+        };
+        let changed = state.shape != id;
+        state.shape = id;
+        changed
+    }
+
+    async fn send_position(&self) {
+        let state = self.state.lock().await;
+        let mut peers = self.peers.lock().await;
+        for peer in peers.values_mut() {
+            Self::send_to(&state, peer).await;
+        }
+    }
+
+    async fn send_to(state: &CursorState, peer: &mut Peer) {
+        if let Some(id) = state.shape {
+            if !peer.shapes.contains(&id) {
+                if let Some(message) = state.shape_message(id) {
+                    if peer.sender.send(message.clone()).await.is_err() {
+                        return;
+                    }
+                    peer.shapes.insert(id);
+                }
+            }
+        }
+        // positions are superseded quickly, drop them if the peer falls behind
+        peer.sender.try_send(state.position_message()).ok();
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for CursorManager {
+    type Message = String;
+
+    /// Register a peer and send it the current cursor.
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        let mut peer = Peer {
+            sender,
+            shapes: HashSet::new(),
+        };
+        let state = self.state.lock().await;
+        Self::send_to(&state, &mut peer).await;
+        self.peers.lock().await.insert(uuid, peer);
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().await.remove(uuid);
+    }
+
+    async fn clear(&self) {
+        self.peers.lock().await.clear();
+        *self.state.lock().await = CursorState::default();
+    }
+}
@@ -0,0 +1,147 @@
+use std::ffi::c_void;
+use std::mem::size_of;
+
+use windows::Win32::Foundation::HWND;
+use windows::Win32::Graphics::Gdi::{
+    DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO, BITMAPINFOHEADER,
+    BI_RGB, DIB_RGB_COLORS, HBITMAP, HDC,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    GetCursorInfo, GetIconInfo, CURSORINFO, CURSOR_SHOWING, HICON, ICONINFO,
+};
+
+use crate::cursor::CursorShape;
+
+fn cursor_info() -> Option<CURSORINFO> {
+    let mut info = CURSORINFO {
+        cbSize: size_of::<CURSORINFO>() as u32,
+        ..Default::default()
+    };
+    unsafe { GetCursorInfo(&mut info) }.ok().ok()?;
+    (info.flags.0 & CURSOR_SHOWING.0 != 0).then_some(info)
+}
+
+/// The cursor position on the virtual desktop, if it is shown.
+pub fn position() -> Option<(f64, f64)> {
+    cursor_info().map(|info| (info.ptScreenPos.x as f64, info.ptScreenPos.y as f64))
+}
+
+/// Identifies the current cursor shape, if it is shown.
+pub fn shape_key() -> Option<u64> {
+    cursor_info().map(|info| info.hCursor.0 as u64)
+}
+
+pub fn shape() -> Option<CursorShape> {
+    let info = cursor_info()?;
+    let mut icon = ICONINFO::default();
+    unsafe { GetIconInfo(HICON(info.hCursor.0), &mut icon) }
+        .ok()
+        .ok()?;
+    let shape = unsafe { read_icon(&icon) };
+    unsafe {
+        DeleteObject(icon.hbmMask);
+        if !icon.hbmColor.is_invalid() {
+            DeleteObject(icon.hbmColor);
+        }
+    }
+    let (width, height, rgba) = shape?;
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&rgba))
+        .ok()?;
+
+    Some(CursorShape {
+        width: width as f64,
+        height: height as f64,
+        hotspot: (icon.xHotspot as f64, icon.yHotspot as f64),
+        png,
+    })
+}
+
+/// Read the icon as RGBA. Monochrome cursors have no color bitmap, their mask holds
+/// an AND mask over an XOR mask instead.
+unsafe fn read_icon(icon: &ICONINFO) -> Option<(u32, u32, Vec<u8>)> {
+    let dc = GetDC(HWND(0));
+    let ret = if icon.hbmColor.is_invalid() {
+        read_bitmap(dc, icon.hbmMask).map(|(width, height, mask)| {
+            let height = height / 2;
+            let (and, xor) = mask.split_at((width * height * 4) as usize);
+            let rgba = and
+                .chunks_exact(4)
+                .zip(xor.chunks_exact(4))
+                .flat_map(|(and, xor)| match (and[0] != 0, xor[0] != 0) {
+                    (false, false) => [0, 0, 0, 255],
+                    (false, true) => [255, 255, 255, 255],
+                    (true, false) => [0, 0, 0, 0],
+                    // inverts the screen, which cannot be drawn by viewers
+                    (true, true) => [0, 0, 0, 160],
+                })
+                .collect();
+            (width, height, rgba)
+        })
+    } else {
+        read_bitmap(dc, icon.hbmColor).map(|(width, height, bgra)| {
+            let has_alpha = bgra.chunks_exact(4).any(|pixel| pixel[3] != 0);
+            let mask = (!has_alpha)
+                .then(|| read_bitmap(dc, icon.hbmMask))
+                .flatten();
+            let rgba = bgra
+                .chunks_exact(4)
+                .enumerate()
+                .flat_map(|(i, pixel)| {
+                    let alpha = match &mask {
+                        Some((_, _, mask)) if mask[i * 4] != 0 => 0,
+                        Some(_) => 255,
+                        None => pixel[3],
+                    };
+                    [pixel[2], pixel[1], pixel[0], alpha]
+                })
+                .collect();
+            (width, height, rgba)
+        })
+    };
+    ReleaseDC(HWND(0), dc);
+    ret
+}
+
+/// Read a bitmap as top-down 32 bit BGRA.
+unsafe fn read_bitmap(dc: HDC, bitmap: HBITMAP) -> Option<(u32, u32, Vec<u8>)> {
+    let mut info = BITMAP::default();
+    if GetObjectW(
+        bitmap,
+        size_of::<BITMAP>() as i32,
+        Some(&mut info as *mut _ as *mut c_void),
+    ) == 0
+    {
+        return None;
+    }
+    let (width, height) = (info.bmWidth as u32, info.bmHeight as u32);
+    let mut header = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut pixels = vec![0u8; (width * height * 4) as usize];
+    let lines = GetDIBits(
+        dc,
+        bitmap,
+        0,
+        height,
+        Some(pixels.as_mut_ptr() as *mut c_void),
+        &mut header,
+        DIB_RGB_COLORS,
+    );
+    (lines == height as i32).then_some((width, height, pixels))
+}
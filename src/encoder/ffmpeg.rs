@@ -1,5 +1,6 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64};
 use std::sync::Arc;
+use std::time::Instant;
 
 use ac_ffmpeg::codec::video::VideoEncoder;
 use ac_ffmpeg::codec::{video, Encoder};
@@ -7,6 +8,7 @@ use ac_ffmpeg::time::{TimeBase, Timestamp};
 use bytes::Bytes;
 use itertools::enumerate;
 
+use crate::annotation::{AnnotationManager, Canvas, Pixels};
 use crate::capture::YUVFrame;
 use crate::config::EncoderConfig;
 use crate::encoder::frame_pool::FramePool;
@@ -19,6 +21,15 @@ pub struct FfmpegEncoder {
     w: usize,
     h: usize,
     pub force_idr: Arc<AtomicBool>,
+    /// Keyframes encoded because `force_idr` was set.
+    forced_keyframes: Arc<AtomicU64>,
+    overlay: Option<Arc<AnnotationManager>>,
+    /// When the first frame was encoded, timestamps count from it.
+    started: Option<Instant>,
+    /// Timestamp of the last encoded frame.
+    last_pts: Option<i64>,
+    /// Whether the last encoded frame was a keyframe.
+    keyframe: bool,
 }
 
 unsafe impl Send for FfmpegEncoder {}
@@ -55,30 +66,59 @@ impl FfmpegEncoder {
             pixel_format: encoder_config.pixel_format.clone(),
             frame_pool: FramePool::new(w, h, time_base, pixel_format),
             force_idr: Arc::new(AtomicBool::new(false)),
+            forced_keyframes: Arc::new(AtomicU64::new(0)),
+            overlay: None,
+            started: None,
+            last_pts: None,
+            keyframe: false,
             w,
             h,
         }
     }
 
-    pub fn encode(&mut self, frame_data: FrameData, frame_time: i64) -> Result<Bytes> {
+    /// Draw the annotations of `overlay` over every encoded frame.
+    pub fn set_overlay(&mut self, overlay: Arc<AnnotationManager>) {
+        self.overlay.replace(overlay);
+    }
+
+    pub fn overlay(&self) -> Option<Arc<AnnotationManager>> {
+        self.overlay.clone()
+    }
+
+    /// Counter of the keyframes encoded because `force_idr` was set.
+    pub fn forced_keyframes(&self) -> Arc<AtomicU64> {
+        self.forced_keyframes.clone()
+    }
+
+    /// Whether the last encoded frame was a keyframe.
+    pub fn is_keyframe(&self) -> bool {
+        self.keyframe
+    }
+
+    /// Encode a frame, timestamped with the time it is encoded at. Frames encoded
+    /// again, e.g. when the overlay changed while the screen did not, share the same
+    /// clock as captured ones, so timestamps stay evenly spaced.
+    pub fn encode(&mut self, frame_data: FrameData) -> Result<Bytes> {
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let pts = (started.elapsed().as_secs_f64() * 90_000.) as i64;
+        // timestamps must increase, even for frames encoded within the same tick
+        let pts = match self.last_pts {
+            Some(last) => pts.max(last + 1),
+            None => pts,
+        };
+        self.last_pts = Some(pts);
+
         let mut frame = self.frame_pool.take();
         let time_base = frame.time_base();
         frame = frame
-            .with_pts(Timestamp::new(
-                if cfg!(target_os = "windows") {
-                    (frame_time as f64 * 9. / 1000.) as i64
-                } else if cfg!(target_os = "macos") {
-                    (frame_time as f64 * 9. / 1e5) as i64
-                } else {
-                    panic!("Unsupported OS")
-                },
-                time_base,
-            ))
+            .with_pts(Timestamp::new(pts, time_base))
             .with_picture_type(
                 if self
                     .force_idr
                     .swap(false, std::sync::atomic::Ordering::Relaxed)
                 {
+                    self.forced_keyframes
+                        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                     video::frame::PictureType::I
                 } else {
                     video::frame::PictureType::None
@@ -103,10 +143,34 @@ impl FfmpegEncoder {
                     encoder_line_size,
                     frame.planes_mut()[1].data_mut(),
                 );
+
+                if let Some(overlay) = &self.overlay {
+                    let mut planes = frame.planes_mut();
+                    let (luma, chroma) = planes.split_at_mut(1);
+                    overlay.draw(&mut Canvas {
+                        pixels: Pixels::Nv12 {
+                            luma: luma[0].data_mut(),
+                            chroma: chroma[0].data_mut(),
+                        },
+                        stride: encoder_line_size,
+                        width: self.w,
+                        height: self.h,
+                    });
+                }
             }
             FrameData::BGR0(bgr0) => match self.pixel_format.as_str() {
                 "bgra" => {
-                    frame.planes_mut()[0].data_mut().copy_from_slice(bgr0);
+                    let mut planes = frame.planes_mut();
+                    let pixels = planes[0].data_mut();
+                    pixels.copy_from_slice(bgr0);
+                    if let Some(overlay) = &self.overlay {
+                        overlay.draw(&mut Canvas {
+                            stride: pixels.len() / self.h,
+                            pixels: Pixels::Bgra(pixels),
+                            width: self.w,
+                            height: self.h,
+                        });
+                    }
                 }
                 _ => unimplemented!(),
             },
@@ -115,7 +179,9 @@ impl FfmpegEncoder {
         self.encoder.push(frame.clone())?;
         self.frame_pool.put(frame);
         let mut ret = Vec::new();
+        self.keyframe = false;
         while let Some(packet) = self.encoder.take()? {
+            self.keyframe |= packet.is_key();
             ret.extend(packet.data());
         }
         Ok(Bytes::from(ret))
@@ -0,0 +1,1090 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use directories::UserDirs;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::sync::mpsc::Sender;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+
+use crate::auth::{ViewerIdentifier, ViewerManager};
+use crate::config::FileTransferConfig;
+use crate::output::{ChannelHandler, ChannelMessage};
+use crate::Result;
+
+/// How many bytes are received between progress reports.
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+/// Size of the header of binary chunk messages: transfer id (u32) and offset (u64),
+/// both big endian.
+const CHUNK_HEADER_SIZE: usize = 12;
+/// Size of the data of the chunks sent to viewers.
+const CHUNK_SIZE: usize = 16 * 1024;
+/// Ids of the files offered by the sharer have the top bit set, so they never collide
+/// with those chosen by viewers.
+const SHARER_ID_BASE: u32 = 1 << 31;
+/// Bytes queued on the channel of a peer above which sending a file waits.
+const MAX_BUFFERED_AMOUNT: usize = 1024 * 1024;
+/// Characters Windows does not allow in file names, rejected everywhere so a file
+/// name is valid on any platform.
+const INVALID_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+/// Files a viewer may have offered without the sharer answering yet, further offers
+/// are rejected.
+const MAX_PENDING_OFFERS: usize = 8;
+/// Age after which a partial file nobody resumed is deleted.
+const PART_FILE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Control messages of the `file` data channel. File content is sent as binary
+/// messages of a [`CHUNK_HEADER_SIZE`] byte header followed by the data.
+///
+/// Either side may offer a file, the other one accepts or rejects it and the one
+/// receiving it reports progress and completion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum TransferMessage {
+    /// A file is offered. `sha256` is the hex digest of the whole file.
+    Offer {
+        id: u32,
+        name: String,
+        size: u64,
+        sha256: String,
+    },
+    /// The file was accepted, chunks should be sent starting at `offset`.
+    Accept {
+        id: u32,
+        offset: u64,
+    },
+    Reject {
+        id: u32,
+        reason: String,
+    },
+    Progress {
+        id: u32,
+        received: u64,
+    },
+    /// The file was received and its checksum matches.
+    Complete {
+        id: u32,
+    },
+    Failed {
+        id: u32,
+        reason: String,
+    },
+    /// Either side stops the transfer.
+    Cancel {
+        id: u32,
+    },
+}
+
+/// A message sent to a viewer on the `file` data channel.
+#[derive(Debug)]
+pub enum FileChannelMessage {
+    Control(String),
+    Chunk(Bytes),
+}
+
+#[async_trait]
+impl ChannelMessage for FileChannelMessage {
+    async fn send_on(self, channel: &RTCDataChannel) -> Result<()> {
+        match self {
+            FileChannelMessage::Control(message) => {
+                channel.send_text(message).await?;
+            }
+            FileChannelMessage::Chunk(chunk) => {
+                // keep the SCTP send buffer bounded while streaming a file
+                while channel.buffered_amount().await > MAX_BUFFERED_AMOUNT {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+                channel.send(&chunk).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransferDirection {
+    /// A file sent by a viewer to the sharer.
+    Incoming,
+    /// A file sent by the sharer to a viewer.
+    Outgoing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferState {
+    /// Waiting for the receiving side to accept or reject the file.
+    Pending,
+    Receiving,
+    Sending,
+    /// Where the file was saved when receiving, or the file sent.
+    Completed(PathBuf),
+    Failed(String),
+}
+
+/// A file sent by a viewer or to a viewer, as shown in the GUI.
+#[derive(Debug, Clone)]
+pub struct FileTransfer {
+    pub viewer: ViewerIdentifier,
+    pub id: u32,
+    pub direction: TransferDirection,
+    pub name: String,
+    pub size: u64,
+    /// Bytes received, or sent when the file is outgoing.
+    pub received: u64,
+    pub state: TransferState,
+}
+
+impl FileTransfer {
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self.state,
+            TransferState::Pending | TransferState::Receiving | TransferState::Sending
+        )
+    }
+}
+
+struct Transfer {
+    info: FileTransfer,
+    sha256: String,
+    /// The partial file being received, taken out while a chunk is written.
+    file: Option<File>,
+    hasher: Sha256,
+    last_reported: u64,
+    /// The file being sent.
+    source: Option<PathBuf>,
+    /// Stops sending the file.
+    cancel: CancellationToken,
+}
+
+impl Transfer {
+    fn is(&self, uuid: &str, id: u32) -> bool {
+        self.info.viewer.uuid == uuid && self.info.id == id
+    }
+}
+
+/// Receives files from viewers into the download directory, and sends files to them.
+///
+/// Incoming files are written to a partial file named after their checksum and size,
+/// so a transfer that was interrupted resumes where it stopped when the same file is
+/// offered again, even by a viewer that reconnected. The file is moved into place once
+/// its checksum is verified, partial files nobody resumed are deleted after
+/// [`PART_FILE_MAX_AGE`].
+pub struct FileTransferManager {
+    config: FileTransferConfig,
+    viewer_manager: Arc<ViewerManager>,
+    notify_update: Arc<dyn Fn() + Send + Sync>,
+    peers: Mutex<HashMap<String, Sender<FileChannelMessage>>>,
+    transfers: Arc<Mutex<Vec<Transfer>>>,
+    next_id: AtomicU32,
+}
+
+impl FileTransferManager {
+    pub fn new(
+        config: FileTransferConfig,
+        viewer_manager: Arc<ViewerManager>,
+        notify_update: Arc<dyn Fn() + Send + Sync>,
+    ) -> Self {
+        Self {
+            config,
+            viewer_manager,
+            notify_update,
+            peers: Mutex::new(HashMap::new()),
+            transfers: Arc::new(Mutex::new(Vec::new())),
+            next_id: AtomicU32::new(SHARER_ID_BASE),
+        }
+    }
+
+    pub fn download_dir(&self) -> PathBuf {
+        self.config
+            .download_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .or_else(|| UserDirs::new().and_then(|dirs| dirs.download_dir().map(Path::to_path_buf)))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Whether viewers may send files to the sharer.
+    pub fn is_receiving_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub async fn get_transfers(&self) -> Vec<FileTransfer> {
+        self.transfers
+            .lock()
+            .await
+            .iter()
+            .map(|t| t.info.clone())
+            .collect()
+    }
+
+    async fn offer(&self, uuid: &str, id: u32, name: String, size: u64, sha256: String) {
+        let name = sanitize_file_name(&name);
+        let reason = if !self.config.enabled {
+            Some("File transfer is disabled")
+        } else if id >= SHARER_ID_BASE {
+            Some("Invalid id")
+        } else if size > self.config.max_size {
+            Some("File is too large")
+        } else if name.is_none() {
+            Some("Invalid file name")
+        } else if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            Some("Invalid checksum")
+        } else {
+            None
+        };
+        if let Some(reason) = reason {
+            info!("Rejected file offer from {}: {}", uuid, reason);
+            self.send(
+                uuid,
+                TransferMessage::Reject {
+                    id,
+                    reason: reason.to_string(),
+                },
+            )
+            .await;
+            return;
+        }
+
+        let mut transfers = self.transfers.lock().await;
+        transfers.retain(|t| !t.is(uuid, id));
+        let pending = transfers
+            .iter()
+            .filter(|t| {
+                t.info.viewer.uuid == uuid
+                    && t.info.direction == TransferDirection::Incoming
+                    && t.info.state == TransferState::Pending
+            })
+            .count();
+        if pending >= MAX_PENDING_OFFERS {
+            drop(transfers);
+            info!("Rejected file offer from {}: too many pending offers", uuid);
+            self.send(
+                uuid,
+                TransferMessage::Reject {
+                    id,
+                    reason: "Too many pending offers".to_string(),
+                },
+            )
+            .await;
+            return;
+        }
+        drop(transfers);
+
+        let viewer = self.viewer(uuid).await;
+        let name = name.unwrap();
+        info!("{} offers file {} ({} bytes)", viewer.name, name, size);
+
+        let mut transfers = self.transfers.lock().await;
+        transfers.push(Transfer {
+            info: FileTransfer {
+                viewer,
+                id,
+                direction: TransferDirection::Incoming,
+                name,
+                size,
+                received: 0,
+                state: TransferState::Pending,
+            },
+            sha256: sha256.to_ascii_lowercase(),
+            file: None,
+            hasher: Sha256::new(),
+            last_reported: 0,
+            source: None,
+            cancel: CancellationToken::new(),
+        });
+        drop(transfers);
+        (self.notify_update)();
+    }
+
+    /// Offer a file to every connected viewer.
+    pub async fn send_file(&self, path: &Path) {
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => name.to_string(),
+            None => return,
+        };
+        let (size, sha256) = match hash_file(path).await {
+            Ok(hashed) => hashed,
+            Err(e) => {
+                error!("Failed to read {}: {}", path.display(), e);
+                return;
+            }
+        };
+        let uuids: Vec<String> = self.peers.lock().await.keys().cloned().collect();
+        for uuid in uuids {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+            let viewer = self.viewer(&uuid).await;
+            info!("Offering {} to {}", path.display(), viewer.name);
+            self.transfers.lock().await.push(Transfer {
+                info: FileTransfer {
+                    viewer,
+                    id,
+                    direction: TransferDirection::Outgoing,
+                    name: name.clone(),
+                    size,
+                    received: 0,
+                    state: TransferState::Pending,
+                },
+                sha256: sha256.clone(),
+                file: None,
+                hasher: Sha256::new(),
+                last_reported: 0,
+                source: Some(path.to_path_buf()),
+                cancel: CancellationToken::new(),
+            });
+            self.send(
+                &uuid,
+                TransferMessage::Offer {
+                    id,
+                    name: name.clone(),
+                    size,
+                    sha256: sha256.clone(),
+                },
+            )
+            .await;
+        }
+        (self.notify_update)();
+    }
+
+    /// Start sending an offered file from `offset` once the viewer accepted it.
+    async fn start_sending(&self, uuid: &str, id: u32, offset: u64) {
+        let (source, size, cancel) = {
+            let mut transfers = self.transfers.lock().await;
+            let entry = match transfers.iter_mut().find(|t| t.is(uuid, id)) {
+                Some(entry)
+                    if entry.info.direction == TransferDirection::Outgoing
+                        && entry.info.state == TransferState::Pending =>
+                {
+                    entry
+                }
+                _ => return,
+            };
+            entry.info.state = TransferState::Sending;
+            entry.info.received = offset.min(entry.info.size);
+            (
+                entry.source.clone().unwrap(),
+                entry.info.size,
+                entry.cancel.clone(),
+            )
+        };
+        let sender = match self.peers.lock().await.get(uuid).cloned() {
+            Some(sender) => sender,
+            None => return,
+        };
+        (self.notify_update)();
+
+        let transfers = self.transfers.clone();
+        let notify_update = self.notify_update.clone();
+        let uuid = uuid.to_string();
+        tokio::spawn(async move {
+            let result = tokio::select! {
+                result = send_chunks(&source, id, offset, size, &sender) => result,
+                _ = cancel.cancelled() => return,
+            };
+            if let Err(e) = result {
+                error!("Failed to send {}: {}", source.display(), e);
+                if let Some(entry) = transfers.lock().await.iter_mut().find(|t| t.is(&uuid, id)) {
+                    entry.info.state = TransferState::Failed("Failed to read file".to_string());
+                }
+                let message = TransferMessage::Failed {
+                    id,
+                    reason: "Failed to read file".to_string(),
+                };
+                let message = serde_json::to_string(&message).unwrap();
+                sender.send(FileChannelMessage::Control(message)).await.ok();
+                notify_update();
+            }
+        });
+    }
+
+    /// Accept a pending file, resuming from a partial file if there is one.
+    pub async fn accept(&self, transfer: &FileTransfer) {
+        let uuid = &transfer.viewer.uuid;
+        let id = transfer.id;
+        let (part_path, size) = {
+            let mut transfers = self.transfers.lock().await;
+            let (sha256, size) = match transfers.iter().find(|t| t.is(uuid, id)) {
+                Some(entry)
+                    if entry.info.direction == TransferDirection::Incoming
+                        && entry.info.state == TransferState::Pending =>
+                {
+                    (entry.sha256.clone(), entry.info.size)
+                }
+                _ => return,
+            };
+            // the same file offered twice would be written to the same partial file
+            let busy = transfers.iter().any(|t| {
+                t.info.direction == TransferDirection::Incoming
+                    && t.info.state == TransferState::Receiving
+                    && t.sha256 == sha256
+                    && t.info.size == size
+            });
+            if busy {
+                if let Some(entry) = transfers.iter_mut().find(|t| t.is(uuid, id)) {
+                    entry.info.state =
+                        TransferState::Failed("Already receiving this file".to_string());
+                }
+                drop(transfers);
+                self.send(
+                    uuid,
+                    TransferMessage::Reject {
+                        id,
+                        reason: "Already receiving this file".to_string(),
+                    },
+                )
+                .await;
+                (self.notify_update)();
+                return;
+            }
+            (self.part_path(&sha256, size), size)
+        };
+
+        let opened = open_part_file(&part_path, size).await;
+        let mut transfers = self.transfers.lock().await;
+        let entry = match transfers.iter_mut().find(|t| t.is(uuid, id)) {
+            Some(entry) if entry.info.state == TransferState::Pending => entry,
+            _ => return,
+        };
+        match opened {
+            Ok((file, hasher, offset)) => {
+                info!("Receiving {} from offset {}", entry.info.name, offset);
+                entry.file = Some(file);
+                entry.hasher = hasher;
+                entry.info.received = offset;
+                entry.last_reported = offset;
+                entry.info.state = TransferState::Receiving;
+                drop(transfers);
+                self.send(uuid, TransferMessage::Accept { id, offset })
+                    .await;
+                if offset == size {
+                    self.finish(uuid, id).await;
+                }
+            }
+            Err(e) => {
+                error!("Failed to open {}: {}", part_path.display(), e);
+                entry.info.state = TransferState::Failed(e.to_string());
+                drop(transfers);
+                self.send(
+                    uuid,
+                    TransferMessage::Failed {
+                        id,
+                        reason: "Failed to write file".to_string(),
+                    },
+                )
+                .await;
+            }
+        }
+        (self.notify_update)();
+    }
+
+    pub async fn reject(&self, transfer: &FileTransfer) {
+        let uuid = &transfer.viewer.uuid;
+        self.transfers
+            .lock()
+            .await
+            .retain(|t| !t.is(uuid, transfer.id));
+        self.send(
+            uuid,
+            TransferMessage::Reject {
+                id: transfer.id,
+                reason: "Declined by sharer".to_string(),
+            },
+        )
+        .await;
+        (self.notify_update)();
+    }
+
+    /// Stop a transfer, discarding what was received so far.
+    pub async fn cancel(&self, transfer: &FileTransfer) {
+        let uuid = &transfer.viewer.uuid;
+        self.send(uuid, TransferMessage::Cancel { id: transfer.id })
+            .await;
+        self.stop(uuid, transfer.id, "Cancelled", true).await;
+    }
+
+    /// Remove a finished transfer from the list.
+    pub async fn dismiss(&self, transfer: &FileTransfer) {
+        self.transfers
+            .lock()
+            .await
+            .retain(|t| !t.is(&transfer.viewer.uuid, transfer.id) || t.info.is_active());
+        (self.notify_update)();
+    }
+
+    async fn stop(&self, uuid: &str, id: u32, reason: &str, discard: bool) {
+        let mut transfers = self.transfers.lock().await;
+        let part_path = match transfers.iter_mut().find(|t| t.is(uuid, id)) {
+            Some(entry) if entry.info.is_active() => {
+                info!("Transfer of {} stopped: {}", entry.info.name, reason);
+                entry.file = None;
+                entry.cancel.cancel();
+                entry.info.state = TransferState::Failed(reason.to_string());
+                (discard && entry.info.direction == TransferDirection::Incoming)
+                    .then(|| self.part_path(&entry.sha256, entry.info.size))
+            }
+            _ => None,
+        };
+        drop(transfers);
+        if let Some(part_path) = part_path {
+            tokio::fs::remove_file(part_path).await.ok();
+        }
+        (self.notify_update)();
+    }
+
+    /// Apply `update` to a transfer and refresh the GUI.
+    async fn update(&self, uuid: &str, id: u32, update: impl FnOnce(&mut Transfer)) {
+        if let Some(entry) = self
+            .transfers
+            .lock()
+            .await
+            .iter_mut()
+            .find(|t| t.is(uuid, id))
+        {
+            update(entry);
+        }
+        (self.notify_update)();
+    }
+
+    async fn write_chunk(&self, uuid: &str, data: &[u8]) -> Result<()> {
+        if data.len() < CHUNK_HEADER_SIZE {
+            return Err(anyhow!("chunk is too short"));
+        }
+        let id = u32::from_be_bytes(data[0..4].try_into()?);
+        let offset = u64::from_be_bytes(data[4..12].try_into()?);
+        let data = &data[CHUNK_HEADER_SIZE..];
+
+        // The file is taken out of the transfer while it is written, chunks of a
+        // channel are handled one at a time.
+        let (mut file, mut hasher) = {
+            let mut transfers = self.transfers.lock().await;
+            let entry = transfers
+                .iter_mut()
+                .find(|t| t.is(uuid, id) && t.info.state == TransferState::Receiving)
+                .ok_or_else(|| anyhow!("no transfer {} is in progress", id))?;
+            if offset != entry.info.received || offset + data.len() as u64 > entry.info.size {
+                drop(transfers);
+                self.fail(uuid, id, "Unexpected chunk").await;
+                return Err(anyhow!("unexpected chunk at offset {}", offset));
+            }
+            let file = entry.file.take().ok_or_else(|| anyhow!("file is closed"))?;
+            (file, std::mem::take(&mut entry.hasher))
+        };
+        let written = file.write_all(data).await;
+        hasher.update(data);
+
+        let mut transfers = self.transfers.lock().await;
+        let entry = match transfers
+            .iter_mut()
+            .find(|t| t.is(uuid, id) && t.info.state == TransferState::Receiving)
+        {
+            Some(entry) => entry,
+            // stopped while the chunk was written
+            None => return Ok(()),
+        };
+        if let Err(e) = written {
+            drop(transfers);
+            self.fail(uuid, id, "Failed to write file").await;
+            return Err(e.into());
+        }
+        entry.file = Some(file);
+        entry.hasher = hasher;
+        entry.info.received += data.len() as u64;
+
+        let received = entry.info.received;
+        if received == entry.info.size {
+            drop(transfers);
+            self.finish(uuid, id).await;
+            (self.notify_update)();
+        } else if received - entry.last_reported >= PROGRESS_INTERVAL {
+            entry.last_reported = received;
+            drop(transfers);
+            self.send(uuid, TransferMessage::Progress { id, received })
+                .await;
+            (self.notify_update)();
+        }
+        Ok(())
+    }
+
+    /// Verify the checksum of a fully received file and move it into place.
+    async fn finish(&self, uuid: &str, id: u32) {
+        let (file, hasher, sha256, size, name) = {
+            let mut transfers = self.transfers.lock().await;
+            let entry = match transfers.iter_mut().find(|t| t.is(uuid, id)) {
+                Some(entry) => entry,
+                None => return,
+            };
+            (
+                entry.file.take(),
+                std::mem::take(&mut entry.hasher),
+                entry.sha256.clone(),
+                entry.info.size,
+                entry.info.name.clone(),
+            )
+        };
+        let part_path = self.part_path(&sha256, size);
+        if let Some(mut file) = file {
+            file.flush().await.ok();
+        }
+
+        let digest = format!("{:x}", hasher.finalize());
+        let state = if digest != sha256 {
+            warn!("Checksum mismatch for {}", name);
+            tokio::fs::remove_file(&part_path).await.ok();
+            Err("Checksum mismatch")
+        } else {
+            let path = unique_path(&self.download_dir(), &name);
+            match tokio::fs::rename(&part_path, &path).await {
+                Ok(()) => {
+                    info!("Received {}", path.display());
+                    Ok(path)
+                }
+                Err(e) => {
+                    error!("Failed to move {}: {}", part_path.display(), e);
+                    Err("Failed to save file")
+                }
+            }
+        };
+
+        if let Some(entry) = self
+            .transfers
+            .lock()
+            .await
+            .iter_mut()
+            .find(|t| t.is(uuid, id))
+        {
+            entry.info.state = match &state {
+                Ok(path) => TransferState::Completed(path.clone()),
+                Err(reason) => TransferState::Failed(reason.to_string()),
+            };
+        }
+        let message = match state {
+            Ok(_) => TransferMessage::Complete { id },
+            Err(reason) => TransferMessage::Failed {
+                id,
+                reason: reason.to_string(),
+            },
+        };
+        self.send(uuid, message).await;
+    }
+
+    async fn fail(&self, uuid: &str, id: u32, reason: &str) {
+        self.send(
+            uuid,
+            TransferMessage::Failed {
+                id,
+                reason: reason.to_string(),
+            },
+        )
+        .await;
+        self.stop(uuid, id, reason, false).await;
+    }
+
+    async fn send(&self, uuid: &str, message: TransferMessage) {
+        let sender = self.peers.lock().await.get(uuid).cloned();
+        if let Some(sender) = sender {
+            let message = serde_json::to_string(&message).unwrap();
+            sender
+                .send(FileChannelMessage::Control(message))
+                .await
+                .unwrap_or_else(|_| warn!("Failed to send file transfer message"));
+        }
+    }
+
+    async fn viewer(&self, uuid: &str) -> ViewerIdentifier {
+        self.viewer_manager
+            .get_viewing_viewers()
+            .await
+            .into_iter()
+            .find(|v| v.uuid == uuid)
+            .unwrap_or_else(|| ViewerIdentifier {
+                uuid: uuid.to_string(),
+                name: uuid.to_string(),
+            })
+    }
+
+    /// The partial file of an incoming file, shared by every offer of the same content
+    /// so a transfer resumes whichever viewer and transfer id it comes back with.
+    fn part_path(&self, sha256: &str, size: u64) -> PathBuf {
+        self.download_dir()
+            .join(format!(".{}-{}.part", sha256, size))
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for FileTransferManager {
+    type Message = FileChannelMessage;
+
+    async fn peer_connected(&self, uuid: String, sender: Sender<FileChannelMessage>) {
+        self.peers.lock().await.insert(uuid, sender);
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let data = &message.data;
+        if !message.is_string {
+            if let Err(e) = self.write_chunk(uuid, data).await {
+                warn!("Failed to receive file chunk: {}", e);
+            }
+            return;
+        }
+        match serde_json::from_slice::<TransferMessage>(data) {
+            Ok(TransferMessage::Offer {
+                id,
+                name,
+                size,
+                sha256,
+            }) => self.offer(uuid, id, name, size, sha256).await,
+            Ok(TransferMessage::Accept { id, offset }) => {
+                self.start_sending(uuid, id, offset).await
+            }
+            Ok(TransferMessage::Reject { id, reason }) => {
+                self.stop(uuid, id, &format!("Declined: {}", reason), false)
+                    .await;
+            }
+            Ok(TransferMessage::Progress { id, received }) => {
+                self.update(uuid, id, |transfer| {
+                    if transfer.info.direction == TransferDirection::Outgoing {
+                        transfer.info.received = received.min(transfer.info.size);
+                    }
+                })
+                .await;
+            }
+            Ok(TransferMessage::Complete { id }) => {
+                self.update(uuid, id, |transfer| {
+                    if let Some(source) = &transfer.source {
+                        info!("Sent {} to {}", source.display(), transfer.info.viewer.name);
+                        transfer.info.received = transfer.info.size;
+                        transfer.info.state = TransferState::Completed(source.clone());
+                    }
+                })
+                .await;
+            }
+            Ok(TransferMessage::Failed { id, reason }) => {
+                self.stop(uuid, id, &reason, false).await;
+            }
+            Ok(TransferMessage::Cancel { id }) => {
+                self.stop(uuid, id, "Cancelled by viewer", true).await;
+            }
+            Err(e) => warn!("Invalid file transfer message: {}", e),
+        }
+    }
+
+    /// Stop the transfers of a peer. Partial files are kept so they can be resumed.
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().await.remove(uuid);
+        let mut transfers = self.transfers.lock().await;
+        transfers.retain(|t| {
+            !(t.info.viewer.uuid == uuid
+                && t.info.direction == TransferDirection::Incoming
+                && t.info.state == TransferState::Pending)
+        });
+        for transfer in transfers.iter_mut() {
+            if transfer.info.viewer.uuid == uuid && transfer.info.is_active() {
+                transfer.file = None;
+                transfer.cancel.cancel();
+                transfer.info.state = TransferState::Failed("Viewer disconnected".to_string());
+            }
+        }
+        drop(transfers);
+        (self.notify_update)();
+    }
+
+    async fn clear(&self) {
+        self.peers.lock().await.clear();
+        for transfer in self.transfers.lock().await.drain(..) {
+            transfer.cancel.cancel();
+        }
+        if let Err(e) = remove_stale_part_files(&self.download_dir()).await {
+            warn!("Failed to remove stale partial files: {}", e);
+        }
+    }
+}
+
+/// Send the file at `path` from `offset` as chunks of transfer `id`.
+async fn send_chunks(
+    path: &Path,
+    id: u32,
+    offset: u64,
+    size: u64,
+    sender: &Sender<FileChannelMessage>,
+) -> Result<()> {
+    let mut file = File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    let mut offset = offset;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    while offset < size {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(anyhow!("file is shorter than offered"));
+        }
+        let mut chunk = BytesMut::with_capacity(CHUNK_HEADER_SIZE + read);
+        chunk.put_u32(id);
+        chunk.put_u64(offset);
+        chunk.put_slice(&buffer[..read]);
+        sender
+            .send(FileChannelMessage::Chunk(chunk.freeze()))
+            .await
+            .map_err(|_| anyhow!("peer disconnected"))?;
+        offset += read as u64;
+    }
+    Ok(())
+}
+
+/// Get the size and hex sha256 digest of the file at `path`.
+async fn hash_file(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
+
+/// Open the partial file at `path` for appending, returning it along with the hash of
+/// its current content and its length.
+async fn open_part_file(path: &Path, size: u64) -> Result<(File, Sha256, u64)> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut hasher = Sha256::new();
+    let mut offset = 0;
+    if let Ok(mut file) = File::open(path).await {
+        if file.metadata().await?.len() <= size {
+            let mut buffer = vec![0; 64 * 1024];
+            loop {
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+                offset += read as u64;
+            }
+        }
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(offset > 0)
+        .truncate(offset == 0)
+        .open(path)
+        .await?;
+    Ok((file, hasher, offset))
+}
+
+/// Delete the partial files in `dir` that were not written to for [`PART_FILE_MAX_AGE`].
+async fn remove_stale_part_files(dir: &Path) -> Result<()> {
+    let mut entries = match tokio::fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        let is_part_file = entry.file_name().to_str().map_or(false, is_part_file_name);
+        if !is_part_file {
+            continue;
+        }
+        let age = entry
+            .metadata()
+            .await?
+            .modified()?
+            .elapsed()
+            .unwrap_or_default();
+        if age >= PART_FILE_MAX_AGE {
+            info!("Removing stale partial file {}", entry.path().display());
+            tokio::fs::remove_file(entry.path()).await.ok();
+        }
+    }
+    Ok(())
+}
+
+/// Whether `name` is that of a partial file, `.{sha256}-{size}.part`.
+fn is_part_file_name(name: &str) -> bool {
+    let stem = match name
+        .strip_prefix('.')
+        .and_then(|name| name.strip_suffix(".part"))
+    {
+        Some(stem) => stem,
+        None => return false,
+    };
+    match stem.split_once('-') {
+        Some((sha256, size)) => {
+            sha256.len() == 64
+                && sha256.chars().all(|c| c.is_ascii_hexdigit())
+                && !size.is_empty()
+                && size.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+/// Check a file name sent by a viewer, which must be a plain file name that is valid
+/// on every platform, so it can neither escape the download directory nor refer to
+/// a device or an alternate data stream. Leading dots are stripped so the file is not
+/// hidden, as are the trailing dots and spaces Windows ignores.
+fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' ']);
+    let valid = !name.is_empty()
+        && name.len() <= 255
+        && !name
+            .chars()
+            .any(|c| c.is_control() || INVALID_CHARACTERS.contains(&c))
+        && !is_reserved_name(name)
+        && matches!(
+            Path::new(name).components().collect::<Vec<_>>()[..],
+            [Component::Normal(_)]
+        );
+    valid.then(|| name.to_string())
+}
+
+/// Whether Windows reserves `name` for a device, with or without an extension.
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    let stem = stem.to_ascii_uppercase();
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" | "CONIN$" | "CONOUT$" => true,
+        _ => {
+            stem.len() == 4
+                && (stem.starts_with("COM") || stem.starts_with("LPT"))
+                && stem.as_bytes()[3].is_ascii_digit()
+        }
+    }
+}
+
+/// Get a path in `dir` for `name` that does not exist yet, appending a number if needed.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|i| dir.join(format!("{} ({}){}", stem, i, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_file_names() {
+        for name in [
+            "report.pdf",
+            "photo 1.jpg",
+            "CONFIG.txt",
+            "con-notes.md",
+            "日本語.txt",
+        ] {
+            assert_eq!(sanitize_file_name(name).as_deref(), Some(name));
+        }
+    }
+
+    #[test]
+    fn strips_leading_dots_and_trailing_dots_and_spaces() {
+        assert_eq!(sanitize_file_name(".bashrc").as_deref(), Some("bashrc"));
+        assert_eq!(
+            sanitize_file_name(" notes.txt. ").as_deref(),
+            Some("notes.txt")
+        );
+        assert_eq!(
+            sanitize_file_name("file.txt...").as_deref(),
+            Some("file.txt")
+        );
+    }
+
+    #[test]
+    fn rejects_paths() {
+        for name in [
+            "../evil.exe",
+            "..\\evil.exe",
+            "dir/file.txt",
+            "dir\\file.txt",
+            "/etc/passwd",
+            "\\\\server\\share\\file",
+            "C:evil.exe",
+            "C:\\Windows\\evil.exe",
+            "..",
+            ".",
+        ] {
+            assert_eq!(sanitize_file_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_streams_and_invalid_characters() {
+        for name in [
+            "file.txt:stream",
+            "file.txt::$DATA",
+            "what?.txt",
+            "a*b",
+            "a<b>",
+            "a|b",
+            "quote\".txt",
+            "new\nline",
+            "nul\0byte",
+        ] {
+            assert_eq!(sanitize_file_name(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        for name in [
+            "CON",
+            "con",
+            "PRN.txt",
+            "aux.log",
+            "NUL",
+            "nul.tar.gz",
+            "COM1",
+            "com9.txt",
+            "LPT1",
+            "lpt3.doc",
+            "CON .txt",
+        ] {
+            assert_eq!(sanitize_file_name(name), None, "{}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_and_long_names() {
+        assert_eq!(sanitize_file_name(""), None);
+        assert_eq!(sanitize_file_name("   "), None);
+        assert_eq!(sanitize_file_name("..."), None);
+        assert_eq!(sanitize_file_name(&"a".repeat(256)), None);
+    }
+
+    #[test]
+    fn recognizes_part_files() {
+        let sha256 = "0123456789abcdef".repeat(4);
+        assert!(is_part_file_name(&format!(".{}-1024.part", sha256)));
+        assert!(!is_part_file_name(&format!("{}-1024.part", sha256)));
+        assert!(!is_part_file_name(&format!(".{}-.part", sha256)));
+        assert!(!is_part_file_name(&format!(".{}-10k.part", sha256)));
+        assert!(!is_part_file_name(".abc-1024.part"));
+        assert!(!is_part_file_name(".notes.part"));
+        assert!(!is_part_file_name(&format!(".{}-1024", sha256)));
+    }
+}
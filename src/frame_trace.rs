@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde_json::json;
+
+use crate::Result;
+
+/// The timings of a frame, in microseconds since profiling started.
+pub struct FrameTiming {
+    pub index: u64,
+    /// When the previous frame was sent, and the capturer started waiting for this one.
+    /// The wait includes capturing the frame, which the capture sources don't time.
+    pub wait_start: u128,
+    pub received: u128,
+    pub converted: u128,
+    pub encoded: u128,
+    pub sent: u128,
+    /// Size of the encoded frame in bytes.
+    pub size: usize,
+    pub keyframe: bool,
+}
+
+enum Format {
+    /// Chrome trace events, which Perfetto and chrome://tracing open.
+    Chrome,
+    Csv,
+}
+
+/// Writes the timings of every frame to a file, as a Chrome trace or as CSV when
+/// the file name ends with `.csv`.
+pub struct FrameTrace {
+    file: BufWriter<File>,
+    format: Format,
+    empty: bool,
+}
+
+impl FrameTrace {
+    pub fn create(path: &Path) -> Result<Self> {
+        let format = match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::Chrome,
+        };
+        let mut file = BufWriter::new(File::create(path)?);
+        match format {
+            Format::Chrome => writeln!(file, "[")?,
+            Format::Csv => writeln!(
+                file,
+                "frame,start_us,wait_us,convert_us,encode_us,send_us,total_us,size,type"
+            )?,
+        }
+        Ok(Self {
+            file,
+            format,
+            empty: true,
+        })
+    }
+
+    pub fn record(&mut self, frame: &FrameTiming) -> Result<()> {
+        let frame_type = if frame.keyframe { "key" } else { "delta" };
+        match self.format {
+            Format::Chrome => {
+                let spans = [
+                    ("wait", frame.wait_start, frame.received),
+                    ("convert", frame.received, frame.converted),
+                    ("encode", frame.converted, frame.encoded),
+                    ("send", frame.encoded, frame.sent),
+                ];
+                self.event(json!({
+                    "name": "frame",
+                    "cat": "frame",
+                    "ph": "X",
+                    "ts": frame.received as u64,
+                    "dur": (frame.sent - frame.received) as u64,
+                    "pid": 1,
+                    "tid": 1,
+                    "args": { "frame": frame.index, "size": frame.size, "type": frame_type },
+                }))?;
+                for (name, start, end) in spans {
+                    self.event(json!({
+                        "name": name,
+                        "cat": "stage",
+                        "ph": "X",
+                        "ts": start as u64,
+                        "dur": end.saturating_sub(start) as u64,
+                        "pid": 1,
+                        "tid": 1,
+                        "args": { "frame": frame.index },
+                    }))?;
+                }
+                self.event(json!({
+                    "name": "encoded size",
+                    "ph": "C",
+                    "ts": frame.encoded as u64,
+                    "pid": 1,
+                    "args": { "bytes": frame.size },
+                }))?;
+            }
+            Format::Csv => writeln!(
+                self.file,
+                "{},{},{},{},{},{},{},{},{}",
+                frame.index,
+                frame.received,
+                frame.received.saturating_sub(frame.wait_start),
+                frame.converted - frame.received,
+                frame.encoded - frame.converted,
+                frame.sent - frame.encoded,
+                frame.sent - frame.received,
+                frame.size,
+                frame_type
+            )?,
+        }
+        Ok(())
+    }
+
+    fn event(&mut self, event: serde_json::Value) -> Result<()> {
+        if !self.empty {
+            writeln!(self.file, ",")?;
+        }
+        self.empty = false;
+        write!(self.file, "{}", event)?;
+        Ok(())
+    }
+}
+
+impl Drop for FrameTrace {
+    fn drop(&mut self) {
+        // the closing bracket is optional, so a trace cut short still opens
+        if let Format::Chrome = self.format {
+            writeln!(self.file, "\n]").ok();
+        }
+        self.file.flush().ok();
+    }
+}
@@ -1,5 +1,6 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
 use directories::ProjectDirs;
@@ -18,8 +19,14 @@ use crate::gui::theme::widget::Element;
 use crate::gui::theme::Theme;
 use crate::{column_iced, config};
 
+/// How often the sharing page is redrawn to animate the audio level meter.
+const AUDIO_LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the sharing page is redrawn to show the latest stats otherwise.
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct App {
     capturer: Capturer,
+    config_path: PathBuf,
     pub start_page: StartPage,
     pub sharing_page: SharingPage,
     intermediate_update_receiver: Option<Receiver<()>>,
@@ -48,7 +55,7 @@ impl Application for App {
         } else {
             if cfg!(target_os = "windows") {
                 Path::new("config.toml").to_path_buf()
-            } else if cfg!(target_os = "macos") {
+            } else if cfg!(any(target_os = "macos", target_os = "linux")) {
                 let config_dir = ProjectDirs::from("", "", "Mira Sharer")
                     .unwrap()
                     .config_dir()
@@ -73,7 +80,8 @@ impl Application for App {
         (
             App {
                 capturer,
-                start_page: StartPage {},
+                config_path,
+                start_page: StartPage::new(),
                 sharing_page: SharingPage::new(),
                 intermediate_update_receiver: Some(intermediate_update_receiver),
             },
@@ -91,15 +99,24 @@ impl Application for App {
                 message,
                 start::UpdateProps {
                     capturer: &mut self.capturer,
+                    config_path: self.config_path.as_path(),
                 },
             ),
             Message::Sharing(message) => {
                 let viewer_manager = self.capturer.get_viewer_manager();
+                let file_transfer_manager = self.capturer.get_file_transfer_manager();
+                let chat_manager = self.capturer.get_chat_manager();
+                let annotation_manager = self.capturer.get_annotation_manager();
+                let input_handler = self.capturer.get_input_handler();
                 self.sharing_page.update(
                     message,
                     sharing::UpdateProps {
                         capturer: &mut self.capturer,
                         viewer_manager,
+                        file_transfer_manager,
+                        chat_manager,
+                        annotation_manager,
+                        input_handler,
                     },
                 )
             }
@@ -120,14 +137,31 @@ impl Application for App {
         let is_sharing = self.capturer.is_running();
         let element: Element<Message> = row![column_iced![if is_sharing {
             let viewer_manager = self.capturer.get_viewer_manager();
+            let file_transfer_manager = self.capturer.get_file_transfer_manager();
+            let chat_manager = self.capturer.get_chat_manager();
+            let input_handler = self.capturer.get_input_handler();
+            let viewer_latencies = self.capturer.get_latency_manager().latencies();
+            let viewer_stats = self.capturer.get_stats_manager().stats();
+            let download_dir = file_transfer_manager.download_dir();
+            let receiving_files = file_transfer_manager.is_receiving_enabled();
             let handle = tokio::runtime::Handle::current();
-            let (pending_viewers, viewing_viewers) = tokio::task::block_in_place(move || {
-                handle.block_on(async move {
-                    let pending_viewers = viewer_manager.get_pending_viewers().await;
-                    let viewing_viewers = viewer_manager.get_viewing_viewers().await;
-                    (pending_viewers, viewing_viewers)
-                })
-            });
+            let (pending_viewers, viewing_viewers, viewer_controls, file_transfers, chat_messages) =
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        let pending_viewers = viewer_manager.get_pending_viewers().await;
+                        let viewing_viewers = viewer_manager.get_viewing_viewers().await;
+                        let viewer_controls = input_handler.get_controls().await;
+                        let file_transfers = file_transfer_manager.get_transfers().await;
+                        let chat_messages = chat_manager.get_messages().await;
+                        (
+                            pending_viewers,
+                            viewing_viewers,
+                            viewer_controls,
+                            file_transfers,
+                            chat_messages,
+                        )
+                    })
+                });
 
             self.sharing_page.view(sharing::ViewProps {
                 room_id: self.capturer.get_room_id().unwrap_or_default(),
@@ -135,6 +169,26 @@ impl Application for App {
                 invite_link: self.capturer.get_invite_link().unwrap_or_default(),
                 pending_viewers,
                 viewing_viewers,
+                viewer_controls,
+                viewer_latencies,
+                viewer_stats,
+                performance: self.capturer.performance(),
+                audio_level: (self.capturer.config.audio.system.enabled
+                    || self.capturer.config.audio.microphone.enabled)
+                    .then(|| self.capturer.audio_level()),
+                system_audio_muted: self
+                    .capturer
+                    .config
+                    .audio
+                    .system
+                    .enabled
+                    .then(|| self.capturer.is_system_audio_muted()),
+                clipboard_enabled: self.capturer.is_clipboard_enabled(),
+                annotations_enabled: self.capturer.get_annotation_manager().is_enabled(),
+                file_transfers,
+                download_dir: download_dir.display().to_string(),
+                receiving_files,
+                chat_messages,
             })
         } else {
             self.start_page.view(start::ViewProps {
@@ -154,13 +208,35 @@ impl Application for App {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        iced::subscription::channel("updates", 10, |mut s| async move {
+        let updates = iced::subscription::channel("updates", 10, |mut s| async move {
             let (sender, mut receiver) = channel(10);
             s.send(Message::UpdateChannel(sender)).await.unwrap();
             loop {
                 let _ = receiver.recv().await;
                 s.send(Message::Ignore).await.unwrap();
             }
-        })
+        });
+        if !self.capturer.is_running() {
+            return updates;
+        }
+        let dropped_files = iced::subscription::events_with(|event, _| match event {
+            iced::Event::Window(iced::window::Event::FileDropped(path)) => {
+                Some(Message::Sharing(sharing::Message::SendFile(path)))
+            }
+            _ => None,
+        });
+        // Viewer stats and the performance are sampled every second, and redrawn as
+        // often. The audio level meter is animated only when there is audio.
+        let audio = &self.capturer.config.audio;
+        let redraw_interval = if audio.system.enabled || audio.microphone.enabled {
+            AUDIO_LEVEL_INTERVAL
+        } else {
+            STATS_INTERVAL
+        };
+        Subscription::batch([
+            updates,
+            dropped_files,
+            iced::time::every(redraw_interval).map(|_| Message::Ignore),
+        ])
     }
 }
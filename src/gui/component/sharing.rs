@@ -1,31 +1,47 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use iced::alignment::Horizontal;
-use iced::widget::{container, horizontal_space, row, scrollable, text_input, vertical_space};
+use iced::widget::scrollable::RelativeOffset;
+use iced::widget::{
+    container, horizontal_space, progress_bar, row, scrollable, text_input, vertical_space,
+};
 use iced::Alignment::Center;
-use iced::Length::{Fill, Shrink};
+use iced::Length::{Fill, Fixed, Shrink};
 use iced::{clipboard, Command};
 use iced_aw::TabLabel;
 
+use crate::annotation::AnnotationManager;
 use crate::auth::{ViewerIdentifier, ViewerManager};
+use crate::capture::audio::AudioLevel;
 use crate::capture::capturer::Capturer;
+use crate::chat::{ChatManager, ChatMessage};
 use crate::column_iced;
-use crate::gui::component::avatar::text_avatar;
+use crate::file_transfer::{FileTransfer, FileTransferManager, TransferDirection, TransferState};
+use crate::gui::component::avatar::{avatar, text_avatar};
 use crate::gui::component::Component;
 use crate::gui::theme::button::{FilledButton, IconButton};
 use crate::gui::theme::icon::Icon;
+use crate::gui::theme::progress_bar::Style as ProgressBarStyle;
 use crate::gui::theme::tab::Tab;
 use crate::gui::theme::text;
-use crate::gui::theme::text::text;
+use crate::gui::theme::text::{bold, icon, text};
 use crate::gui::theme::widget::Element;
 use crate::gui::theme::widget::Tabs;
 use crate::gui::theme::{button, PaletteColor};
 use crate::gui::{app, resource};
+use crate::inputs::{ControlPermission, InputHandler, ViewerControl};
+use crate::latency::ViewerLatency;
+use crate::performance_profiler::PerformanceSnapshot;
+use crate::stats::PeerStats;
 
 pub struct SharingPage {
     current_tab: usize,
     invite_tab: InviteTab,
     viewers_tab: ViewersTab,
+    files_tab: FilesTab,
+    chat_tab: ChatTab,
 }
 
 impl SharingPage {
@@ -34,6 +50,10 @@ impl SharingPage {
             current_tab: Default::default(),
             invite_tab: InviteTab {},
             viewers_tab: ViewersTab {},
+            files_tab: FilesTab {},
+            chat_tab: ChatTab {
+                draft: String::new(),
+            },
         }
     }
 }
@@ -42,9 +62,19 @@ pub struct InviteTab {}
 
 pub struct ViewersTab {}
 
+pub struct FilesTab {}
+
+pub struct ChatTab {
+    draft: String,
+}
+
 pub struct UpdateProps<'a> {
     pub capturer: &'a mut Capturer,
     pub viewer_manager: Arc<ViewerManager>,
+    pub file_transfer_manager: Arc<FileTransferManager>,
+    pub chat_manager: Arc<ChatManager>,
+    pub annotation_manager: Arc<AnnotationManager>,
+    pub input_handler: Arc<InputHandler>,
 }
 
 #[derive(Clone, Debug)]
@@ -54,6 +84,25 @@ pub struct ViewProps {
     pub invite_link: String,
     pub viewing_viewers: Vec<ViewerIdentifier>,
     pub pending_viewers: Vec<ViewerIdentifier>,
+    /// The control state of connected viewers, by uuid.
+    pub viewer_controls: HashMap<String, ViewerControl>,
+    /// The measured latencies of connected viewers, by uuid.
+    pub viewer_latencies: HashMap<String, ViewerLatency>,
+    /// The WebRTC statistics of connected viewers, by uuid.
+    pub viewer_stats: HashMap<String, PeerStats>,
+    /// The frame rate, bitrate and frame times of the stream.
+    pub performance: PerformanceSnapshot,
+    /// The level of the audio sent to viewers, or `None` if no audio is captured.
+    pub audio_level: Option<AudioLevel>,
+    /// Whether system audio is muted, or `None` if it is not captured.
+    pub system_audio_muted: Option<bool>,
+    pub clipboard_enabled: bool,
+    pub annotations_enabled: bool,
+    pub file_transfers: Vec<FileTransfer>,
+    pub download_dir: String,
+    /// Whether viewers may send files to the sharer.
+    pub receiving_files: bool,
+    pub chat_messages: Vec<ChatMessage>,
 }
 
 #[derive(Clone, Debug)]
@@ -66,6 +115,21 @@ pub enum Message {
     DeclineJoin(ViewerIdentifier),
     AcceptJoin(ViewerIdentifier),
     KickViewer(ViewerIdentifier),
+    SetControlPermission(ViewerIdentifier, ControlPermission),
+    GrantControl(ViewerIdentifier),
+    RevokeControl,
+    ToggleSystemAudioMute,
+    ToggleClipboard,
+    ToggleAnnotations,
+    ClearAnnotations,
+    AcceptFile(FileTransfer),
+    RejectFile(FileTransfer),
+    CancelFile(FileTransfer),
+    DismissFile(FileTransfer),
+    /// A file was dropped onto the window, to be sent to viewers.
+    SendFile(PathBuf),
+    ChatDraftChanged(String),
+    SendChat,
 }
 
 impl From<Message> for app::Message {
@@ -103,6 +167,88 @@ impl<'a> Component<'a> for SharingPage {
             Message::Stop => {
                 props.capturer.shutdown();
             }
+            Message::ToggleSystemAudioMute => {
+                let muted = props.capturer.is_system_audio_muted();
+                props.capturer.set_system_audio_muted(!muted);
+            }
+            Message::ToggleClipboard => {
+                let enabled = props.capturer.is_clipboard_enabled();
+                props.capturer.set_clipboard_enabled(!enabled);
+            }
+            Message::ToggleAnnotations => {
+                let annotation_manager = props.annotation_manager.clone();
+                let handle = tokio::runtime::Handle::current();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        let enabled = annotation_manager.is_enabled();
+                        annotation_manager.set_enabled(!enabled).await;
+                    })
+                });
+            }
+            Message::ClearAnnotations => {
+                let annotation_manager = props.annotation_manager.clone();
+                let handle = tokio::runtime::Handle::current();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        annotation_manager.clear_annotations().await;
+                    })
+                });
+            }
+            Message::AcceptFile(transfer) => {
+                // resuming hashes what was already received, don't block the GUI on it
+                let file_transfer_manager = props.file_transfer_manager.clone();
+                tokio::spawn(async move {
+                    file_transfer_manager.accept(&transfer).await;
+                });
+            }
+            Message::RejectFile(transfer) => {
+                let handle = tokio::runtime::Handle::current();
+                let file_transfer_manager = props.file_transfer_manager.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        file_transfer_manager.reject(&transfer).await;
+                    })
+                });
+            }
+            Message::CancelFile(transfer) => {
+                let handle = tokio::runtime::Handle::current();
+                let file_transfer_manager = props.file_transfer_manager.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        file_transfer_manager.cancel(&transfer).await;
+                    })
+                });
+            }
+            Message::DismissFile(transfer) => {
+                let handle = tokio::runtime::Handle::current();
+                let file_transfer_manager = props.file_transfer_manager.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        file_transfer_manager.dismiss(&transfer).await;
+                    })
+                });
+            }
+            Message::SendFile(path) => {
+                // hashing a large file takes a while, don't block the GUI on it
+                let file_transfer_manager = props.file_transfer_manager.clone();
+                tokio::spawn(async move {
+                    file_transfer_manager.send_file(&path).await;
+                });
+            }
+            Message::ChatDraftChanged(draft) => {
+                self.chat_tab.draft = draft;
+            }
+            Message::SendChat => {
+                let text = std::mem::take(&mut self.chat_tab.draft);
+                let handle = tokio::runtime::Handle::current();
+                let chat_manager = props.chat_manager.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        chat_manager.send(text).await;
+                    })
+                });
+                return scrollable::snap_to(chat_scrollable_id(), RelativeOffset::END);
+            }
             Message::ChangeTab(tab) => {
                 self.current_tab = tab;
             }
@@ -134,6 +280,35 @@ impl<'a> Component<'a> for SharingPage {
                     })
                 });
             }
+            Message::SetControlPermission(viewer_id, permission) => {
+                let handle = tokio::runtime::Handle::current();
+                let input_handler = props.input_handler.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        input_handler
+                            .set_permission(&viewer_id.uuid, permission)
+                            .await;
+                    })
+                });
+            }
+            Message::GrantControl(viewer_id) => {
+                let handle = tokio::runtime::Handle::current();
+                let input_handler = props.input_handler.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        input_handler.grant(&viewer_id.uuid).await;
+                    })
+                });
+            }
+            Message::RevokeControl => {
+                let handle = tokio::runtime::Handle::current();
+                let input_handler = props.input_handler.clone();
+                tokio::task::block_in_place(move || {
+                    handle.block_on(async move {
+                        input_handler.revoke().await;
+                    })
+                });
+            }
         }
         Command::none()
     }
@@ -148,7 +323,15 @@ impl<'a> Component<'a> for SharingPage {
                     self.invite_tab.tab_label(),
                     self.invite_tab.view(props.clone())
                 )
-                .push(self.viewers_tab.tab_label(), self.viewers_tab.view(props))
+                .push(
+                    self.viewers_tab.tab_label(),
+                    self.viewers_tab.view(props.clone())
+                )
+                .push(
+                    self.files_tab.tab_label(),
+                    self.files_tab.view(props.clone())
+                )
+                .push(self.chat_tab.tab_label(), self.chat_tab.view(props.clone()))
                 .tab_bar_style(Default::default())
                 .icon_font(resource::font::ICON)
                 .text_font(resource::font::BARLOW)
@@ -156,7 +339,7 @@ impl<'a> Component<'a> for SharingPage {
             )
             .height(Fill)
             .width(Fill),
-            action_bar(),
+            action_bar(&props),
         ]
         .align_items(Center)
         .width(Fill)
@@ -165,12 +348,82 @@ impl<'a> Component<'a> for SharingPage {
     }
 }
 
-fn action_bar<'a>() -> Element<'a, app::Message> {
-    row![FilledButton::new("End")
-        .icon(Icon::StopCircle)
-        .style(button::Style::Danger)
+fn action_bar<'a>(props: &ViewProps) -> Element<'a, app::Message> {
+    let mut bar = row![
+        IconButton::new(if props.clipboard_enabled {
+            Icon::ContentPaste
+        } else {
+            Icon::ContentPasteOff
+        })
+        .style(if props.clipboard_enabled {
+            button::Style::Primary
+        } else {
+            button::Style::Default
+        })
+        .filled(props.clipboard_enabled)
+        .build()
+        .on_press(Message::ToggleClipboard.into()),
+        horizontal_space(8),
+        IconButton::new(if props.annotations_enabled {
+            Icon::Draw
+        } else {
+            Icon::EditOff
+        })
+        .style(if props.annotations_enabled {
+            button::Style::Primary
+        } else {
+            button::Style::Default
+        })
+        .filled(props.annotations_enabled)
         .build()
-        .on_press(Message::Stop.into()),]
+        .on_press(Message::ToggleAnnotations.into()),
+        horizontal_space(8),
+        IconButton::new(Icon::LayersClear)
+            .build()
+            .on_press(Message::ClearAnnotations.into()),
+        horizontal_space(8),
+    ]
+    .align_items(Center);
+    if let Some(muted) = props.system_audio_muted {
+        bar = bar
+            .push(
+                IconButton::new(if muted {
+                    Icon::VolumeOff
+                } else {
+                    Icon::VolumeUp
+                })
+                .style(if muted {
+                    button::Style::Danger
+                } else {
+                    button::Style::Default
+                })
+                .filled(muted)
+                .build()
+                .on_press(Message::ToggleSystemAudioMute.into()),
+            )
+            .push(horizontal_space(8));
+    }
+    if let Some(level) = props.audio_level {
+        bar = bar
+            .push(
+                progress_bar(0.0..=1.0, level.meter_value())
+                    .style(if level.is_clipping() {
+                        ProgressBarStyle::Danger
+                    } else {
+                        ProgressBarStyle::Default
+                    })
+                    .width(Fixed(96.))
+                    .height(Fixed(6.)),
+            )
+            .push(horizontal_space(24));
+    }
+    bar.push(
+        FilledButton::new("End")
+            .icon(Icon::StopCircle)
+            .style(button::Style::Danger)
+            .build()
+            .on_press(Message::Stop.into()),
+    )
     .padding([0, 16, 16, 16])
     .into()
 }
@@ -267,6 +520,14 @@ impl Tab for ViewersTab {
 
     fn content(&self, props: Self::Props) -> Element<'_, app::Message> {
         let mut column = vec![];
+        if props.performance.frames > 0 {
+            column.push(
+                text(performance_description(&props.performance))
+                    .size(12)
+                    .style(text::Style::Label)
+                    .into(),
+            );
+        }
         if !props.pending_viewers.is_empty() {
             column.push(text("Pending").size(16).style(text::Style::Label).into());
             for pending in props.pending_viewers.iter() {
@@ -279,7 +540,12 @@ impl Tab for ViewersTab {
             }
             column.push(text("Viewing").size(16).style(text::Style::Label).into());
             for viewing in props.viewing_viewers.iter() {
-                column.push(viewing_viewer_cell(viewing));
+                column.push(viewing_viewer_cell(
+                    viewing,
+                    props.viewer_controls.get(&viewing.uuid).copied(),
+                    props.viewer_latencies.get(&viewing.uuid).copied(),
+                    props.viewer_stats.get(&viewing.uuid),
+                ));
             }
         }
 
@@ -299,21 +565,104 @@ impl Tab for ViewersTab {
     }
 }
 
-fn viewing_viewer_cell<'a>(viewer: &ViewerIdentifier) -> Element<'a, app::Message> {
-    row![
+fn viewing_viewer_cell<'a>(
+    viewer: &ViewerIdentifier,
+    control: Option<ViewerControl>,
+    latency: Option<ViewerLatency>,
+    stats: Option<&PeerStats>,
+) -> Element<'a, app::Message> {
+    let mut description = column_iced![text(viewer.name.clone())].width(Fill);
+    if let Some(latency) = latency.and_then(latency_description) {
+        description = description.push(text(latency).size(12).style(text::Style::Label));
+    }
+    if let Some(stats) = stats.and_then(stats_description) {
+        description = description.push(text(stats).size(12).style(text::Style::Label));
+    }
+    let mut cell = row![
         text_avatar(PaletteColor::Primary, viewer.name.chars().next().unwrap()),
         horizontal_space(16),
-        text(viewer.name.clone()).width(Fill),
+        description,
         horizontal_space(16),
+    ]
+    .align_items(Center);
+    // the viewer's control channel may not be open yet
+    if let Some(control) = control {
+        if control.has_control {
+            cell = cell.push(
+                IconButton::new(Icon::CancelPresentation)
+                    .style(button::Style::Danger)
+                    .filled(true)
+                    .build()
+                    .on_press(Message::RevokeControl.into()),
+            );
+        } else if control.requested && control.permission != ControlPermission::ViewOnly {
+            cell = cell.push(
+                IconButton::new(Icon::PanTool)
+                    .style(button::Style::Success)
+                    .filled(true)
+                    .build()
+                    .on_press(Message::GrantControl(viewer.clone()).into()),
+            );
+        }
+        let icon = match control.permission {
+            ControlPermission::ViewOnly => Icon::Visibility,
+            ControlPermission::MouseOnly => Icon::Mouse,
+            ControlPermission::Full => Icon::Keyboard,
+        };
+        cell = cell.push(horizontal_space(8)).push(
+            IconButton::new(icon)
+                .style(button::Style::Default)
+                .build()
+                .on_press(
+                    Message::SetControlPermission(viewer.clone(), control.permission.next()).into(),
+                ),
+        );
+        cell = cell.push(horizontal_space(8));
+    }
+    cell.push(
         IconButton::new(Icon::PersonRemove)
             .style(button::Style::Danger)
             .build()
             .on_press(Message::KickViewer(viewer.clone()).into()),
-    ]
-    .align_items(Center)
+    )
     .into()
 }
 
+/// e.g. "RTT 24 ms · Input 61 ms", or `None` until the RTT is measured.
+fn latency_description(latency: ViewerLatency) -> Option<String> {
+    let mut description = format!("RTT {} ms", latency.rtt?.as_millis());
+    if let Some(input_to_photon) = latency.input_to_photon {
+        description += &format!(" · Input {} ms", input_to_photon.as_millis());
+    } else if let Some(frame) = latency.frame_marker {
+        description += &format!(" · Frame ~{} ms", frame.as_millis());
+    }
+    Some(description)
+}
+
+/// e.g. "60 FPS · 4.2 Mbps · 7.5 ms p95 · 2 dropped"
+fn performance_description(performance: &PerformanceSnapshot) -> String {
+    let mut description = format!(
+        "{:.0} FPS · {:.1} Mbps · {:.1} ms p95",
+        performance.fps,
+        performance.bitrate / 1000.,
+        performance.total.p95.as_secs_f64() * 1000.
+    );
+    if performance.dropped > 0 {
+        description += &format!(" · {} dropped", performance.dropped);
+    }
+    description
+}
+
+/// e.g. "relay · 2.4 Mbps · 0.5% lost", or `None` until a candidate pair is selected.
+fn stats_description(stats: &PeerStats) -> Option<String> {
+    Some(format!(
+        "{} · {:.1} Mbps · {:.1}% lost",
+        stats.connection_type()?,
+        stats.bitrate / 1000.,
+        stats.fraction_lost * 100.
+    ))
+}
+
 fn pending_viewer_cell<'a>(viewer: &ViewerIdentifier) -> Element<'a, app::Message> {
     row![
         text_avatar(PaletteColor::Primary, viewer.name.chars().next().unwrap()),
@@ -335,3 +684,282 @@ fn pending_viewer_cell<'a>(viewer: &ViewerIdentifier) -> Element<'a, app::Messag
     .align_items(Center)
     .into()
 }
+
+impl Tab for FilesTab {
+    type Message = app::Message;
+    type Props = ViewProps;
+
+    fn title(&self) -> String {
+        String::from("Files")
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::IconText(Icon::FileDownload.into(), self.title())
+    }
+
+    fn content(&self, props: Self::Props) -> Element<'_, app::Message> {
+        let (pending, transfers): (Vec<_>, Vec<_>) = props.file_transfers.iter().partition(|t| {
+            t.direction == TransferDirection::Incoming && t.state == TransferState::Pending
+        });
+
+        let mut column = vec![];
+        if !pending.is_empty() {
+            column.push(text("Incoming").size(16).style(text::Style::Label).into());
+            for transfer in pending {
+                column.push(pending_file_cell(transfer));
+            }
+        }
+        if !transfers.is_empty() {
+            if !column.is_empty() {
+                column.push(vertical_space(2).into());
+            }
+            column.push(text("Transfers").size(16).style(text::Style::Label).into());
+            for transfer in transfers {
+                column.push(file_transfer_cell(transfer));
+            }
+        }
+        if column.is_empty() {
+            column.push(
+                text("Drop files here to send them to viewers")
+                    .size(14)
+                    .style(text::Style::Label)
+                    .into(),
+            );
+        }
+        column.push(
+            text(if props.receiving_files {
+                format!("Files from viewers are saved to {}", props.download_dir)
+            } else {
+                String::from("Receiving files from viewers is disabled")
+            })
+            .size(12)
+            .style(text::Style::Label)
+            .into(),
+        );
+
+        scrollable(
+            container(
+                iced::widget::Column::with_children(column)
+                    .width(Fill)
+                    .max_width(400)
+                    .spacing(16)
+                    .padding([0, 24]),
+            )
+            .width(Fill)
+            .align_x(Horizontal::Center),
+        )
+        .height(Fill)
+        .into()
+    }
+}
+
+fn file_avatar<'a>() -> Element<'a, app::Message> {
+    avatar(
+        PaletteColor::SecondaryContainer,
+        icon(Icon::InsertDriveFile)
+            .size(20)
+            .style(text::Style::Colored(PaletteColor::OnSecondaryContainer))
+            .into(),
+    )
+}
+
+fn file_description<'a>(transfer: &FileTransfer, detail: String) -> Element<'a, app::Message> {
+    column_iced![
+        text(transfer.name.clone()),
+        text(detail).size(12).style(text::Style::Label),
+    ]
+    .width(Fill)
+    .into()
+}
+
+fn pending_file_cell<'a>(transfer: &FileTransfer) -> Element<'a, app::Message> {
+    row![
+        file_avatar(),
+        horizontal_space(16),
+        file_description(
+            transfer,
+            format!(
+                "{} from {}",
+                format_size(transfer.size),
+                transfer.viewer.name
+            )
+        ),
+        horizontal_space(16),
+        IconButton::new(Icon::Done)
+            .style(button::Style::Success)
+            .filled(true)
+            .build()
+            .on_press(Message::AcceptFile(transfer.clone()).into()),
+        horizontal_space(8),
+        IconButton::new(Icon::Close)
+            .style(button::Style::Danger)
+            .filled(true)
+            .build()
+            .on_press(Message::RejectFile(transfer.clone()).into()),
+    ]
+    .align_items(Center)
+    .into()
+}
+
+fn file_transfer_cell<'a>(transfer: &FileTransfer) -> Element<'a, app::Message> {
+    let (detail, action) = match &transfer.state {
+        TransferState::Pending => (
+            format!("Waiting for {}", transfer.viewer.name),
+            Message::CancelFile(transfer.clone()),
+        ),
+        TransferState::Sending => (
+            format!(
+                "{} of {} to {}",
+                format_size(transfer.received),
+                format_size(transfer.size),
+                transfer.viewer.name
+            ),
+            Message::CancelFile(transfer.clone()),
+        ),
+        TransferState::Receiving => (
+            format!(
+                "{} of {}",
+                format_size(transfer.received),
+                format_size(transfer.size)
+            ),
+            Message::CancelFile(transfer.clone()),
+        ),
+        TransferState::Completed(path) => (
+            match transfer.direction {
+                TransferDirection::Incoming => format!("Saved as {}", path.display()),
+                TransferDirection::Outgoing => format!("Sent to {}", transfer.viewer.name),
+            },
+            Message::DismissFile(transfer.clone()),
+        ),
+        TransferState::Failed(reason) => (reason.clone(), Message::DismissFile(transfer.clone())),
+    };
+    let mut description = column_iced![file_description(transfer, detail)].width(Fill);
+    if matches!(
+        transfer.state,
+        TransferState::Receiving | TransferState::Sending
+    ) {
+        description = description.push(vertical_space(4)).push(
+            progress_bar(0.0..=transfer.size.max(1) as f32, transfer.received as f32)
+                .height(Fixed(4.)),
+        );
+    }
+    row![
+        file_avatar(),
+        horizontal_space(16),
+        description,
+        horizontal_space(16),
+        IconButton::new(Icon::Close).build().on_press(action.into()),
+    ]
+    .align_items(Center)
+    .into()
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1024. {
+            break;
+        }
+        size /= 1024.;
+        unit = next;
+    }
+    format!("{:.1} {}", size, unit)
+}
+
+impl Tab for ChatTab {
+    type Message = app::Message;
+    type Props = ViewProps;
+
+    fn title(&self) -> String {
+        String::from("Chat")
+    }
+
+    fn tab_label(&self) -> TabLabel {
+        TabLabel::IconText(Icon::Chat.into(), self.title())
+    }
+
+    fn content(&self, props: Self::Props) -> Element<'_, app::Message> {
+        let messages: Vec<Element<'_, app::Message>> = if props.chat_messages.is_empty() {
+            vec![text("Messages sent here are shown to all viewers")
+                .size(14)
+                .style(text::Style::Label)
+                .into()]
+        } else {
+            props.chat_messages.iter().map(chat_message_cell).collect()
+        };
+
+        column_iced![
+            scrollable(
+                container(
+                    iced::widget::Column::with_children(messages)
+                        .width(Fill)
+                        .max_width(400)
+                        .spacing(12)
+                        .padding([0, 24]),
+                )
+                .width(Fill)
+                .align_x(Horizontal::Center),
+            )
+            .id(chat_scrollable_id())
+            .height(Fill),
+            vertical_space(12),
+            container(
+                column_iced![row![
+                    text_input("Message", &self.draft)
+                        .style(crate::gui::theme::text_input::Style::Outlined)
+                        .font(resource::font::BARLOW)
+                        .size(16)
+                        .padding(10)
+                        .on_input(|draft| Message::ChatDraftChanged(draft).into())
+                        .on_submit(Message::SendChat.into())
+                        .width(Fill),
+                    horizontal_space(8),
+                    IconButton::new(Icon::Send)
+                        .style(button::Style::Primary)
+                        .build()
+                        .on_press(Message::SendChat.into()),
+                ]
+                .align_items(Center)]
+                .width(Fill)
+                .max_width(400)
+                .padding([0, 24]),
+            )
+            .width(Fill)
+            .align_x(Horizontal::Center),
+        ]
+        .height(Fill)
+        .into()
+    }
+}
+
+fn chat_scrollable_id() -> scrollable::Id {
+    scrollable::Id::new("chat")
+}
+
+fn chat_message_cell<'a>(message: &ChatMessage) -> Element<'a, app::Message> {
+    column_iced![
+        row![
+            bold(message.sender.clone())
+                .size(14)
+                .style(if message.from_sharer {
+                    text::Style::Colored(PaletteColor::Primary)
+                } else {
+                    text::Style::Text
+                }),
+            horizontal_space(8),
+            text(message.time.format("%H:%M").to_string())
+                .size(12)
+                .style(text::Style::Label),
+        ]
+        .align_items(Center),
+        vertical_space(2),
+        text(message.text.clone()),
+    ]
+    .width(Fill)
+    .into()
+}
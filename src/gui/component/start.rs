@@ -1,13 +1,17 @@
+use std::path::Path;
+
 use iced::alignment::{Horizontal, Vertical};
-use iced::widget::{container, pick_list, vertical_space};
+use iced::widget::{container, horizontal_space, pick_list, row, slider, vertical_space};
 use iced::Alignment;
 use iced::Alignment::Center;
-use iced::Length::Fill;
+use iced::Length::{Fill, Fixed};
 
+use crate::capture::audio;
 use crate::capture::capturer::Capturer;
 use crate::capture::display::DisplaySelector;
 use crate::capture::ScreenCaptureImpl;
 use crate::column_iced;
+use crate::config::{self, AudioSourceConfig};
 use crate::gui::app;
 use crate::gui::component::Component;
 use crate::gui::theme::button;
@@ -17,12 +21,77 @@ use crate::gui::theme::text;
 use crate::gui::theme::text::text;
 use crate::gui::theme::widget::Element;
 
-pub struct StartPage {}
+pub struct StartPage {
+    system_audio_devices: Vec<AudioDevice>,
+    microphone_devices: Vec<AudioDevice>,
+}
+
+impl StartPage {
+    pub fn new() -> Self {
+        let with_defaults = |devices: Vec<String>| -> Vec<AudioDevice> {
+            [AudioDevice::Off, AudioDevice::Default]
+                .into_iter()
+                .chain(devices.into_iter().map(AudioDevice::Named))
+                .collect()
+        };
+        Self {
+            system_audio_devices: with_defaults(audio::system_devices()),
+            microphone_devices: with_defaults(audio::microphone_devices()),
+        }
+    }
+}
+
+/// An audio device option of the pick lists.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AudioDevice {
+    Off,
+    Default,
+    Named(String),
+}
+
+impl AudioDevice {
+    fn from_config(config: &AudioSourceConfig) -> Self {
+        match (config.enabled, &config.device) {
+            (false, _) => AudioDevice::Off,
+            (true, None) => AudioDevice::Default,
+            (true, Some(name)) => AudioDevice::Named(name.clone()),
+        }
+    }
+
+    fn apply_to(self, config: &mut AudioSourceConfig) {
+        match self {
+            AudioDevice::Off => config.enabled = false,
+            AudioDevice::Default => {
+                config.enabled = true;
+                config.device = None;
+            }
+            AudioDevice::Named(name) => {
+                config.enabled = true;
+                config.device = Some(name);
+            }
+        }
+    }
+}
+
+impl ToString for AudioDevice {
+    fn to_string(&self) -> String {
+        match self {
+            AudioDevice::Off => "Off".to_string(),
+            AudioDevice::Default => "Default".to_string(),
+            AudioDevice::Named(name) => name.clone(),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum Message {
     Start,
     SelectDisplay(<ScreenCaptureImpl as DisplaySelector>::Display),
+    SelectSystemAudio(AudioDevice),
+    SelectMicrophone(AudioDevice),
+    SetSystemAudioGain(f32),
+    SetMicrophoneGain(f32),
+    SaveConfig,
 }
 
 impl From<Message> for app::Message {
@@ -33,6 +102,7 @@ impl From<Message> for app::Message {
 
 pub struct UpdateProps<'a> {
     pub capturer: &'a mut Capturer,
+    pub config_path: &'a Path,
 }
 
 pub struct ViewProps<'a> {
@@ -56,11 +126,29 @@ impl<'a> Component<'a> for StartPage {
             Message::SelectDisplay(display) => {
                 props.capturer.select_display(display);
             }
+            Message::SelectSystemAudio(device) => {
+                device.apply_to(&mut props.capturer.config.audio.system);
+                save_config(props.capturer, props.config_path);
+            }
+            Message::SelectMicrophone(device) => {
+                device.apply_to(&mut props.capturer.config.audio.microphone);
+                save_config(props.capturer, props.config_path);
+            }
+            Message::SetSystemAudioGain(gain) => {
+                props.capturer.config.audio.system.gain = gain;
+            }
+            Message::SetMicrophoneGain(gain) => {
+                props.capturer.config.audio.microphone.gain = gain;
+            }
+            Message::SaveConfig => {
+                save_config(props.capturer, props.config_path);
+            }
         }
         iced::Command::none()
     }
 
     fn view(&self, params: Self::ViewProps) -> Element<'_, app::Message> {
+        let audio_config = &params.capturer.config.audio;
         container(
             column_iced![
                 column_iced![
@@ -72,10 +160,28 @@ impl<'a> Component<'a> for StartPage {
                         move |message| app::Message::Start(Message::SelectDisplay(message))
                     )
                     .width(Fill),
+                    vertical_space(16),
+                    text("System Audio").size(16).style(text::Style::Label),
+                    vertical_space(8),
+                    audio_source_row(
+                        &self.system_audio_devices,
+                        &audio_config.system,
+                        Message::SelectSystemAudio,
+                        Message::SetSystemAudioGain,
+                    ),
+                    vertical_space(16),
+                    text("Microphone").size(16).style(text::Style::Label),
+                    vertical_space(8),
+                    audio_source_row(
+                        &self.microphone_devices,
+                        &audio_config.microphone,
+                        Message::SelectMicrophone,
+                        Message::SetMicrophoneGain,
+                    ),
                 ]
                 .align_items(Alignment::Start)
                 .width(Fill),
-                vertical_space(32),
+                vertical_space(24),
                 FAB::new("Start Sharing", Icon::PlayCircle)
                     .style(button::Style::Primary)
                     .build()
@@ -93,3 +199,37 @@ impl<'a> Component<'a> for StartPage {
         .into()
     }
 }
+
+fn audio_source_row<'a>(
+    devices: &[AudioDevice],
+    config: &AudioSourceConfig,
+    on_select: fn(AudioDevice) -> Message,
+    on_gain: fn(f32) -> Message,
+) -> Element<'a, app::Message> {
+    row![
+        pick_list(
+            devices.to_vec(),
+            Some(AudioDevice::from_config(config)),
+            move |device| on_select(device).into()
+        )
+        .width(Fill),
+        horizontal_space(16),
+        slider(0.0..=2.0, config.gain, move |gain| on_gain(gain).into())
+            .step(0.05)
+            .on_release(Message::SaveConfig.into())
+            .width(Fixed(96.)),
+        horizontal_space(8),
+        text(format!("{}%", (config.gain * 100.).round() as u32))
+            .size(14)
+            .style(text::Style::Label)
+            .width(Fixed(40.)),
+    ]
+    .align_items(Center)
+    .into()
+}
+
+fn save_config(capturer: &Capturer, config_path: &Path) {
+    if let Err(e) = config::save(config_path, &capturer.config) {
+        error!("Failed to save config: {}", e);
+    }
+}
@@ -10,6 +10,22 @@ pub enum Icon {
     Close,
     Done,
     PersonRemove,
+    VolumeUp,
+    VolumeOff,
+    ContentPaste,
+    ContentPasteOff,
+    FileDownload,
+    InsertDriveFile,
+    Chat,
+    Send,
+    Draw,
+    EditOff,
+    LayersClear,
+    PanTool,
+    CancelPresentation,
+    Visibility,
+    Mouse,
+    Keyboard,
 }
 
 impl From<&Icon> for char {
@@ -23,6 +39,22 @@ impl From<&Icon> for char {
             Icon::Close => '\u{e5cd}',
             Icon::Done => '\u{e876}',
             Icon::PersonRemove => '\u{ef66}',
+            Icon::VolumeUp => '\u{e050}',
+            Icon::VolumeOff => '\u{e04f}',
+            Icon::ContentPaste => '\u{e14f}',
+            Icon::ContentPasteOff => '\u{e4f8}',
+            Icon::FileDownload => '\u{e2c4}',
+            Icon::InsertDriveFile => '\u{e24d}',
+            Icon::Chat => '\u{e0b7}',
+            Icon::Send => '\u{e163}',
+            Icon::Draw => '\u{e746}',
+            Icon::EditOff => '\u{e950}',
+            Icon::LayersClear => '\u{e53c}',
+            Icon::PanTool => '\u{e925}',
+            Icon::CancelPresentation => '\u{e0e9}',
+            Icon::Visibility => '\u{e8f4}',
+            Icon::Mouse => '\u{e323}',
+            Icon::Keyboard => '\u{e312}',
         }
     }
 }
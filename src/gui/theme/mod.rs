@@ -8,7 +8,9 @@ pub mod container;
 pub mod icon;
 pub mod menu;
 pub mod picklist;
+pub mod progress_bar;
 pub mod scrollable;
+pub mod slider;
 pub mod svg;
 pub mod tab;
 pub mod text;
@@ -0,0 +1,27 @@
+use iced::widget::progress_bar::{Appearance, StyleSheet};
+use iced::Background;
+
+use crate::gui::theme::Theme;
+
+#[derive(Default)]
+pub enum Style {
+    #[default]
+    Default,
+    Danger,
+}
+
+impl StyleSheet for Theme {
+    type Style = Style;
+
+    fn appearance(&self, style: &Self::Style) -> Appearance {
+        let palette = self.palette();
+        Appearance {
+            background: Background::Color(palette.surface_variant),
+            bar: Background::Color(match style {
+                Style::Default => palette.primary,
+                Style::Danger => palette.error,
+            }),
+            border_radius: 2.,
+        }
+    }
+}
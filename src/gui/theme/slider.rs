@@ -0,0 +1,38 @@
+use iced::widget::slider::{Appearance, Handle, HandleShape, StyleSheet};
+use iced::Color;
+
+use crate::gui::theme::color::ColorExt;
+use crate::gui::theme::Theme;
+
+impl StyleSheet for Theme {
+    type Style = ();
+
+    fn active(&self, _style: &Self::Style) -> Appearance {
+        let palette = self.palette();
+        Appearance {
+            rail_colors: (palette.primary, palette.surface_variant),
+            handle: Handle {
+                shape: HandleShape::Circle { radius: 8. },
+                color: palette.primary,
+                border_width: 0.,
+                border_color: Color::TRANSPARENT,
+            },
+        }
+    }
+
+    fn hovered(&self, style: &Self::Style) -> Appearance {
+        let base = self.active(style);
+        Appearance {
+            handle: Handle {
+                border_width: 4.,
+                border_color: self.palette().primary.with_alpha(0.12),
+                ..base.handle
+            },
+            ..base
+        }
+    }
+
+    fn dragging(&self, style: &Self::Style) -> Appearance {
+        self.hovered(style)
+    }
+}
@@ -9,6 +9,9 @@ use crate::gui::theme::Theme;
 pub enum Style {
     #[default]
     Selectable,
+    /// Material Design 3 Outlined Text Field
+    /// https://m3.material.io/components/text-fields/specs#68b00bd6-ab40-4b4f-93d9-ed1fbbc5d06e
+    Outlined,
 }
 
 impl StyleSheet for Theme {
@@ -23,42 +26,61 @@ impl StyleSheet for Theme {
                 border_color: Color::BLACK,
                 icon_color: Color::BLACK,
             },
+            Style::Outlined => Appearance {
+                background: Color::TRANSPARENT.into(),
+                border_radius: 4.0,
+                border_width: 1.0,
+                border_color: self.palette().outline,
+                icon_color: self.palette().on_surface_variant,
+            },
         }
     }
 
     fn focused(&self, style: &Self::Style) -> Appearance {
         match style {
             Style::Selectable => self.active(style),
+            Style::Outlined => Appearance {
+                border_width: 2.0,
+                border_color: self.palette().primary,
+                ..self.active(style)
+            },
         }
     }
 
     fn placeholder_color(&self, style: &Self::Style) -> Color {
         match style {
             Style::Selectable => Color::TRANSPARENT,
+            Style::Outlined => self.palette().on_surface_variant,
         }
     }
 
     fn value_color(&self, style: &Self::Style) -> Color {
         match style {
             Style::Selectable => self.palette().on_background,
+            Style::Outlined => self.palette().on_surface,
         }
     }
 
     fn disabled_color(&self, style: &Self::Style) -> Color {
         match style {
             Style::Selectable => Color::TRANSPARENT,
+            Style::Outlined => self.palette().on_surface.with_alpha(0.38),
         }
     }
 
     fn selection_color(&self, style: &Self::Style) -> Color {
         match style {
-            Style::Selectable => self.palette().primary.with_alpha(0.2),
+            Style::Selectable | Style::Outlined => self.palette().primary.with_alpha(0.2),
         }
     }
 
     fn disabled(&self, style: &Self::Style) -> Appearance {
         match style {
             Style::Selectable => self.active(style),
+            Style::Outlined => Appearance {
+                border_color: self.palette().on_surface.with_alpha(0.12),
+                ..self.active(style)
+            },
         }
     }
 }
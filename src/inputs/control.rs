@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// What a viewer may control once it is granted control.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlPermission {
+    ViewOnly,
+    MouseOnly,
+    Full,
+}
+
+impl ControlPermission {
+    /// The permission after this one, to cycle through them from the GUI.
+    pub fn next(self) -> Self {
+        match self {
+            ControlPermission::ViewOnly => ControlPermission::MouseOnly,
+            ControlPermission::MouseOnly => ControlPermission::Full,
+            ControlPermission::Full => ControlPermission::ViewOnly,
+        }
+    }
+}
+
+/// The control state of a viewer, shown in the GUI and sent to the viewer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct ViewerControl {
+    pub permission: ControlPermission,
+    /// Whether the viewer currently drives the sharer.
+    pub has_control: bool,
+    /// Whether the viewer asked for control and waits for the sharer to grant it.
+    pub requested: bool,
+}
+
+/// Who may control the sharer. Only one viewer, the controller, has its input
+/// applied at a time, and only the kind of input its permission allows.
+pub(super) struct ControlState {
+    default_permission: ControlPermission,
+    permissions: HashMap<String, ControlPermission>,
+    requests: HashSet<String>,
+    controller: Option<String>,
+}
+
+impl ControlState {
+    pub fn new(default_permission: ControlPermission) -> Self {
+        Self {
+            default_permission,
+            permissions: HashMap::new(),
+            requests: HashSet::new(),
+            controller: None,
+        }
+    }
+
+    pub fn add_viewer(&mut self, uuid: &str) {
+        self.permissions
+            .insert(uuid.to_string(), self.default_permission);
+    }
+
+    pub fn remove_viewer(&mut self, uuid: &str) {
+        self.permissions.remove(uuid);
+        self.requests.remove(uuid);
+        if self.controller.as_deref() == Some(uuid) {
+            self.controller = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.permissions.clear();
+        self.requests.clear();
+        self.controller = None;
+    }
+
+    pub fn viewer(&self, uuid: &str) -> Option<ViewerControl> {
+        self.permissions.get(uuid).map(|permission| ViewerControl {
+            permission: *permission,
+            has_control: self.controller.as_deref() == Some(uuid),
+            requested: self.requests.contains(uuid),
+        })
+    }
+
+    pub fn viewers(&self) -> HashMap<String, ViewerControl> {
+        self.permissions
+            .keys()
+            .filter_map(|uuid| Some((uuid.clone(), self.viewer(uuid)?)))
+            .collect()
+    }
+
+    pub fn controller(&self) -> Option<&str> {
+        self.controller.as_deref()
+    }
+
+    /// Whether input of the viewer is applied, `keyboard` being whether the input
+    /// comes from the keyboard rather than the mouse.
+    pub fn allows(&self, uuid: &str, keyboard: bool) -> bool {
+        if self.controller.as_deref() != Some(uuid) {
+            return false;
+        }
+        match self.permissions.get(uuid) {
+            Some(ControlPermission::Full) => true,
+            Some(ControlPermission::MouseOnly) => !keyboard,
+            Some(ControlPermission::ViewOnly) | None => false,
+        }
+    }
+
+    /// Returns whether the request is new.
+    pub fn request(&mut self, uuid: &str) -> bool {
+        if !self.permissions.contains_key(uuid) || self.controller.as_deref() == Some(uuid) {
+            return false;
+        }
+        self.requests.insert(uuid.to_string())
+    }
+
+    /// Sets the permission of the viewer, returns the viewer that lost control
+    /// because of it, if any.
+    pub fn set_permission(&mut self, uuid: &str, permission: ControlPermission) -> Option<String> {
+        match self.permissions.get_mut(uuid) {
+            Some(p) => *p = permission,
+            None => return None,
+        }
+        if permission == ControlPermission::ViewOnly {
+            self.requests.remove(uuid);
+            if self.controller.as_deref() == Some(uuid) {
+                return self.controller.take();
+            }
+        }
+        None
+    }
+
+    /// Gives control to the viewer, returns the previous controller, if any.
+    pub fn grant(&mut self, uuid: &str) -> Option<String> {
+        match self.permissions.get(uuid) {
+            None | Some(ControlPermission::ViewOnly) => return None,
+            Some(_) => {}
+        }
+        self.requests.remove(uuid);
+        match self.controller.replace(uuid.to_string()) {
+            Some(previous) if previous != uuid => Some(previous),
+            _ => None,
+        }
+    }
+
+    /// Takes control away from whoever has it, returns the previous controller.
+    pub fn revoke(&mut self) -> Option<String> {
+        self.controller.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(viewers: &[&str]) -> ControlState {
+        let mut state = ControlState::new(ControlPermission::Full);
+        for uuid in viewers {
+            state.add_viewer(uuid);
+        }
+        state
+    }
+
+    #[test]
+    fn granting_takes_control_from_the_previous_controller() {
+        let mut state = state(&["a", "b"]);
+        assert!(state.request("b"));
+        assert_eq!(state.grant("a"), None);
+        assert_eq!(state.grant("b"), Some("a".to_string()));
+        assert_eq!(state.controller(), Some("b"));
+        assert!(!state.allows("a", false));
+        assert!(state.allows("b", true));
+        assert!(!state.viewer("a").unwrap().has_control);
+        assert!(!state.viewer("b").unwrap().requested);
+        // granting the controller again changes nothing
+        assert_eq!(state.grant("b"), None);
+    }
+
+    #[test]
+    fn view_only_revokes_control() {
+        let mut state = state(&["a"]);
+        state.grant("a");
+        assert_eq!(
+            state.set_permission("a", ControlPermission::ViewOnly),
+            Some("a".to_string())
+        );
+        assert_eq!(state.controller(), None);
+        assert!(!state.allows("a", false));
+        // and it can not be granted again
+        assert_eq!(state.grant("a"), None);
+        assert_eq!(state.controller(), None);
+    }
+
+    #[test]
+    fn mouse_only_rejects_keyboard_input() {
+        let mut state = state(&["a"]);
+        state.set_permission("a", ControlPermission::MouseOnly);
+        state.grant("a");
+        assert!(state.allows("a", false));
+        assert!(!state.allows("a", true));
+    }
+
+    #[test]
+    fn removing_the_controller_clears_control() {
+        let mut state = state(&["a", "b"]);
+        state.grant("a");
+        state.request("b");
+        state.remove_viewer("a");
+        assert_eq!(state.controller(), None);
+        assert_eq!(state.viewer("a"), None);
+        assert!(!state.allows("a", false));
+        assert!(state.viewer("b").unwrap().requested);
+    }
+}
@@ -1,13 +1,36 @@
-use crate::Result;
-use bytes::Bytes;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use chrono::Local;
 use enigo::{KeyboardControllable, MouseControllable};
-use parse_key::FromJsKey;
 use serde::{Deserialize, Serialize};
-use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::{mpsc, Mutex};
+use tokio_util::sync::CancellationToken;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+
+use crate::capture::DisplayInfo;
+use crate::config::InputConfig;
+use crate::output::ChannelHandler;
+use crate::Result;
+use control::ControlState;
+pub use control::{ControlPermission, ViewerControl};
+use policy::{InputPolicy, PolicyState, Verdict};
+use recording::Recorder;
+use scroll::{ScrollState, WheelMode};
+use touch::{PenPhase, TouchInput, TouchPhase};
 
+mod control;
 mod parse_key;
+mod policy;
+mod recording;
+mod scroll;
+mod touch;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 enum MouseButton {
     Left,
@@ -25,76 +48,590 @@ impl From<MouseButton> for enigo::MouseButton {
     }
 }
 
+/// The shared display, in the coordinate space the system uses for input, which
+/// spans all displays of the desktop.
+#[derive(Debug, Copy, Clone)]
+struct InputArea {
+    origin: (f64, f64),
+    size: (f64, f64),
+}
+
+impl InputArea {
+    fn of(display: &dyn DisplayInfo) -> Self {
+        let (x, y) = display.origin();
+        let (width, height) = display.resolution();
+        let scale = display.dpi_conversion_factor();
+        Self {
+            origin: (x as f64, y as f64),
+            size: (width as f64 / scale, height as f64 / scale),
+        }
+    }
+
+    /// Map a position on the shared display, from 0 to 1, to the desktop, clamped to
+    /// the area so viewers can't reach other displays.
+    fn desktop_position(&self, x: f32, y: f32) -> (i32, i32) {
+        let x = (x.clamp(0., 1.) as f64 * self.size.0).min((self.size.0 - 1.).max(0.));
+        let y = (y.clamp(0., 1.) as f64 * self.size.1).min((self.size.1 - 1.).max(0.));
+        (
+            (self.origin.0 + x).round() as i32,
+            (self.origin.1 + y).round() as i32,
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Wheel {
+    x: f32,
+    y: f32,
+    /// Positive to scroll right and down.
+    dx: f64,
+    dy: f64,
+    #[serde(default)]
+    mode: WheelMode,
+}
+
+/// Longest text typed by a single `TextInput`, in characters.
+const MAX_TEXT_INPUT_LENGTH: usize = 1024;
+
+/// Turns wheel deltas, positive when scrolling down, into enigo's direction.
+const SCROLL_REVERSE_FACTOR: f64 = if cfg!(target_os = "windows") { -1. } else { 1. };
+
+/// Keyboard input comes in one of two modes chosen by the viewer: in physical-key
+/// mode every key is sent as `KeyDown`/`KeyUp`, in text mode printable input
+/// (including IME composition and emoji) is sent as `TextInput` and only keys that
+/// don't produce text, such as shortcuts, arrows or Enter, are sent as keys.
+///
+/// Positions are fractions of the shared display from its top left corner, from 0
+/// to 1, like on the `annotation` and `cursor` channels, so they don't depend on the
+/// size the viewer shows the video at.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 enum InputMessage {
     KeyDown { key: String }, // Key from KeyboardEvent.code
     KeyUp { key: String },
-    MouseMove { x: i32, y: i32 },
-    MouseDown { x: i32, y: i32, button: MouseButton },
-    MouseUp { x: i32, y: i32, button: MouseButton },
-    MouseWheel { x: i32, y: i32, dx: i32, dy: i32 },
+    TextInput { text: String }, // Typed as is, regardless of the keyboard layout
+    MouseMove { x: f32, y: f32 },
+    MouseDown { x: f32, y: f32, button: MouseButton },
+    MouseUp { x: f32, y: f32, button: MouseButton },
+    MouseWheel(Wheel),
+    TouchStart { id: u32, x: f32, y: f32 }, // Ids are unique among the current touches
+    TouchMove { id: u32, x: f32, y: f32 },
+    TouchEnd { id: u32, x: f32, y: f32 },
+    TouchCancel { id: u32 },
+    PenDown { x: f32, y: f32, pressure: f32 }, // Pressure from 0 to 1
+    PenMove { x: f32, y: f32, pressure: f32 }, // Hovering while the pen is up
+    PenUp { x: f32, y: f32 },
+    Pinch { x: f32, y: f32, scale: f32 }, // Zoom since the last pinch, above 1 to zoom in
+    RequestControl,                       // Ask the sharer to grant control
+    ReleaseControl,                       // Give control back
+}
+
+impl InputMessage {
+    fn is_keyboard(&self) -> bool {
+        matches!(
+            self,
+            InputMessage::KeyDown { .. }
+                | InputMessage::KeyUp { .. }
+                | InputMessage::TextInput { .. }
+        )
+    }
+}
+
+/// Messages sent back to the viewer on the `control` channel.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum InputReply {
+    /// The key is not available on the sharer's platform and was ignored.
+    UnknownKey { key: String },
+    /// The shortcut is blocked by the sharer and was ignored.
+    BlockedShortcut { shortcut: String },
+    /// The control state of the viewer changed.
+    ControlState(ViewerControl),
+}
+
+enum Command {
+    /// An input message of the peer.
+    Input(String, InputMessage),
+    /// A recorded input message of the peer, applied whether it has control or not.
+    Replay(String, InputMessage),
+    /// Release everything the peer holds down.
+    Release(String),
+    /// Release everything the peer holds down and forget it, it disconnected.
+    Disconnect(String),
+    /// The display being shared changed.
+    SetArea(InputArea),
+    /// Record applied input messages, until `None` is sent.
+    Record(Option<Recorder>),
+}
+
+/// The keys, mouse buttons, touches and pen a viewer holds down, to release them
+/// when the viewer can no longer do so itself.
+#[derive(Default)]
+struct HeldInputs {
+    keys: HashSet<parse_key::KeyCode>,
+    buttons: HashSet<MouseButton>,
+    touches: HashSet<u32>,
+    /// Where the pen is, if it is down.
+    pen: Option<(i32, i32)>,
+}
+
+impl HeldInputs {
+    fn release(self, enigo: &mut enigo::Enigo, touch: &mut TouchInput) {
+        for key in self.keys {
+            key.release(enigo);
+        }
+        for button in self.buttons {
+            enigo.mouse_up(button.into());
+        }
+        for id in self.touches {
+            touch.touch(enigo, id, TouchPhase::Cancel, None);
+        }
+        if let Some(position) = self.pen {
+            touch.pen(enigo, PenPhase::Up, position, 0.);
+        }
+    }
 }
 
 pub struct InputHandler {
-    pub sender: mpsc::Sender<Bytes>,
+    sender: mpsc::Sender<Command>,
+    peers: Arc<Mutex<HashMap<String, mpsc::Sender<String>>>>,
+    control: Arc<Mutex<ControlState>>,
+    notify_update: Arc<dyn Fn() + Send + Sync>,
 }
 
 impl InputHandler {
     fn handle_input_event(
         enigo: &mut enigo::Enigo,
-        input_msg: Bytes,
-        dpi_factor: f64,
-    ) -> Result<()> {
-        let scroll_reverse_factor = if cfg!(target_os = "windows") { -1. } else { 1. };
-        let input_msg = serde_json::from_slice::<InputMessage>(&input_msg)?;
-        debug!("Deserialized input message: {:#?}", input_msg);
+        touch: &mut TouchInput,
+        held: &mut HeldInputs,
+        scroll: &mut ScrollState,
+        input_msg: InputMessage,
+        area: &InputArea,
+        smooth_scrolling: bool,
+    ) -> Result<Option<InputReply>> {
         match input_msg {
-            InputMessage::KeyDown { key } => enigo.key_down(enigo::Key::from_js_key(&key)?),
-            InputMessage::KeyUp { key } => enigo.key_up(enigo::Key::from_js_key(&key)?),
-            InputMessage::MouseMove { x, y } => enigo.mouse_move_to(
-                (x as f64 * dpi_factor) as i32,
-                (y as f64 * dpi_factor) as i32,
-            ),
+            InputMessage::KeyDown { key } => match parse_key::key_code(&key) {
+                Some(code) => {
+                    held.keys.insert(code);
+                    code.press(enigo)
+                }
+                None => return Ok(Some(InputReply::UnknownKey { key })),
+            },
+            // the viewer was told about unknown keys when they were pressed
+            InputMessage::KeyUp { key } => {
+                if let Some(code) = parse_key::key_code(&key) {
+                    held.keys.remove(&code);
+                    code.release(enigo)
+                }
+            }
+            InputMessage::TextInput { text } => {
+                let text: String = text
+                    .chars()
+                    .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+                    .take(MAX_TEXT_INPUT_LENGTH)
+                    .collect();
+                enigo.key_sequence(&text)
+            }
+            InputMessage::MouseMove { x, y } => {
+                let (x, y) = area.desktop_position(x, y);
+                enigo.mouse_move_to(x, y)
+            }
             InputMessage::MouseDown { x, y, button } => {
-                enigo.mouse_move_to(
-                    (x as f64 * dpi_factor) as i32,
-                    (y as f64 * dpi_factor) as i32,
-                );
+                let (x, y) = area.desktop_position(x, y);
+                enigo.mouse_move_to(x, y);
+                held.buttons.insert(button);
                 enigo.mouse_down(button.into())
             }
             InputMessage::MouseUp { x, y, button } => {
-                enigo.mouse_move_to(
-                    (x as f64 * dpi_factor) as i32,
-                    (y as f64 * dpi_factor) as i32,
-                );
+                let (x, y) = area.desktop_position(x, y);
+                enigo.mouse_move_to(x, y);
+                held.buttons.remove(&button);
                 enigo.mouse_up(button.into())
             }
-            InputMessage::MouseWheel { x, y, dx, dy } => {
-                enigo.mouse_move_to(
-                    (x as f64 * dpi_factor) as i32,
-                    (y as f64 * dpi_factor) as i32,
-                );
-                enigo.mouse_scroll_y((dy as f64 / 120. * scroll_reverse_factor) as i32);
-                enigo.mouse_scroll_x((dx as f64 / 120.) as i32);
+            InputMessage::MouseWheel(wheel) => {
+                let (x, y) = area.desktop_position(wheel.x, wheel.y);
+                enigo.mouse_move_to(x, y);
+                let delta = (wheel.dx, wheel.dy);
+                let units = scroll.scroll(delta, wheel.mode, area.size.1, smooth_scrolling);
+                scroll::inject(enigo, units);
+            }
+            InputMessage::TouchStart { id, x, y } => {
+                held.touches.insert(id);
+                let position = area.desktop_position(x, y);
+                touch.touch(enigo, id, TouchPhase::Start, Some(position));
             }
+            InputMessage::TouchMove { id, x, y } => {
+                if held.touches.contains(&id) {
+                    let position = area.desktop_position(x, y);
+                    touch.touch(enigo, id, TouchPhase::Move, Some(position));
+                }
+            }
+            InputMessage::TouchEnd { id, x, y } => {
+                if held.touches.remove(&id) {
+                    let position = area.desktop_position(x, y);
+                    touch.touch(enigo, id, TouchPhase::End, Some(position));
+                }
+            }
+            InputMessage::TouchCancel { id } => {
+                if held.touches.remove(&id) {
+                    touch.touch(enigo, id, TouchPhase::Cancel, None);
+                }
+            }
+            InputMessage::PenDown { x, y, pressure } => {
+                let position = area.desktop_position(x, y);
+                held.pen = Some(position);
+                touch.pen(enigo, PenPhase::Down, position, pressure);
+            }
+            InputMessage::PenMove { x, y, pressure } => {
+                let position = area.desktop_position(x, y);
+                let phase = match held.pen {
+                    Some(_) => {
+                        held.pen = Some(position);
+                        PenPhase::Move
+                    }
+                    None => PenPhase::Hover,
+                };
+                touch.pen(enigo, phase, position, pressure);
+            }
+            InputMessage::PenUp { x, y } => {
+                if held.pen.take().is_some() {
+                    touch.pen(enigo, PenPhase::Up, area.desktop_position(x, y), 0.);
+                }
+            }
+            InputMessage::Pinch { x, y, scale } => {
+                // the wheel value enigo scrolls up one step with
+                let wheel_up = (-SCROLL_REVERSE_FACTOR) as i32;
+                let modifier_held = touch::ZOOM_MODIFIER_KEYS
+                    .iter()
+                    .filter_map(|key| parse_key::key_code(key))
+                    .any(|code| held.keys.contains(&code));
+                let position = area.desktop_position(x, y);
+                touch.pinch(enigo, position, scale, wheel_up, modifier_held);
+            }
+            // handled by the InputHandler before reaching the input thread
+            InputMessage::RequestControl | InputMessage::ReleaseControl => {}
         };
-        Ok(())
+        Ok(None)
     }
 
-    pub fn new(disabled_control: bool, dpi_factor: f64) -> Self {
-        let (sender, mut receiver) = mpsc::channel::<Bytes>(32);
+    pub fn new(
+        disabled_control: bool,
+        config: &InputConfig,
+        notify_update: Arc<dyn Fn() + Send + Sync>,
+    ) -> Self {
+        let (sender, mut receiver) = mpsc::channel::<Command>(32);
+        let peers = Arc::new(Mutex::new(HashMap::<String, mpsc::Sender<String>>::new()));
+        let control = Arc::new(Mutex::new(ControlState::new(config.default_permission)));
+        let policy = InputPolicy::new(config);
+        let smooth_scrolling = config.smooth_scrolling;
+        let reply_peers = peers.clone();
+        let input_control = control.clone();
         std::thread::spawn(move || {
             let mut enigo = enigo::Enigo::new();
-            while let Some(msg) = receiver.blocking_recv() {
+            let mut touch = TouchInput::new();
+            let mut held = HashMap::<String, HeldInputs>::new();
+            let mut policy_states = HashMap::<String, PolicyState>::new();
+            let mut scroll_states = HashMap::<String, ScrollState>::new();
+            let mut last_tick = Instant::now();
+            let mut recorder: Option<Recorder> = None;
+            // until the capture starts, when no viewer can be connected yet
+            let mut area = InputArea {
+                origin: (0., 0.),
+                size: (0., 0.),
+            };
+            loop {
+                let command = if scroll_states.values().any(ScrollState::is_smoothing) {
+                    if last_tick.elapsed() >= scroll::SMOOTHING_INTERVAL {
+                        last_tick = Instant::now();
+                        for scroll in scroll_states.values_mut() {
+                            scroll::inject(&mut enigo, scroll.tick());
+                        }
+                        continue;
+                    }
+                    match receiver.try_recv() {
+                        Ok(command) => command,
+                        Err(TryRecvError::Empty) => {
+                            let elapsed = last_tick.elapsed();
+                            std::thread::sleep(scroll::SMOOTHING_INTERVAL.saturating_sub(elapsed));
+                            continue;
+                        }
+                        Err(TryRecvError::Disconnected) => break,
+                    }
+                } else {
+                    match receiver.blocking_recv() {
+                        Some(command) => command,
+                        None => break,
+                    }
+                };
+                let disconnected = matches!(command, Command::Disconnect(_));
+                let (uuid, msg, replayed) = match command {
+                    Command::Input(uuid, msg) => (uuid, msg, false),
+                    Command::Replay(uuid, msg) => (uuid, msg, true),
+                    Command::Release(uuid) | Command::Disconnect(uuid) => {
+                        // the rate limit outlives losing control, so releasing and
+                        // requesting control again doesn't reset it
+                        if disconnected {
+                            policy_states.remove(&uuid);
+                        } else if let Some(state) = policy_states.get_mut(&uuid) {
+                            state.release_keys();
+                        }
+                        scroll_states.remove(&uuid);
+                        if let Some(inputs) = held.remove(&uuid) {
+                            debug!("Releasing inputs held by {}", uuid);
+                            inputs.release(&mut enigo, &mut touch);
+                        }
+                        continue;
+                    }
+                    Command::SetArea(display_area) => {
+                        area = display_area;
+                        continue;
+                    }
+                    Command::Record(new_recorder) => {
+                        recorder = new_recorder;
+                        continue;
+                    }
+                };
                 if disabled_control {
                     continue; // Skip the message if user disabled remote control
                 }
-                if let Err(err) = Self::handle_input_event(&mut enigo, msg, 1. / dpi_factor) {
-                    warn!("Error handling input event: {}", err);
+                if !replayed {
+                    if !input_control
+                        .blocking_lock()
+                        .allows(&uuid, msg.is_keyboard())
+                    {
+                        continue; // The viewer has no control, or not of this kind of input
+                    }
+                    if let Some(rec) = recorder.as_mut() {
+                        if let Err(err) = rec.record(&uuid, &msg) {
+                            warn!("Failed to record input, recording stopped: {}", err);
+                            recorder = None;
+                        }
+                    }
+                }
+                let state = policy_states
+                    .entry(uuid.clone())
+                    .or_insert_with(|| policy.new_state());
+                let result = match policy.check(state, &msg) {
+                    Verdict::Allow => {
+                        let inputs = held.entry(uuid.clone()).or_default();
+                        let scroll = scroll_states.entry(uuid.clone()).or_default();
+                        Self::handle_input_event(
+                            &mut enigo,
+                            &mut touch,
+                            inputs,
+                            scroll,
+                            msg,
+                            &area,
+                            smooth_scrolling,
+                        )
+                    }
+                    Verdict::Drop => Ok(None),
+                    Verdict::Blocked(shortcut) => {
+                        info!("Blocked shortcut {} of {}", shortcut, uuid);
+                        Ok(Some(InputReply::BlockedShortcut { shortcut }))
+                    }
+                };
+                match result {
+                    Ok(Some(reply)) => {
+                        debug!("Replying to {}: {:?}", uuid, reply);
+                        if let Some(peer) = reply_peers.blocking_lock().get(&uuid) {
+                            peer.try_send(serde_json::to_string(&reply).unwrap()).ok();
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(err) => warn!("Error handling input event: {}", err),
+                }
+            }
+        });
+        Self {
+            sender,
+            peers,
+            control,
+            notify_update,
+        }
+    }
+
+    /// Set the display being shared, viewer coordinates are relative to it.
+    pub async fn set_display(&self, display: &dyn DisplayInfo) {
+        self.send(Command::SetArea(InputArea::of(display))).await;
+    }
+
+    /// Record the input applied from now on, until the session ends, to a file named
+    /// after `path` and the time the session started.
+    pub async fn start_recording(&self, path: &Path) -> Result<()> {
+        let path = recording::session_path(path, Local::now());
+        let recorder = Recorder::create(&path)?;
+        info!("Recording input to {}", path.display());
+        self.send(Command::Record(Some(recorder))).await;
+        Ok(())
+    }
+
+    /// Replay a recording made with `start_recording`, keeping the timing of the
+    /// messages. Messages are applied regardless of which viewer has control, but
+    /// not when remote control is disabled, and are still subject to the input
+    /// policy.
+    pub fn replay(self: &Arc<Self>, path: &Path, cancel: CancellationToken) -> Result<()> {
+        let inputs = recording::read(path)?;
+        info!(
+            "Replaying {} input messages from {}",
+            inputs.len(),
+            path.display()
+        );
+        let handler = self.clone();
+        tokio::spawn(async move {
+            let start = tokio::time::Instant::now();
+            let mut viewers = HashSet::new();
+            for input in inputs {
+                let at = start + std::time::Duration::from_millis(input.time_ms);
+                tokio::select! {
+                    _ = tokio::time::sleep_until(at) => {}
+                    _ = cancel.cancelled() => break,
                 }
+                viewers.insert(input.viewer.clone());
+                handler
+                    .send(Command::Replay(input.viewer, input.message))
+                    .await;
+            }
+            // the recording may end with keys held down
+            for viewer in viewers {
+                handler.release(&viewer).await;
             }
+            info!("Finished replaying input");
         });
-        Self { sender }
+        Ok(())
+    }
+
+    /// Release all keys and mouse buttons the peer holds down.
+    pub async fn release(&self, uuid: &str) {
+        self.send(Command::Release(uuid.to_string())).await;
+    }
+
+    /// The control state of every connected viewer, by uuid.
+    pub async fn get_controls(&self) -> HashMap<String, ViewerControl> {
+        self.control.lock().await.viewers()
+    }
+
+    pub async fn set_permission(&self, uuid: &str, permission: ControlPermission) {
+        let (revoked, is_controller) = {
+            let mut control = self.control.lock().await;
+            let revoked = control.set_permission(uuid, permission);
+            (revoked, control.controller() == Some(uuid))
+        };
+        // keys held by a viewer that may now only use the mouse are released too
+        if revoked.is_some() || is_controller {
+            self.release(uuid).await;
+        }
+        self.send_state(uuid).await;
+        (self.notify_update)();
+    }
+
+    /// Give control to the viewer, taking it away from the current controller.
+    pub async fn grant(&self, uuid: &str) {
+        let previous = self.control.lock().await.grant(uuid);
+        if let Some(previous) = previous {
+            self.release(&previous).await;
+            self.send_state(&previous).await;
+        }
+        self.send_state(uuid).await;
+        (self.notify_update)();
+    }
+
+    /// Take control away from whoever has it, effective immediately.
+    pub async fn revoke(&self) {
+        let previous = self.control.lock().await.revoke();
+        if let Some(previous) = previous {
+            info!("Revoked control of {}", previous);
+            self.release(&previous).await;
+            self.send_state(&previous).await;
+        }
+        (self.notify_update)();
+    }
+
+    async fn send_state(&self, uuid: &str) {
+        let viewer = self.control.lock().await.viewer(uuid);
+        if let Some(viewer) = viewer {
+            if let Some(peer) = self.peers.lock().await.get(uuid) {
+                let reply = InputReply::ControlState(viewer);
+                peer.try_send(serde_json::to_string(&reply).unwrap()).ok();
+            }
+        }
+    }
+
+    async fn send(&self, command: Command) {
+        if self.sender.send(command).await.is_err() {
+            warn!("Input handler has stopped");
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for InputHandler {
+    type Message = String;
+
+    /// Register a peer to send replies to.
+    async fn peer_connected(&self, uuid: String, sender: mpsc::Sender<String>) {
+        self.control.lock().await.add_viewer(&uuid);
+        self.peers.lock().await.insert(uuid.clone(), sender);
+        self.send_state(&uuid).await;
+        (self.notify_update)();
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let msg = match serde_json::from_slice::<InputMessage>(&message.data) {
+            Ok(msg) => msg,
+            Err(err) => {
+                warn!("Error parsing input message: {}", err);
+                return;
+            }
+        };
+        debug!("Deserialized input message: {:#?}", msg);
+        match msg {
+            InputMessage::RequestControl => {
+                if self.control.lock().await.request(uuid) {
+                    info!("Viewer {} requested control", uuid);
+                    self.send_state(uuid).await;
+                    (self.notify_update)();
+                }
+            }
+            InputMessage::ReleaseControl => {
+                let is_controller = self.control.lock().await.controller() == Some(uuid);
+                if is_controller {
+                    self.revoke().await;
+                }
+            }
+            msg => self.send(Command::Input(uuid.to_string(), msg)).await,
+        }
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.control.lock().await.remove_viewer(uuid);
+        self.peers.lock().await.remove(uuid);
+        self.send(Command::Disconnect(uuid.to_string())).await;
+        (self.notify_update)();
+    }
+
+    /// Forget all viewers, at the end of a session.
+    async fn clear(&self) {
+        self.control.lock().await.clear();
+        self.send(Command::Record(None)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn desktop_position_maps_fractions_to_the_area() {
+        // a 1280x720 display at 2x scaling, right of a 1920 wide display
+        let area = InputArea {
+            origin: (1920., 0.),
+            size: (640., 360.),
+        };
+        assert_eq!(area.desktop_position(0., 0.), (1920, 0));
+        assert_eq!(area.desktop_position(0.5, 0.5), (2240, 180));
+        assert_eq!(area.desktop_position(1., 1.), (2559, 359));
+        // viewers can't reach other displays
+        assert_eq!(area.desktop_position(-0.5, 2.), (1920, 359));
     }
 }
@@ -1,78 +1,1128 @@
-use crate::Result;
-use anyhow::format_err;
-
-pub trait FromJsKey {
-    /// convert from KeyboardEvent.code
-    fn from_js_key(s: &str) -> Result<Self>
-    where
-        Self: Sized;
+use enigo::{Key, KeyboardControllable};
+
+/// A key to press, by its native key code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeyCode {
+    code: u16,
+    /// Whether the key is sent with the extended key flag on Windows, which tells
+    /// apart keys sharing a virtual key, such as NumpadEnter and Enter.
+    extended: bool,
 }
 
-impl FromJsKey for enigo::Key {
-    fn from_js_key(s: &str) -> Result<Self> {
-        // Handle Digit0-9
-        if s.starts_with("Digit") || (s.len() == 7 && s.starts_with("Numpad")) {
-            let digit = s.chars().last().unwrap();
-            return Ok(enigo::Key::Layout(digit));
+impl KeyCode {
+    pub fn press(self, enigo: &mut enigo::Enigo) {
+        #[cfg(target_os = "windows")]
+        if self.extended {
+            return send_extended_key(self.code, false);
         }
+        enigo.key_down(Key::Raw(self.code));
+    }
 
-        // Handle KeyA-Z
-        if s.starts_with("Key") {
-            let letter = s.chars().last().unwrap().to_ascii_lowercase();
-            return Ok(enigo::Key::Layout(letter));
+    pub fn release(self, enigo: &mut enigo::Enigo) {
+        #[cfg(target_os = "windows")]
+        if self.extended {
+            return send_extended_key(self.code, true);
         }
+        enigo.key_up(Key::Raw(self.code));
+    }
+}
+
+/// The native key code of a KeyboardEvent.code, `None` if the key is not available
+/// on this platform
+pub fn key_code(s: &str) -> Option<KeyCode> {
+    KEYS.iter()
+        .find(|(code, _)| *code == s)
+        .and_then(|(_, key)| key.to_key_code())
+}
+
+/// Press or release the virtual key `vk` as an extended key, which enigo cannot do.
+#[cfg(target_os = "windows")]
+fn send_extended_key(vk: u16, up: bool) {
+    use std::mem::size_of;
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        MapVirtualKeyW, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
+        KEYEVENTF_EXTENDEDKEY, KEYEVENTF_KEYUP, MAPVK_VK_TO_VSC, VIRTUAL_KEY,
+    };
+
+    let flags = if up {
+        KEYEVENTF_EXTENDEDKEY | KEYEVENTF_KEYUP
+    } else {
+        KEYEVENTF_EXTENDEDKEY
+    };
+    let input = INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: VIRTUAL_KEY(vk),
+                wScan: unsafe { MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC) } as u16,
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    };
+    let sent = unsafe { SendInput(&[input], size_of::<INPUT>() as i32) };
+    if sent != 1 {
+        warn!(
+            "Failed to inject extended key: {}",
+            windows::core::Error::from_win32()
+        );
+    }
+}
+
+/// How a key is identified on Windows. Keys of the writing system (letters, digits
+/// and punctuation) are given by scan code, i.e. their physical position, and mapped
+/// to the virtual key of the active keyboard layout. All others are virtual keys,
+/// sent as extended keys when they share their virtual key with another key.
+#[cfg(target_os = "windows")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum NativeKey {
+    Scan(u16),
+    Vk(u16),
+    ExtendedVk(u16),
+}
+
+#[cfg(target_os = "windows")]
+impl NativeKey {
+    fn to_key_code(self) -> Option<KeyCode> {
+        match self {
+            NativeKey::ExtendedVk(code) => Some(KeyCode {
+                code,
+                extended: true,
+            }),
+            key => key.to_raw().map(|code| KeyCode {
+                code,
+                extended: false,
+            }),
+        }
+    }
+
+    fn to_raw(self) -> Option<u16> {
+        use windows::Win32::UI::Input::KeyboardAndMouse::{
+            GetKeyboardLayout, MapVirtualKeyExW, MAPVK_VSC_TO_VK_EX,
+        };
+        use windows::Win32::UI::WindowsAndMessaging::{
+            GetForegroundWindow, GetWindowThreadProcessId,
+        };
+
+        match self {
+            NativeKey::Vk(vk) | NativeKey::ExtendedVk(vk) => Some(vk),
+            NativeKey::Scan(scan) => unsafe {
+                // the layout of the application that receives the input
+                let thread = GetWindowThreadProcessId(GetForegroundWindow(), None);
+                let layout = GetKeyboardLayout(thread);
+                match MapVirtualKeyExW(scan as u32, MAPVK_VSC_TO_VK_EX, layout) {
+                    0 => None,
+                    vk => Some(vk as u16),
+                }
+            },
+        }
+    }
+}
+
+/// Virtual key codes by KeyboardEvent.code, see
+/// https://www.w3.org/TR/uievents-code/ for the codes.
+#[cfg(target_os = "windows")]
+const KEYS: &[(&str, NativeKey)] = {
+    use NativeKey::{ExtendedVk, Scan, Vk};
+    &[
+        // Writing system keys
+        ("Backquote", Scan(0x29)),
+        ("Backslash", Scan(0x2b)),
+        ("BracketLeft", Scan(0x1a)),
+        ("BracketRight", Scan(0x1b)),
+        ("Comma", Scan(0x33)),
+        ("Digit0", Scan(0x0b)),
+        ("Digit1", Scan(0x02)),
+        ("Digit2", Scan(0x03)),
+        ("Digit3", Scan(0x04)),
+        ("Digit4", Scan(0x05)),
+        ("Digit5", Scan(0x06)),
+        ("Digit6", Scan(0x07)),
+        ("Digit7", Scan(0x08)),
+        ("Digit8", Scan(0x09)),
+        ("Digit9", Scan(0x0a)),
+        ("Equal", Scan(0x0d)),
+        ("IntlBackslash", Scan(0x56)),
+        ("IntlRo", Scan(0x73)),
+        ("IntlYen", Scan(0x7d)),
+        ("KeyA", Scan(0x1e)),
+        ("KeyB", Scan(0x30)),
+        ("KeyC", Scan(0x2e)),
+        ("KeyD", Scan(0x20)),
+        ("KeyE", Scan(0x12)),
+        ("KeyF", Scan(0x21)),
+        ("KeyG", Scan(0x22)),
+        ("KeyH", Scan(0x23)),
+        ("KeyI", Scan(0x17)),
+        ("KeyJ", Scan(0x24)),
+        ("KeyK", Scan(0x25)),
+        ("KeyL", Scan(0x26)),
+        ("KeyM", Scan(0x32)),
+        ("KeyN", Scan(0x31)),
+        ("KeyO", Scan(0x18)),
+        ("KeyP", Scan(0x19)),
+        ("KeyQ", Scan(0x10)),
+        ("KeyR", Scan(0x13)),
+        ("KeyS", Scan(0x1f)),
+        ("KeyT", Scan(0x14)),
+        ("KeyU", Scan(0x16)),
+        ("KeyV", Scan(0x2f)),
+        ("KeyW", Scan(0x11)),
+        ("KeyX", Scan(0x2d)),
+        ("KeyY", Scan(0x15)),
+        ("KeyZ", Scan(0x2c)),
+        ("Minus", Scan(0x0c)),
+        ("Period", Scan(0x34)),
+        ("Quote", Scan(0x28)),
+        ("Semicolon", Scan(0x27)),
+        ("Slash", Scan(0x35)),
+        // Functional keys
+        ("AltLeft", Vk(0xa4)),
+        ("AltRight", Vk(0xa5)),
+        ("Backspace", Vk(0x08)),
+        ("CapsLock", Vk(0x14)),
+        ("ContextMenu", Vk(0x5d)),
+        ("ControlLeft", Vk(0xa2)),
+        ("ControlRight", Vk(0xa3)),
+        ("Enter", Vk(0x0d)),
+        ("MetaLeft", Vk(0x5b)),
+        ("MetaRight", Vk(0x5c)),
+        ("ShiftLeft", Vk(0xa0)),
+        ("ShiftRight", Vk(0xa1)),
+        ("Space", Vk(0x20)),
+        ("Tab", Vk(0x09)),
+        ("Convert", Vk(0x1c)),
+        ("KanaMode", Vk(0x15)),
+        ("Lang1", Vk(0x15)),
+        ("Lang2", Vk(0x19)),
+        ("NonConvert", Vk(0x1d)),
+        // Control pad
+        ("Delete", Vk(0x2e)),
+        ("End", Vk(0x23)),
+        ("Help", Vk(0x2f)),
+        ("Home", Vk(0x24)),
+        ("Insert", Vk(0x2d)),
+        ("PageDown", Vk(0x22)),
+        ("PageUp", Vk(0x21)),
+        // Arrow pad
+        ("ArrowDown", Vk(0x28)),
+        ("ArrowLeft", Vk(0x25)),
+        ("ArrowRight", Vk(0x27)),
+        ("ArrowUp", Vk(0x26)),
+        // Numpad
+        ("NumLock", Vk(0x90)),
+        ("Numpad0", Vk(0x60)),
+        ("Numpad1", Vk(0x61)),
+        ("Numpad2", Vk(0x62)),
+        ("Numpad3", Vk(0x63)),
+        ("Numpad4", Vk(0x64)),
+        ("Numpad5", Vk(0x65)),
+        ("Numpad6", Vk(0x66)),
+        ("Numpad7", Vk(0x67)),
+        ("Numpad8", Vk(0x68)),
+        ("Numpad9", Vk(0x69)),
+        ("NumpadAdd", Vk(0x6b)),
+        ("NumpadClear", Vk(0x0c)),
+        ("NumpadComma", Vk(0x6c)),
+        ("NumpadDecimal", Vk(0x6e)),
+        ("NumpadDivide", Vk(0x6f)),
+        ("NumpadEnter", ExtendedVk(0x0d)),
+        ("NumpadEqual", Vk(0x92)),
+        ("NumpadMultiply", Vk(0x6a)),
+        ("NumpadSubtract", Vk(0x6d)),
+        // Function section
+        ("Escape", Vk(0x1b)),
+        ("F1", Vk(0x70)),
+        ("F2", Vk(0x71)),
+        ("F3", Vk(0x72)),
+        ("F4", Vk(0x73)),
+        ("F5", Vk(0x74)),
+        ("F6", Vk(0x75)),
+        ("F7", Vk(0x76)),
+        ("F8", Vk(0x77)),
+        ("F9", Vk(0x78)),
+        ("F10", Vk(0x79)),
+        ("F11", Vk(0x7a)),
+        ("F12", Vk(0x7b)),
+        ("F13", Vk(0x7c)),
+        ("F14", Vk(0x7d)),
+        ("F15", Vk(0x7e)),
+        ("F16", Vk(0x7f)),
+        ("F17", Vk(0x80)),
+        ("F18", Vk(0x81)),
+        ("F19", Vk(0x82)),
+        ("F20", Vk(0x83)),
+        ("F21", Vk(0x84)),
+        ("F22", Vk(0x85)),
+        ("F23", Vk(0x86)),
+        ("F24", Vk(0x87)),
+        ("PrintScreen", Vk(0x2c)),
+        ("ScrollLock", Vk(0x91)),
+        ("Pause", Vk(0x13)),
+        // Media keys
+        ("BrowserBack", Vk(0xa6)),
+        ("BrowserFavorites", Vk(0xab)),
+        ("BrowserForward", Vk(0xa7)),
+        ("BrowserHome", Vk(0xac)),
+        ("BrowserRefresh", Vk(0xa8)),
+        ("BrowserSearch", Vk(0xaa)),
+        ("BrowserStop", Vk(0xa9)),
+        ("LaunchApp1", Vk(0xb6)),
+        ("LaunchApp2", Vk(0xb7)),
+        ("LaunchMail", Vk(0xb4)),
+        ("MediaPlayPause", Vk(0xb3)),
+        ("MediaSelect", Vk(0xb5)),
+        ("MediaStop", Vk(0xb2)),
+        ("MediaTrackNext", Vk(0xb0)),
+        ("MediaTrackPrevious", Vk(0xb1)),
+        ("Sleep", Vk(0x5f)),
+        ("AudioVolumeDown", Vk(0xae)),
+        ("AudioVolumeMute", Vk(0xad)),
+        ("AudioVolumeUp", Vk(0xaf)),
+        // Legacy keys
+        ("Select", Vk(0x29)),
+    ]
+};
+
+/// Key codes are physical positions on macOS and Linux, so they are used as is.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct NativeKey(u16);
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+impl NativeKey {
+    fn to_key_code(self) -> Option<KeyCode> {
+        Some(KeyCode {
+            code: self.0,
+            extended: false,
+        })
+    }
+}
+
+/// Virtual key codes (`kVK_*`) by KeyboardEvent.code, see
+/// https://www.w3.org/TR/uievents-code/ for the codes. Keys without a key code, such
+/// as media keys, are not available.
+#[cfg(target_os = "macos")]
+const KEYS: &[(&str, NativeKey)] = &[
+    // Writing system keys
+    ("Backquote", NativeKey(0x32)),
+    ("Backslash", NativeKey(0x2a)),
+    ("BracketLeft", NativeKey(0x21)),
+    ("BracketRight", NativeKey(0x1e)),
+    ("Comma", NativeKey(0x2b)),
+    ("Digit0", NativeKey(0x1d)),
+    ("Digit1", NativeKey(0x12)),
+    ("Digit2", NativeKey(0x13)),
+    ("Digit3", NativeKey(0x14)),
+    ("Digit4", NativeKey(0x15)),
+    ("Digit5", NativeKey(0x17)),
+    ("Digit6", NativeKey(0x16)),
+    ("Digit7", NativeKey(0x1a)),
+    ("Digit8", NativeKey(0x1c)),
+    ("Digit9", NativeKey(0x19)),
+    ("Equal", NativeKey(0x18)),
+    ("IntlBackslash", NativeKey(0x0a)),
+    ("IntlRo", NativeKey(0x5e)),
+    ("IntlYen", NativeKey(0x5d)),
+    ("KeyA", NativeKey(0x00)),
+    ("KeyB", NativeKey(0x0b)),
+    ("KeyC", NativeKey(0x08)),
+    ("KeyD", NativeKey(0x02)),
+    ("KeyE", NativeKey(0x0e)),
+    ("KeyF", NativeKey(0x03)),
+    ("KeyG", NativeKey(0x05)),
+    ("KeyH", NativeKey(0x04)),
+    ("KeyI", NativeKey(0x22)),
+    ("KeyJ", NativeKey(0x26)),
+    ("KeyK", NativeKey(0x28)),
+    ("KeyL", NativeKey(0x25)),
+    ("KeyM", NativeKey(0x2e)),
+    ("KeyN", NativeKey(0x2d)),
+    ("KeyO", NativeKey(0x1f)),
+    ("KeyP", NativeKey(0x23)),
+    ("KeyQ", NativeKey(0x0c)),
+    ("KeyR", NativeKey(0x0f)),
+    ("KeyS", NativeKey(0x01)),
+    ("KeyT", NativeKey(0x11)),
+    ("KeyU", NativeKey(0x20)),
+    ("KeyV", NativeKey(0x09)),
+    ("KeyW", NativeKey(0x0d)),
+    ("KeyX", NativeKey(0x07)),
+    ("KeyY", NativeKey(0x10)),
+    ("KeyZ", NativeKey(0x06)),
+    ("Minus", NativeKey(0x1b)),
+    ("Period", NativeKey(0x2f)),
+    ("Quote", NativeKey(0x27)),
+    ("Semicolon", NativeKey(0x29)),
+    ("Slash", NativeKey(0x2c)),
+    // Functional keys
+    ("AltLeft", NativeKey(0x3a)),
+    ("AltRight", NativeKey(0x3d)),
+    ("Backspace", NativeKey(0x33)),
+    ("CapsLock", NativeKey(0x39)),
+    ("ContextMenu", NativeKey(0x6e)),
+    ("ControlLeft", NativeKey(0x3b)),
+    ("ControlRight", NativeKey(0x3e)),
+    ("Enter", NativeKey(0x24)),
+    ("MetaLeft", NativeKey(0x37)),
+    ("MetaRight", NativeKey(0x36)),
+    ("ShiftLeft", NativeKey(0x38)),
+    ("ShiftRight", NativeKey(0x3c)),
+    ("Space", NativeKey(0x31)),
+    ("Tab", NativeKey(0x30)),
+    ("Lang1", NativeKey(0x68)),
+    ("Lang2", NativeKey(0x66)),
+    ("Fn", NativeKey(0x3f)),
+    // Control pad, Insert is the Help key on Apple keyboards, there is no other
+    ("Delete", NativeKey(0x75)),
+    ("End", NativeKey(0x77)),
+    ("Home", NativeKey(0x73)),
+    ("Insert", NativeKey(0x72)),
+    ("PageDown", NativeKey(0x79)),
+    ("PageUp", NativeKey(0x74)),
+    // Arrow pad
+    ("ArrowDown", NativeKey(0x7d)),
+    ("ArrowLeft", NativeKey(0x7b)),
+    ("ArrowRight", NativeKey(0x7c)),
+    ("ArrowUp", NativeKey(0x7e)),
+    // Numpad, NumLock is the Clear key on Apple keyboards
+    ("NumLock", NativeKey(0x47)),
+    ("Numpad0", NativeKey(0x52)),
+    ("Numpad1", NativeKey(0x53)),
+    ("Numpad2", NativeKey(0x54)),
+    ("Numpad3", NativeKey(0x55)),
+    ("Numpad4", NativeKey(0x56)),
+    ("Numpad5", NativeKey(0x57)),
+    ("Numpad6", NativeKey(0x58)),
+    ("Numpad7", NativeKey(0x59)),
+    ("Numpad8", NativeKey(0x5b)),
+    ("Numpad9", NativeKey(0x5c)),
+    ("NumpadAdd", NativeKey(0x45)),
+    ("NumpadClear", NativeKey(0x47)),
+    ("NumpadComma", NativeKey(0x5f)),
+    ("NumpadDecimal", NativeKey(0x41)),
+    ("NumpadDivide", NativeKey(0x4b)),
+    ("NumpadEnter", NativeKey(0x4c)),
+    ("NumpadEqual", NativeKey(0x51)),
+    ("NumpadMultiply", NativeKey(0x43)),
+    ("NumpadSubtract", NativeKey(0x4e)),
+    // Function section
+    ("Escape", NativeKey(0x35)),
+    ("F1", NativeKey(0x7a)),
+    ("F2", NativeKey(0x78)),
+    ("F3", NativeKey(0x63)),
+    ("F4", NativeKey(0x76)),
+    ("F5", NativeKey(0x60)),
+    ("F6", NativeKey(0x61)),
+    ("F7", NativeKey(0x62)),
+    ("F8", NativeKey(0x64)),
+    ("F9", NativeKey(0x65)),
+    ("F10", NativeKey(0x6d)),
+    ("F11", NativeKey(0x67)),
+    ("F12", NativeKey(0x6f)),
+    ("F13", NativeKey(0x69)),
+    ("F14", NativeKey(0x6b)),
+    ("F15", NativeKey(0x71)),
+    ("F16", NativeKey(0x6a)),
+    ("F17", NativeKey(0x40)),
+    ("F18", NativeKey(0x4f)),
+    ("F19", NativeKey(0x50)),
+    ("F20", NativeKey(0x5a)),
+    // Media keys
+    ("AudioVolumeDown", NativeKey(0x49)),
+    ("AudioVolumeMute", NativeKey(0x4a)),
+    ("AudioVolumeUp", NativeKey(0x48)),
+];
+
+/// X11 key codes (evdev codes + 8) by KeyboardEvent.code, see
+/// https://www.w3.org/TR/uievents-code/ for the codes.
+#[cfg(target_os = "linux")]
+const KEYS: &[(&str, NativeKey)] = &[
+    // Writing system keys
+    ("Backquote", NativeKey(49)),
+    ("Backslash", NativeKey(51)),
+    ("BracketLeft", NativeKey(34)),
+    ("BracketRight", NativeKey(35)),
+    ("Comma", NativeKey(59)),
+    ("Digit0", NativeKey(19)),
+    ("Digit1", NativeKey(10)),
+    ("Digit2", NativeKey(11)),
+    ("Digit3", NativeKey(12)),
+    ("Digit4", NativeKey(13)),
+    ("Digit5", NativeKey(14)),
+    ("Digit6", NativeKey(15)),
+    ("Digit7", NativeKey(16)),
+    ("Digit8", NativeKey(17)),
+    ("Digit9", NativeKey(18)),
+    ("Equal", NativeKey(21)),
+    ("IntlBackslash", NativeKey(94)),
+    ("IntlRo", NativeKey(97)),
+    ("IntlYen", NativeKey(132)),
+    ("KeyA", NativeKey(38)),
+    ("KeyB", NativeKey(56)),
+    ("KeyC", NativeKey(54)),
+    ("KeyD", NativeKey(40)),
+    ("KeyE", NativeKey(26)),
+    ("KeyF", NativeKey(41)),
+    ("KeyG", NativeKey(42)),
+    ("KeyH", NativeKey(43)),
+    ("KeyI", NativeKey(31)),
+    ("KeyJ", NativeKey(44)),
+    ("KeyK", NativeKey(45)),
+    ("KeyL", NativeKey(46)),
+    ("KeyM", NativeKey(58)),
+    ("KeyN", NativeKey(57)),
+    ("KeyO", NativeKey(32)),
+    ("KeyP", NativeKey(33)),
+    ("KeyQ", NativeKey(24)),
+    ("KeyR", NativeKey(27)),
+    ("KeyS", NativeKey(39)),
+    ("KeyT", NativeKey(28)),
+    ("KeyU", NativeKey(30)),
+    ("KeyV", NativeKey(55)),
+    ("KeyW", NativeKey(25)),
+    ("KeyX", NativeKey(53)),
+    ("KeyY", NativeKey(29)),
+    ("KeyZ", NativeKey(52)),
+    ("Minus", NativeKey(20)),
+    ("Period", NativeKey(60)),
+    ("Quote", NativeKey(48)),
+    ("Semicolon", NativeKey(47)),
+    ("Slash", NativeKey(61)),
+    // Functional keys
+    ("AltLeft", NativeKey(64)),
+    ("AltRight", NativeKey(108)),
+    ("Backspace", NativeKey(22)),
+    ("CapsLock", NativeKey(66)),
+    ("ContextMenu", NativeKey(135)),
+    ("ControlLeft", NativeKey(37)),
+    ("ControlRight", NativeKey(105)),
+    ("Enter", NativeKey(36)),
+    ("MetaLeft", NativeKey(133)),
+    ("MetaRight", NativeKey(134)),
+    ("ShiftLeft", NativeKey(50)),
+    ("ShiftRight", NativeKey(62)),
+    ("Space", NativeKey(65)),
+    ("Tab", NativeKey(23)),
+    ("Convert", NativeKey(100)),
+    ("KanaMode", NativeKey(101)),
+    ("Lang1", NativeKey(130)),
+    ("Lang2", NativeKey(131)),
+    ("NonConvert", NativeKey(102)),
+    // Control pad
+    ("Delete", NativeKey(119)),
+    ("End", NativeKey(115)),
+    ("Help", NativeKey(146)),
+    ("Home", NativeKey(110)),
+    ("Insert", NativeKey(118)),
+    ("PageDown", NativeKey(117)),
+    ("PageUp", NativeKey(112)),
+    // Arrow pad
+    ("ArrowDown", NativeKey(116)),
+    ("ArrowLeft", NativeKey(113)),
+    ("ArrowRight", NativeKey(114)),
+    ("ArrowUp", NativeKey(111)),
+    // Numpad
+    ("NumLock", NativeKey(77)),
+    ("Numpad0", NativeKey(90)),
+    ("Numpad1", NativeKey(87)),
+    ("Numpad2", NativeKey(88)),
+    ("Numpad3", NativeKey(89)),
+    ("Numpad4", NativeKey(83)),
+    ("Numpad5", NativeKey(84)),
+    ("Numpad6", NativeKey(85)),
+    ("Numpad7", NativeKey(79)),
+    ("Numpad8", NativeKey(80)),
+    ("Numpad9", NativeKey(81)),
+    ("NumpadAdd", NativeKey(86)),
+    ("NumpadComma", NativeKey(129)),
+    ("NumpadDecimal", NativeKey(91)),
+    ("NumpadDivide", NativeKey(106)),
+    ("NumpadEnter", NativeKey(104)),
+    ("NumpadEqual", NativeKey(125)),
+    ("NumpadMultiply", NativeKey(63)),
+    ("NumpadSubtract", NativeKey(82)),
+    // Function section
+    ("Escape", NativeKey(9)),
+    ("F1", NativeKey(67)),
+    ("F2", NativeKey(68)),
+    ("F3", NativeKey(69)),
+    ("F4", NativeKey(70)),
+    ("F5", NativeKey(71)),
+    ("F6", NativeKey(72)),
+    ("F7", NativeKey(73)),
+    ("F8", NativeKey(74)),
+    ("F9", NativeKey(75)),
+    ("F10", NativeKey(76)),
+    ("F11", NativeKey(95)),
+    ("F12", NativeKey(96)),
+    ("F13", NativeKey(191)),
+    ("F14", NativeKey(192)),
+    ("F15", NativeKey(193)),
+    ("F16", NativeKey(194)),
+    ("F17", NativeKey(195)),
+    ("F18", NativeKey(196)),
+    ("F19", NativeKey(197)),
+    ("F20", NativeKey(198)),
+    ("F21", NativeKey(199)),
+    ("F22", NativeKey(200)),
+    ("F23", NativeKey(201)),
+    ("F24", NativeKey(202)),
+    ("PrintScreen", NativeKey(107)),
+    ("ScrollLock", NativeKey(78)),
+    ("Pause", NativeKey(127)),
+    // Media keys
+    ("BrowserBack", NativeKey(166)),
+    ("BrowserFavorites", NativeKey(164)),
+    ("BrowserForward", NativeKey(167)),
+    ("BrowserHome", NativeKey(180)),
+    ("BrowserRefresh", NativeKey(181)),
+    ("BrowserSearch", NativeKey(225)),
+    ("BrowserStop", NativeKey(136)),
+    ("LaunchApp1", NativeKey(165)),
+    ("LaunchApp2", NativeKey(148)),
+    ("LaunchMail", NativeKey(163)),
+    ("MediaPlayPause", NativeKey(172)),
+    ("MediaStop", NativeKey(174)),
+    ("MediaTrackNext", NativeKey(171)),
+    ("MediaTrackPrevious", NativeKey(173)),
+    ("Sleep", NativeKey(150)),
+    ("AudioVolumeDown", NativeKey(122)),
+    ("AudioVolumeMute", NativeKey(121)),
+    ("AudioVolumeUp", NativeKey(123)),
+];
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Codes of the keys of a standard keyboard, which every platform has.
+    const STANDARD_CODES: &[&str] = &[
+        "Backquote",
+        "Backslash",
+        "BracketLeft",
+        "BracketRight",
+        "Comma",
+        "Digit0",
+        "Digit1",
+        "Digit2",
+        "Digit3",
+        "Digit4",
+        "Digit5",
+        "Digit6",
+        "Digit7",
+        "Digit8",
+        "Digit9",
+        "Equal",
+        "IntlBackslash",
+        "KeyA",
+        "KeyB",
+        "KeyC",
+        "KeyD",
+        "KeyE",
+        "KeyF",
+        "KeyG",
+        "KeyH",
+        "KeyI",
+        "KeyJ",
+        "KeyK",
+        "KeyL",
+        "KeyM",
+        "KeyN",
+        "KeyO",
+        "KeyP",
+        "KeyQ",
+        "KeyR",
+        "KeyS",
+        "KeyT",
+        "KeyU",
+        "KeyV",
+        "KeyW",
+        "KeyX",
+        "KeyY",
+        "KeyZ",
+        "Minus",
+        "Period",
+        "Quote",
+        "Semicolon",
+        "Slash",
+        "AltLeft",
+        "AltRight",
+        "Backspace",
+        "CapsLock",
+        "ControlLeft",
+        "ControlRight",
+        "Enter",
+        "MetaLeft",
+        "MetaRight",
+        "ShiftLeft",
+        "ShiftRight",
+        "Space",
+        "Tab",
+        "Delete",
+        "End",
+        "Home",
+        "Insert",
+        "PageDown",
+        "PageUp",
+        "ArrowDown",
+        "ArrowLeft",
+        "ArrowRight",
+        "ArrowUp",
+        "NumLock",
+        "Numpad0",
+        "Numpad1",
+        "Numpad2",
+        "Numpad3",
+        "Numpad4",
+        "Numpad5",
+        "Numpad6",
+        "Numpad7",
+        "Numpad8",
+        "Numpad9",
+        "NumpadAdd",
+        "NumpadDecimal",
+        "NumpadDivide",
+        "NumpadEnter",
+        "NumpadMultiply",
+        "NumpadSubtract",
+        "Escape",
+        "F1",
+        "F2",
+        "F3",
+        "F4",
+        "F5",
+        "F6",
+        "F7",
+        "F8",
+        "F9",
+        "F10",
+        "F11",
+        "F12",
+        "AudioVolumeDown",
+        "AudioVolumeMute",
+        "AudioVolumeUp",
+    ];
+
+    /// Codes that map to the same key on purpose.
+    #[cfg(target_os = "windows")]
+    const SHARED_KEYS: &[(&str, &str)] = &[
+        // VK_KANA and VK_HANGUL are the same virtual key
+        ("KanaMode", "Lang1"),
+    ];
+    #[cfg(target_os = "macos")]
+    const SHARED_KEYS: &[(&str, &str)] = &[
+        // the Clear key is where NumLock is on other keyboards
+        ("NumLock", "NumpadClear"),
+    ];
+    #[cfg(target_os = "linux")]
+    const SHARED_KEYS: &[(&str, &str)] = &[];
+
+    /// Every code of https://www.w3.org/TR/uievents-code/ but `Unidentified`, with the
+    /// additional function keys up to F24.
+    const SPEC_CODES: &[&str] = &[
+        // Writing system keys
+        "Backquote",
+        "Backslash",
+        "BracketLeft",
+        "BracketRight",
+        "Comma",
+        "Digit0",
+        "Digit1",
+        "Digit2",
+        "Digit3",
+        "Digit4",
+        "Digit5",
+        "Digit6",
+        "Digit7",
+        "Digit8",
+        "Digit9",
+        "Equal",
+        "IntlBackslash",
+        "IntlRo",
+        "IntlYen",
+        "KeyA",
+        "KeyB",
+        "KeyC",
+        "KeyD",
+        "KeyE",
+        "KeyF",
+        "KeyG",
+        "KeyH",
+        "KeyI",
+        "KeyJ",
+        "KeyK",
+        "KeyL",
+        "KeyM",
+        "KeyN",
+        "KeyO",
+        "KeyP",
+        "KeyQ",
+        "KeyR",
+        "KeyS",
+        "KeyT",
+        "KeyU",
+        "KeyV",
+        "KeyW",
+        "KeyX",
+        "KeyY",
+        "KeyZ",
+        "Minus",
+        "Period",
+        "Quote",
+        "Semicolon",
+        "Slash",
+        // Functional keys
+        "AltLeft",
+        "AltRight",
+        "Backspace",
+        "CapsLock",
+        "ContextMenu",
+        "ControlLeft",
+        "ControlRight",
+        "Enter",
+        "MetaLeft",
+        "MetaRight",
+        "ShiftLeft",
+        "ShiftRight",
+        "Space",
+        "Tab",
+        "Convert",
+        "KanaMode",
+        "Lang1",
+        "Lang2",
+        "Lang3",
+        "Lang4",
+        "Lang5",
+        "NonConvert",
+        // Control pad
+        "Delete",
+        "End",
+        "Help",
+        "Home",
+        "Insert",
+        "PageDown",
+        "PageUp",
+        // Arrow pad
+        "ArrowDown",
+        "ArrowLeft",
+        "ArrowRight",
+        "ArrowUp",
+        // Numpad
+        "NumLock",
+        "Numpad0",
+        "Numpad1",
+        "Numpad2",
+        "Numpad3",
+        "Numpad4",
+        "Numpad5",
+        "Numpad6",
+        "Numpad7",
+        "Numpad8",
+        "Numpad9",
+        "NumpadAdd",
+        "NumpadBackspace",
+        "NumpadClear",
+        "NumpadClearEntry",
+        "NumpadComma",
+        "NumpadDecimal",
+        "NumpadDivide",
+        "NumpadEnter",
+        "NumpadEqual",
+        "NumpadHash",
+        "NumpadMemoryAdd",
+        "NumpadMemoryClear",
+        "NumpadMemoryRecall",
+        "NumpadMemoryStore",
+        "NumpadMemorySubtract",
+        "NumpadMultiply",
+        "NumpadParenLeft",
+        "NumpadParenRight",
+        "NumpadStar",
+        "NumpadSubtract",
+        // Function section
+        "Escape",
+        "F1",
+        "F2",
+        "F3",
+        "F4",
+        "F5",
+        "F6",
+        "F7",
+        "F8",
+        "F9",
+        "F10",
+        "F11",
+        "F12",
+        "F13",
+        "F14",
+        "F15",
+        "F16",
+        "F17",
+        "F18",
+        "F19",
+        "F20",
+        "F21",
+        "F22",
+        "F23",
+        "F24",
+        "Fn",
+        "FnLock",
+        "PrintScreen",
+        "ScrollLock",
+        "Pause",
+        // Media keys
+        "BrowserBack",
+        "BrowserFavorites",
+        "BrowserForward",
+        "BrowserHome",
+        "BrowserRefresh",
+        "BrowserSearch",
+        "BrowserStop",
+        "Eject",
+        "LaunchApp1",
+        "LaunchApp2",
+        "LaunchMail",
+        "MediaPlayPause",
+        "MediaSelect",
+        "MediaStop",
+        "MediaTrackNext",
+        "MediaTrackPrevious",
+        "Power",
+        "Sleep",
+        "AudioVolumeDown",
+        "AudioVolumeMute",
+        "AudioVolumeUp",
+        "WakeUp",
+        // Legacy keys
+        "Hyper",
+        "Super",
+        "Turbo",
+        "Abort",
+        "Resume",
+        "Suspend",
+        "Again",
+        "Copy",
+        "Cut",
+        "Find",
+        "Open",
+        "Paste",
+        "Props",
+        "Select",
+        "Undo",
+        "Hiragana",
+        "Katakana",
+    ];
+
+    /// Codes with no key that can be injected on this platform.
+    #[cfg(target_os = "windows")]
+    const UNAVAILABLE: &[&str] = &[
+        "Lang3",
+        "Lang4",
+        "Lang5",
+        "NumpadBackspace",
+        "NumpadClearEntry",
+        "NumpadHash",
+        "NumpadMemoryAdd",
+        "NumpadMemoryClear",
+        "NumpadMemoryRecall",
+        "NumpadMemoryStore",
+        "NumpadMemorySubtract",
+        "NumpadParenLeft",
+        "NumpadParenRight",
+        "NumpadStar",
+        "Fn",
+        "FnLock",
+        "Eject",
+        "Power",
+        "WakeUp",
+        "Hyper",
+        "Super",
+        "Turbo",
+        "Abort",
+        "Resume",
+        "Suspend",
+        "Again",
+        "Copy",
+        "Cut",
+        "Find",
+        "Open",
+        "Paste",
+        "Props",
+        "Undo",
+        "Hiragana",
+        "Katakana",
+    ];
+    #[cfg(target_os = "macos")]
+    const UNAVAILABLE: &[&str] = &[
+        "Convert",
+        "KanaMode",
+        "Lang3",
+        "Lang4",
+        "Lang5",
+        "NonConvert",
+        "Help",
+        "NumpadBackspace",
+        "NumpadClearEntry",
+        "NumpadHash",
+        "NumpadMemoryAdd",
+        "NumpadMemoryClear",
+        "NumpadMemoryRecall",
+        "NumpadMemoryStore",
+        "NumpadMemorySubtract",
+        "NumpadParenLeft",
+        "NumpadParenRight",
+        "NumpadStar",
+        "F21",
+        "F22",
+        "F23",
+        "F24",
+        "FnLock",
+        "PrintScreen",
+        "ScrollLock",
+        "Pause",
+        "BrowserBack",
+        "BrowserFavorites",
+        "BrowserForward",
+        "BrowserHome",
+        "BrowserRefresh",
+        "BrowserSearch",
+        "BrowserStop",
+        "Eject",
+        "LaunchApp1",
+        "LaunchApp2",
+        "LaunchMail",
+        "MediaPlayPause",
+        "MediaSelect",
+        "MediaStop",
+        "MediaTrackNext",
+        "MediaTrackPrevious",
+        "Power",
+        "Sleep",
+        "WakeUp",
+        "Hyper",
+        "Super",
+        "Turbo",
+        "Abort",
+        "Resume",
+        "Suspend",
+        "Again",
+        "Copy",
+        "Cut",
+        "Find",
+        "Open",
+        "Paste",
+        "Props",
+        "Select",
+        "Undo",
+        "Hiragana",
+        "Katakana",
+    ];
+    #[cfg(target_os = "linux")]
+    const UNAVAILABLE: &[&str] = &[
+        "Lang3",
+        "Lang4",
+        "Lang5",
+        "NumpadBackspace",
+        "NumpadClear",
+        "NumpadClearEntry",
+        "NumpadHash",
+        "NumpadMemoryAdd",
+        "NumpadMemoryClear",
+        "NumpadMemoryRecall",
+        "NumpadMemoryStore",
+        "NumpadMemorySubtract",
+        "NumpadParenLeft",
+        "NumpadParenRight",
+        "NumpadStar",
+        "Fn",
+        "FnLock",
+        "Eject",
+        "MediaSelect",
+        "Power",
+        "WakeUp",
+        "Hyper",
+        "Super",
+        "Turbo",
+        "Abort",
+        "Resume",
+        "Suspend",
+        "Again",
+        "Copy",
+        "Cut",
+        "Find",
+        "Open",
+        "Paste",
+        "Props",
+        "Select",
+        "Undo",
+        "Hiragana",
+        "Katakana",
+    ];
+
+    #[test]
+    fn standard_codes_resolve() {
+        for code in STANDARD_CODES {
+            assert!(
+                KEYS.iter().any(|(known, _)| known == code),
+                "{} has no key",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn codes_are_unique() {
+        let mut seen = HashMap::new();
+        for (code, _) in KEYS {
+            assert!(seen.insert(code, ()).is_none(), "{} is listed twice", code);
+        }
+    }
+
+    #[test]
+    fn keys_are_not_shared_unless_intended() {
+        let mut codes_by_key: HashMap<NativeKey, &str> = HashMap::new();
+        for (code, key) in KEYS {
+            if let Some(other) = codes_by_key.insert(*key, code) {
+                assert!(
+                    SHARED_KEYS.contains(&(other, code)) || SHARED_KEYS.contains(&(code, other)),
+                    "{} and {} are the same key {:?}",
+                    other,
+                    code,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn every_code_is_mapped_or_unavailable() {
+        for code in SPEC_CODES {
+            let mapped = KEYS.iter().any(|(known, _)| known == code);
+            let unavailable = UNAVAILABLE.contains(code);
+            assert!(
+                mapped || unavailable,
+                "{} is neither mapped nor unavailable",
+                code
+            );
+            assert!(
+                !(mapped && unavailable),
+                "{} is mapped but listed unavailable",
+                code
+            );
+        }
+    }
 
-        // Unsupported Keys:
-        // Pause, NumLock, Context Menu, Numpad*
-        // Currently does not distinguish between left and right keys
-        match s {
-            "Backspace" => Ok(enigo::Key::Backspace),
-            "Tab" => Ok(enigo::Key::Tab),
-            "ShiftLeft" => Ok(enigo::Key::Shift),
-            "ShiftRight" => Ok(enigo::Key::Shift),
-            "ControlLeft" => Ok(enigo::Key::Control),
-            "ControlRight" => Ok(enigo::Key::Control),
-            "AltLeft" => Ok(enigo::Key::Alt),
-            "AltRight" => Ok(enigo::Key::Alt),
-            "CapsLock" => Ok(enigo::Key::CapsLock),
-            "Escape" => Ok(enigo::Key::Escape),
-            "Space" => Ok(enigo::Key::Space),
-            "ArrowLeft" => Ok(enigo::Key::LeftArrow),
-            "ArrowUp" => Ok(enigo::Key::UpArrow),
-            "ArrowRight" => Ok(enigo::Key::RightArrow),
-            "ArrowDown" => Ok(enigo::Key::DownArrow),
-            "MetaLeft" => Ok(enigo::Key::Meta),
-            "MetaRight" => Ok(enigo::Key::Meta),
-            "End" => Ok(enigo::Key::End),
-            "F1" => Ok(enigo::Key::F1),
-            "F2" => Ok(enigo::Key::F2),
-            "F3" => Ok(enigo::Key::F3),
-            "F4" => Ok(enigo::Key::F4),
-            "F5" => Ok(enigo::Key::F5),
-            "F6" => Ok(enigo::Key::F6),
-            "F7" => Ok(enigo::Key::F7),
-            "F8" => Ok(enigo::Key::F8),
-            "F9" => Ok(enigo::Key::F9),
-            "F10" => Ok(enigo::Key::F10),
-            "F11" => Ok(enigo::Key::F11),
-            "F12" => Ok(enigo::Key::F12),
-            "BracketLeft" => Ok(enigo::Key::Layout('[')),
-            "BracketRight" => Ok(enigo::Key::Layout(']')),
-            "Backslash" => Ok(enigo::Key::Layout('\\')),
-            "Semicolon" => Ok(enigo::Key::Layout(';')),
-            "Backquote" => Ok(enigo::Key::Layout('`')),
-            "Quote" => Ok(enigo::Key::Layout('\'')),
-            "Equal" => Ok(enigo::Key::Layout('=')),
-            "Minus" => Ok(enigo::Key::Layout('-')),
-            "Comma" => Ok(enigo::Key::Layout(',')),
-            "Period" => Ok(enigo::Key::Layout('.')),
-            "Slash" => Ok(enigo::Key::Layout('/')),
-            "Delete" => Ok(enigo::Key::Delete),
-            "Home" => Ok(enigo::Key::Home),
-            "Enter" => Ok(enigo::Key::Return),
-            "PageUp" => Ok(enigo::Key::PageUp),
-            "PageDown" => Ok(enigo::Key::PageDown),
-            _ => Err(format_err!("Unknown key: {}", s)),
+    #[test]
+    fn codes_are_in_the_spec() {
+        for code in KEYS.iter().map(|(code, _)| code).chain(UNAVAILABLE) {
+            assert!(
+                SPEC_CODES.contains(code),
+                "{} is not a KeyboardEvent code",
+                code
+            );
         }
     }
 }
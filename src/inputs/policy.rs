@@ -0,0 +1,408 @@
+use std::collections::HashSet;
+use std::time::Instant;
+
+use crate::config::InputConfig;
+
+use super::InputMessage;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Modifier {
+    Control,
+    Alt,
+    Shift,
+    Meta,
+}
+
+impl Modifier {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Control" | "Ctrl" => Some(Modifier::Control),
+            "Alt" | "Option" => Some(Modifier::Alt),
+            "Shift" => Some(Modifier::Shift),
+            "Meta" | "Cmd" | "Win" => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+
+    /// The modifier of a KeyboardEvent.code, if it is one.
+    fn of_key(key: &str) -> Option<Self> {
+        match key {
+            "ControlLeft" | "ControlRight" => Some(Modifier::Control),
+            "AltLeft" | "AltRight" => Some(Modifier::Alt),
+            "ShiftLeft" | "ShiftRight" => Some(Modifier::Shift),
+            "MetaLeft" | "MetaRight" | "OSLeft" | "OSRight" => Some(Modifier::Meta),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Shortcut {
+    name: String,
+    modifiers: HashSet<Modifier>,
+    key: String,
+}
+
+impl Shortcut {
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        let key = parts.pop()?;
+        if key.is_empty() || Modifier::parse(key).is_some() {
+            return None;
+        }
+        let modifiers = parts
+            .into_iter()
+            .map(Modifier::parse)
+            .collect::<Option<HashSet<_>>>()?;
+        Some(Self {
+            name: s.to_string(),
+            modifiers,
+            key: key.to_string(),
+        })
+    }
+}
+
+/// What the policy decided about an input event.
+#[derive(Debug)]
+pub enum Verdict {
+    Allow,
+    /// The event is dropped silently.
+    Drop,
+    /// The event is dropped and the viewer told about the shortcut it pressed.
+    Blocked(String),
+}
+
+/// The state the policy keeps for each viewer.
+pub struct PolicyState {
+    /// Modifiers currently held, once for each key holding one.
+    modifiers: Vec<Modifier>,
+    /// Keys whose press was blocked, so their release is dropped as well.
+    blocked: HashSet<String>,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl PolicyState {
+    /// Forget the keys held down once they were released on the viewer's behalf,
+    /// keeping the rate limit.
+    pub fn release_keys(&mut self) {
+        self.modifiers.clear();
+        self.blocked.clear();
+    }
+
+    fn refill(&mut self, rate: f64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.last_refill = now;
+    }
+}
+
+/// Restrictions applied to remote input on top of the viewer's permission:
+/// blocked shortcuts, keys while the sharer app is focused, and a rate limit.
+/// Pointer events are confined to the shared area by `InputArea`.
+pub struct InputPolicy {
+    shortcuts: Vec<Shortcut>,
+    block_sharer_app: bool,
+    max_events_per_second: f64,
+}
+
+impl InputPolicy {
+    pub fn new(config: &InputConfig) -> Self {
+        let shortcuts = config
+            .blocked_shortcuts
+            .iter()
+            .filter_map(|s| {
+                let shortcut = Shortcut::parse(s);
+                if shortcut.is_none() {
+                    warn!("Ignoring invalid blocked shortcut: {}", s);
+                }
+                shortcut
+            })
+            .collect();
+        Self {
+            shortcuts,
+            block_sharer_app: config.block_sharer_app,
+            max_events_per_second: config.max_events_per_second as f64,
+        }
+    }
+
+    pub fn new_state(&self) -> PolicyState {
+        PolicyState {
+            modifiers: Vec::new(),
+            blocked: HashSet::new(),
+            tokens: self.max_events_per_second,
+            last_refill: Instant::now(),
+        }
+    }
+
+    pub fn check(&self, state: &mut PolicyState, msg: &InputMessage) -> Verdict {
+        match msg {
+            // releases always go through so nothing stays held down, unless the
+            // press never did
+            InputMessage::KeyUp { key } => {
+                if let Some(modifier) = Modifier::of_key(key) {
+                    if let Some(i) = state.modifiers.iter().position(|m| *m == modifier) {
+                        state.modifiers.swap_remove(i);
+                    }
+                }
+                if state.blocked.remove(key) {
+                    return Verdict::Drop;
+                }
+                return Verdict::Allow;
+            }
+            InputMessage::MouseUp { .. }
+            | InputMessage::TouchEnd { .. }
+            | InputMessage::TouchCancel { .. }
+            | InputMessage::PenUp { .. } => return Verdict::Allow,
+            _ => {}
+        }
+
+        state.refill(self.max_events_per_second);
+        if state.tokens < 1. {
+            return Verdict::Drop;
+        }
+        state.tokens -= 1.;
+
+        if let InputMessage::KeyDown { key } = msg {
+            let modifier = Modifier::of_key(key);
+            if let Some(modifier) = modifier {
+                state.modifiers.push(modifier);
+            }
+            if self.block_sharer_app && platform::sharer_focused() {
+                if modifier.is_none() {
+                    state.blocked.insert(key.clone());
+                }
+                return Verdict::Drop;
+            }
+            // extra modifiers don't get around a shortcut, e.g. Meta+Shift+KeyL still
+            // locks the screen like Meta+KeyL
+            let held: HashSet<Modifier> = state.modifiers.iter().copied().collect();
+            if let Some(shortcut) = self
+                .shortcuts
+                .iter()
+                .find(|s| s.key == *key && s.modifiers.is_subset(&held))
+            {
+                state.blocked.insert(key.clone());
+                return Verdict::Blocked(shortcut.name.clone());
+            }
+        }
+        if let InputMessage::TextInput { .. } = msg {
+            if self.block_sharer_app && platform::sharer_focused() {
+                return Verdict::Drop;
+            }
+        }
+        Verdict::Allow
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    /// Whether a window of this process is in the foreground.
+    pub fn sharer_focused() -> bool {
+        let mut pid = 0;
+        unsafe {
+            GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid));
+        }
+        pid == std::process::id()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc::runtime::{Object, BOOL, NO};
+    use objc::{class, msg_send, sel, sel_impl};
+
+    /// Whether this application is the active one.
+    pub fn sharer_focused() -> bool {
+        unsafe {
+            let app: *mut Object = msg_send![class!(NSRunningApplication), currentApplication];
+            let active: BOOL = msg_send![app, isActive];
+            active != NO
+        }
+    }
+}
+
+/// X11 and Wayland have no common way to tell which window is focused, the sharer is
+/// never considered to be.
+#[cfg(target_os = "linux")]
+mod platform {
+    pub fn sharer_focused() -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::MouseButton;
+    use super::*;
+
+    fn policy(blocked_shortcuts: &[&str], max_events_per_second: u32) -> InputPolicy {
+        InputPolicy::new(&InputConfig {
+            blocked_shortcuts: blocked_shortcuts.iter().map(|s| s.to_string()).collect(),
+            block_sharer_app: false,
+            max_events_per_second,
+            ..Default::default()
+        })
+    }
+
+    fn key_down(key: &str) -> InputMessage {
+        InputMessage::KeyDown {
+            key: key.to_string(),
+        }
+    }
+
+    fn key_up(key: &str) -> InputMessage {
+        InputMessage::KeyUp {
+            key: key.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_shortcut() {
+        let shortcut = Shortcut::parse("Ctrl + Alt+Delete").unwrap();
+        assert_eq!(
+            shortcut.modifiers,
+            HashSet::from([Modifier::Control, Modifier::Alt])
+        );
+        assert_eq!(shortcut.key, "Delete");
+        assert_eq!(shortcut.name, "Ctrl + Alt+Delete");
+
+        let shortcut = Shortcut::parse("F4").unwrap();
+        assert!(shortcut.modifiers.is_empty());
+        assert_eq!(shortcut.key, "F4");
+    }
+
+    #[test]
+    fn parse_invalid_shortcut() {
+        assert!(Shortcut::parse("").is_none());
+        assert!(Shortcut::parse("Meta+").is_none());
+        assert!(Shortcut::parse("Meta+Shift").is_none());
+        assert!(Shortcut::parse("Hyper+KeyL").is_none());
+    }
+
+    #[test]
+    fn blocks_shortcut() {
+        let policy = policy(&["Meta+KeyL"], 100);
+        let mut state = policy.new_state();
+        assert!(matches!(
+            policy.check(&mut state, &key_down("MetaLeft")),
+            Verdict::Allow
+        ));
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyL")),
+            Verdict::Blocked(name) if name == "Meta+KeyL"
+        ));
+        // the release of a blocked press is dropped too
+        assert!(matches!(
+            policy.check(&mut state, &key_up("KeyL")),
+            Verdict::Drop
+        ));
+        assert!(matches!(
+            policy.check(&mut state, &key_up("MetaLeft")),
+            Verdict::Allow
+        ));
+        // without the modifier the key is just a key
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyL")),
+            Verdict::Allow
+        ));
+    }
+
+    #[test]
+    fn blocks_shortcut_with_extra_modifiers() {
+        let policy = policy(&["Meta+KeyL"], 100);
+        let mut state = policy.new_state();
+        policy.check(&mut state, &key_down("MetaRight"));
+        policy.check(&mut state, &key_down("ShiftLeft"));
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyL")),
+            Verdict::Blocked(_)
+        ));
+    }
+
+    #[test]
+    fn modifier_held_on_both_sides() {
+        let policy = policy(&["Control+KeyW"], 100);
+        let mut state = policy.new_state();
+        policy.check(&mut state, &key_down("ControlLeft"));
+        policy.check(&mut state, &key_down("ControlRight"));
+        policy.check(&mut state, &key_up("ControlLeft"));
+        // still held by the right key
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyW")),
+            Verdict::Blocked(_)
+        ));
+    }
+
+    #[test]
+    fn released_keys_are_forgotten_but_not_the_rate() {
+        let policy = policy(&["Meta+KeyL"], 2);
+        let mut state = policy.new_state();
+        policy.check(&mut state, &key_down("MetaLeft"));
+        state.release_keys();
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyL")),
+            Verdict::Allow
+        ));
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyL")),
+            Verdict::Drop
+        ));
+    }
+
+    #[test]
+    fn limits_rate() {
+        let policy = policy(&[], 2);
+        let mut state = policy.new_state();
+        let mouse_move = InputMessage::MouseMove { x: 0.5, y: 0.5 };
+        assert!(matches!(
+            policy.check(&mut state, &mouse_move),
+            Verdict::Allow
+        ));
+        assert!(matches!(
+            policy.check(&mut state, &mouse_move),
+            Verdict::Allow
+        ));
+        assert!(matches!(
+            policy.check(&mut state, &mouse_move),
+            Verdict::Drop
+        ));
+    }
+
+    #[test]
+    fn release_always_passes() {
+        let policy = policy(&[], 1);
+        let mut state = policy.new_state();
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyA")),
+            Verdict::Allow
+        ));
+        // out of tokens
+        assert!(matches!(
+            policy.check(&mut state, &key_down("KeyB")),
+            Verdict::Drop
+        ));
+        let releases = [
+            key_up("KeyA"),
+            key_up("KeyB"),
+            InputMessage::MouseUp {
+                x: 0.,
+                y: 0.,
+                button: MouseButton::Left,
+            },
+            InputMessage::TouchEnd {
+                id: 0,
+                x: 0.,
+                y: 0.,
+            },
+            InputMessage::TouchCancel { id: 0 },
+            InputMessage::PenUp { x: 0., y: 0. },
+        ];
+        for release in &releases {
+            assert!(matches!(policy.check(&mut state, release), Verdict::Allow));
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::InputMessage;
+use crate::Result;
+
+/// A line of a recording.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// Milliseconds since the recording started.
+    pub time_ms: u64,
+    /// The uuid of the viewer that sent the message.
+    pub viewer: String,
+    pub message: InputMessage,
+}
+
+/// Writes the input messages of a session to a file, one JSON object per line.
+pub struct Recorder {
+    file: LineWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    /// Create a new recording, failing rather than overwriting an existing one.
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+        Ok(Self {
+            file: LineWriter::new(file),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, viewer: &str, message: &InputMessage) -> Result<()> {
+        #[derive(Serialize)]
+        struct Line<'a> {
+            time_ms: u64,
+            viewer: &'a str,
+            message: &'a InputMessage,
+        }
+        let line = serde_json::to_string(&Line {
+            time_ms: self.start.elapsed().as_millis() as u64,
+            viewer,
+            message,
+        })?;
+        // flushed at each line, so the recording survives a crash
+        writeln!(self.file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Where to record a session started at `start` when recording to `path`, which
+/// has the start time added to its name, e.g. `input-20230101-120000.jsonl`, so the
+/// recordings of earlier sessions are kept.
+pub fn session_path(path: &Path, start: DateTime<Local>) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{}-{}", stem, start.format("%Y%m%d-%H%M%S"));
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+/// Read a recording made by `Recorder`.
+pub fn read(path: &Path) -> Result<Vec<RecordedInput>> {
+    let mut inputs = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        inputs.push(serde_json::from_str(&line)?);
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn session_path_adds_start_time() {
+        let start = Local.with_ymd_and_hms(2023, 1, 2, 3, 4, 5).unwrap();
+        assert_eq!(
+            session_path(Path::new("recordings/input.jsonl"), start),
+            Path::new("recordings/input-20230102-030405.jsonl")
+        );
+        assert_eq!(
+            session_path(Path::new("input"), start),
+            Path::new("input-20230102-030405")
+        );
+    }
+}
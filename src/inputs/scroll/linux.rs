@@ -0,0 +1,15 @@
+use enigo::MouseControllable;
+
+/// X11 scrolls by wheel steps, which enigo calls lines, so fractions of a step are
+/// carried over until they add up to one.
+pub const UNITS_PER_PIXEL: f64 = 1. / super::PIXELS_PER_STEP;
+
+/// Scroll by wheel steps, positive to scroll right and down.
+pub fn scroll(enigo: &mut enigo::Enigo, x: i32, y: i32) {
+    if y != 0 {
+        enigo.mouse_scroll_y(y);
+    }
+    if x != 0 {
+        enigo.mouse_scroll_x(x);
+    }
+}
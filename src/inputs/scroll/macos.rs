@@ -0,0 +1,39 @@
+use std::ffi::c_void;
+
+/// Scroll events are in pixels, which macOS scrolls by smoothly.
+pub const UNITS_PER_PIXEL: f64 = 1.;
+
+const SCROLL_EVENT_UNIT_PIXEL: u32 = 0;
+const HID_EVENT_TAP: u32 = 0;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventCreateScrollWheelEvent(
+        source: *const c_void,
+        units: u32,
+        wheel_count: u32,
+        wheel1: i32,
+        ...
+    ) -> *const c_void;
+    fn CGEventPost(tap: u32, event: *const c_void);
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Scroll by pixels, positive to scroll right and down.
+pub fn scroll(_: &mut enigo::Enigo, x: i32, y: i32) {
+    unsafe {
+        // positive wheel values scroll up and left
+        let event =
+            CGEventCreateScrollWheelEvent(std::ptr::null(), SCROLL_EVENT_UNIT_PIXEL, 2, -y, -x);
+        if event.is_null() {
+            warn!("Failed to create scroll event");
+            return;
+        }
+        CGEventPost(HID_EVENT_TAP, event);
+        CFRelease(event);
+    }
+}
@@ -0,0 +1,183 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+use linux as platform;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+use macos as platform;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+
+/// Pixels scrolled by one wheel step, as browsers do.
+const PIXELS_PER_STEP: f64 = 100.;
+/// Browsers scroll three lines per wheel step.
+const PIXELS_PER_LINE: f64 = PIXELS_PER_STEP / 3.;
+/// How often smoothed scrolling is injected.
+pub const SMOOTHING_INTERVAL: Duration = Duration::from_millis(8);
+/// Share of the remaining distance scrolled at each interval.
+const SMOOTHING_FACTOR: f64 = 0.3;
+/// Deltas of at least this many pixels are smoothed. Smaller ones come from devices
+/// that scroll smoothly already, such as trackpads.
+const SMOOTHING_THRESHOLD: f64 = 30.;
+
+/// The unit of wheel deltas, as `WheelEvent.deltaMode`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WheelMode {
+    #[default]
+    Pixel,
+    Line,
+    Page,
+}
+
+/// The scrolling of a viewer. Deltas are kept in pixels and turned into the finest
+/// unit the platform supports, carrying over what doesn't make up a whole unit, for
+/// [`inject`] to scroll by.
+#[derive(Default)]
+pub struct ScrollState {
+    /// Distance left to scroll smoothly.
+    pending: (f64, f64),
+    /// Distance scrolled by the viewer but not injected yet, less than a unit.
+    remainder: (f64, f64),
+}
+
+impl ScrollState {
+    /// Scroll by the deltas, positive to scroll right and down. `page` is the
+    /// height of a page in pixels. Returns the units to scroll by now, nothing when
+    /// the distance is smoothed.
+    pub fn scroll(
+        &mut self,
+        (dx, dy): (f64, f64),
+        mode: WheelMode,
+        page: f64,
+        smooth: bool,
+    ) -> (i32, i32) {
+        let factor = match mode {
+            WheelMode::Pixel => 1.,
+            WheelMode::Line => PIXELS_PER_LINE,
+            WheelMode::Page => page,
+        };
+        let (dx, dy) = (dx * factor, dy * factor);
+        if smooth && (dx.abs() >= SMOOTHING_THRESHOLD || dy.abs() >= SMOOTHING_THRESHOLD) {
+            self.pending.0 += dx;
+            self.pending.1 += dy;
+            (0, 0)
+        } else {
+            self.units(dx, dy)
+        }
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.pending != (0., 0.)
+    }
+
+    /// Scroll a share of the pending distance, called every `SMOOTHING_INTERVAL`.
+    /// Returns the units to scroll by.
+    pub fn tick(&mut self) -> (i32, i32) {
+        let step = |pending: f64| {
+            let step = pending * SMOOTHING_FACTOR;
+            // finish once less than a pixel is left
+            if step.abs() < 1. {
+                pending
+            } else {
+                step
+            }
+        };
+        let (dx, dy) = (step(self.pending.0), step(self.pending.1));
+        self.pending.0 -= dx;
+        self.pending.1 -= dy;
+        self.units(dx, dy)
+    }
+
+    /// The whole units in the deltas and what was left over before, keeping the rest.
+    fn units(&mut self, dx: f64, dy: f64) -> (i32, i32) {
+        let x = self.remainder.0 + dx;
+        let y = self.remainder.1 + dy;
+        let units_x = (x * platform::UNITS_PER_PIXEL).trunc();
+        let units_y = (y * platform::UNITS_PER_PIXEL).trunc();
+        self.remainder = (
+            x - units_x / platform::UNITS_PER_PIXEL,
+            y - units_y / platform::UNITS_PER_PIXEL,
+        );
+        (units_x as i32, units_y as i32)
+    }
+}
+
+/// Scroll by units returned by a [`ScrollState`].
+pub fn inject(enigo: &mut enigo::Enigo, (x, y): (i32, i32)) {
+    if x != 0 || y != 0 {
+        platform::scroll(enigo, x, y);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The pixels scrolled by `units` and what was carried over.
+    fn pixels(state: &ScrollState, (_, units): (i64, i64)) -> f64 {
+        units as f64 / platform::UNITS_PER_PIXEL + state.remainder.1
+    }
+
+    fn scroll(state: &mut ScrollState, dy: f64, mode: WheelMode) -> (i64, i64) {
+        let (x, y) = state.scroll((0., dy), mode, 800., false);
+        (x as i64, y as i64)
+    }
+
+    #[test]
+    fn small_deltas_add_up() {
+        let mut state = ScrollState::default();
+        let mut total = (0, 0);
+        for _ in 0..400 {
+            let (x, y) = scroll(&mut state, 0.25, WheelMode::Pixel);
+            total = (total.0 + x, total.1 + y);
+        }
+        assert_eq!(total.0, 0);
+        assert!((pixels(&state, total) - 100.).abs() < 1e-6);
+        assert!(total.1 as f64 >= (100. * platform::UNITS_PER_PIXEL).round() - 1.);
+    }
+
+    #[test]
+    fn modes_scale_to_pixels() {
+        let mut state = ScrollState::default();
+        let units = scroll(&mut state, 3., WheelMode::Line);
+        assert!((pixels(&state, units) - PIXELS_PER_STEP).abs() < 1e-6);
+
+        let mut state = ScrollState::default();
+        let units = scroll(&mut state, -1., WheelMode::Page);
+        assert!((pixels(&state, units) + 800.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn smoothing_scrolls_the_whole_distance() {
+        let mut state = ScrollState::default();
+        assert_eq!(
+            state.scroll((0., 300.), WheelMode::Pixel, 800., true),
+            (0, 0)
+        );
+        let mut total = (0, 0);
+        let mut ticks = 0;
+        while state.is_smoothing() {
+            let (x, y) = state.tick();
+            total = (total.0 + x as i64, total.1 + y as i64);
+            ticks += 1;
+            assert!(ticks < 100, "smoothing does not converge");
+        }
+        assert!(ticks > 1);
+        assert!((pixels(&state, total) - 300.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn small_deltas_are_not_smoothed() {
+        let mut state = ScrollState::default();
+        state.scroll((0., SMOOTHING_THRESHOLD - 1.), WheelMode::Pixel, 800., true);
+        assert!(!state.is_smoothing());
+    }
+}
@@ -0,0 +1,43 @@
+use std::mem::size_of;
+
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL, MOUSEINPUT,
+    MOUSE_EVENT_FLAGS,
+};
+use windows::Win32::UI::WindowsAndMessaging::WHEEL_DELTA;
+
+/// Wheel deltas are in 1/120 of a step, which applications supporting
+/// high-resolution wheels scroll by proportionally.
+pub const UNITS_PER_PIXEL: f64 = WHEEL_DELTA as f64 / super::PIXELS_PER_STEP;
+
+fn wheel_input(flags: MOUSE_EVENT_FLAGS, delta: i32) -> INPUT {
+    INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                mouseData: delta,
+                dwFlags: flags,
+                ..Default::default()
+            },
+        },
+    }
+}
+
+/// Scroll by wheel units, positive to scroll right and down.
+pub fn scroll(_: &mut enigo::Enigo, x: i32, y: i32) {
+    let mut inputs = Vec::with_capacity(2);
+    // a positive vertical delta scrolls up
+    if y != 0 {
+        inputs.push(wheel_input(MOUSEEVENTF_WHEEL, -y));
+    }
+    if x != 0 {
+        inputs.push(wheel_input(MOUSEEVENTF_HWHEEL, x));
+    }
+    let sent = unsafe { SendInput(&inputs, size_of::<INPUT>() as i32) };
+    if sent as usize != inputs.len() {
+        warn!(
+            "Failed to inject scrolling: {}",
+            windows::core::Error::from_win32()
+        );
+    }
+}
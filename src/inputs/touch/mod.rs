@@ -0,0 +1,160 @@
+use enigo::{KeyboardControllable, MouseControllable};
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+use self::windows as platform;
+
+/// Zoom of one wheel step when a pinch is emulated with the zoom modifier and wheel.
+const ZOOM_PER_STEP: f64 = 1.1;
+
+/// The modifier most applications zoom with when the wheel is scrolled, and the
+/// KeyboardEvent codes of its keys.
+#[cfg(target_os = "macos")]
+const ZOOM_MODIFIER: enigo::Key = enigo::Key::Meta;
+#[cfg(target_os = "macos")]
+pub const ZOOM_MODIFIER_KEYS: [&str; 2] = ["MetaLeft", "MetaRight"];
+#[cfg(not(target_os = "macos"))]
+const ZOOM_MODIFIER: enigo::Key = enigo::Key::Control;
+#[cfg(not(target_os = "macos"))]
+pub const ZOOM_MODIFIER_KEYS: [&str; 2] = ["ControlLeft", "ControlRight"];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TouchPhase {
+    Start,
+    Move,
+    End,
+    /// The touch ended without its action being taken, e.g. a tap turned scroll.
+    Cancel,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PenPhase {
+    Hover,
+    Down,
+    Move,
+    Up,
+}
+
+/// Touch, pen and gesture input. Touches and the pen are injected natively where
+/// the platform supports it, otherwise the first touch and the pen drive the mouse.
+/// Pinches are emulated with Ctrl+wheel, or Cmd+wheel on macOS, which most
+/// applications zoom with.
+pub struct TouchInput {
+    injector: platform::Injector,
+    /// The touch driving the mouse when touches are emulated.
+    primary: Option<u32>,
+    /// Zoom not yet applied, in wheel steps.
+    zoom: f64,
+}
+
+impl TouchInput {
+    pub fn new() -> Self {
+        Self {
+            injector: platform::Injector::new(),
+            primary: None,
+            zoom: 0.,
+        }
+    }
+
+    /// `position` is `None` for cancelled touches.
+    pub fn touch(
+        &mut self,
+        enigo: &mut enigo::Enigo,
+        id: u32,
+        phase: TouchPhase,
+        position: Option<(i32, i32)>,
+    ) {
+        if self.injector.touch(id, phase, position) {
+            return;
+        }
+        if let Some((x, y)) = position {
+            if (self.primary.is_none() && phase == TouchPhase::Start) || self.primary == Some(id) {
+                enigo.mouse_move_to(x, y);
+            }
+        }
+        match phase {
+            TouchPhase::Start if self.primary.is_none() => {
+                self.primary = Some(id);
+                enigo.mouse_down(enigo::MouseButton::Left);
+            }
+            TouchPhase::End | TouchPhase::Cancel if self.primary == Some(id) => {
+                self.primary = None;
+                enigo.mouse_up(enigo::MouseButton::Left);
+            }
+            _ => {}
+        }
+    }
+
+    /// `pressure` is from 0 to 1.
+    pub fn pen(
+        &mut self,
+        enigo: &mut enigo::Enigo,
+        phase: PenPhase,
+        (x, y): (i32, i32),
+        pressure: f32,
+    ) {
+        if self.injector.pen(phase, (x, y), pressure.clamp(0., 1.)) {
+            return;
+        }
+        enigo.mouse_move_to(x, y);
+        match phase {
+            PenPhase::Down => enigo.mouse_down(enigo::MouseButton::Left),
+            PenPhase::Up => enigo.mouse_up(enigo::MouseButton::Left),
+            PenPhase::Hover | PenPhase::Move => {}
+        }
+    }
+
+    /// Zoom by `scale` around the position, more than 1 zooming in. `wheel_up` is
+    /// the wheel value that scrolls up. `modifier_held` tells whether the viewer
+    /// holds the zoom modifier already, so it is left as it is.
+    pub fn pinch(
+        &mut self,
+        enigo: &mut enigo::Enigo,
+        (x, y): (i32, i32),
+        scale: f32,
+        wheel_up: i32,
+        modifier_held: bool,
+    ) {
+        if scale <= 0. || !scale.is_finite() {
+            return;
+        }
+        self.zoom += (scale as f64).ln() / ZOOM_PER_STEP.ln();
+        let steps = self.zoom.trunc();
+        if steps == 0. {
+            return;
+        }
+        self.zoom -= steps;
+        enigo.mouse_move_to(x, y);
+        if !modifier_held {
+            enigo.key_down(ZOOM_MODIFIER);
+        }
+        enigo.mouse_scroll_y(steps as i32 * wheel_up);
+        if !modifier_held {
+            enigo.key_up(ZOOM_MODIFIER);
+        }
+    }
+}
+
+/// No public API injects touches or pen input on macOS, and X11 has none either, so
+/// both are emulated.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+mod platform {
+    use super::{PenPhase, TouchPhase};
+
+    pub struct Injector;
+
+    impl Injector {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn touch(&mut self, _: u32, _: TouchPhase, _: Option<(i32, i32)>) -> bool {
+            false
+        }
+
+        pub fn pen(&mut self, _: PenPhase, _: (i32, i32), _: f32) -> bool {
+            false
+        }
+    }
+}
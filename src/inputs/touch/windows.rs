@@ -0,0 +1,180 @@
+use windows::Win32::Foundation::{POINT, RECT};
+use windows::Win32::UI::Controls::{
+    CreateSyntheticPointerDevice, DestroySyntheticPointerDevice, HSYNTHETICPOINTERDEVICE,
+    POINTER_FEEDBACK_DEFAULT, POINTER_TYPE_INFO, POINTER_TYPE_INFO_0,
+};
+use windows::Win32::UI::Input::Pointer::{
+    InitializeTouchInjection, InjectSyntheticPointerInput, InjectTouchInput, POINTER_FLAGS,
+    POINTER_FLAG_CANCELED, POINTER_FLAG_DOWN, POINTER_FLAG_INCONTACT, POINTER_FLAG_INRANGE,
+    POINTER_FLAG_UP, POINTER_FLAG_UPDATE, POINTER_INFO, POINTER_PEN_INFO, POINTER_TOUCH_INFO,
+    TOUCH_FEEDBACK_DEFAULT,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    PEN_FLAG_NONE, PEN_MASK_PRESSURE, PT_PEN, PT_TOUCH, TOUCH_FLAG_NONE, TOUCH_MASK_CONTACTAREA,
+    TOUCH_MASK_PRESSURE,
+};
+
+use super::{PenPhase, TouchPhase};
+
+/// Most touches injected at once, the most Windows supports is 256.
+const MAX_CONTACTS: u32 = 10;
+/// Half the size of the contact area of a touch, in pixels.
+const CONTACT_RADIUS: i32 = 2;
+
+struct Contact {
+    id: u32,
+    /// The pointer id, which must be below `MAX_CONTACTS`.
+    slot: u32,
+    position: (i32, i32),
+}
+
+/// Injects touches with `InjectTouchInput` (Windows 8) and the pen with a synthetic
+/// pointer device (Windows 10 1809).
+pub struct Injector {
+    touch: bool,
+    pen: Option<HSYNTHETICPOINTERDEVICE>,
+    contacts: Vec<Contact>,
+}
+
+impl Injector {
+    pub fn new() -> Self {
+        let touch =
+            unsafe { InitializeTouchInjection(MAX_CONTACTS, TOUCH_FEEDBACK_DEFAULT) }.as_bool();
+        if !touch {
+            warn!("Touch injection is not available, touches will move the mouse");
+        }
+        let pen = unsafe { CreateSyntheticPointerDevice(PT_PEN, 1, POINTER_FEEDBACK_DEFAULT) }
+            .map_err(|e| warn!("Pen injection is not available: {}", e))
+            .ok();
+        Self {
+            touch,
+            pen,
+            contacts: Vec::new(),
+        }
+    }
+
+    pub fn touch(&mut self, id: u32, phase: TouchPhase, position: Option<(i32, i32)>) -> bool {
+        if !self.touch {
+            return false;
+        }
+        let index = match self.contacts.iter().position(|c| c.id == id) {
+            Some(index) => index,
+            None if phase == TouchPhase::Start => {
+                let slot =
+                    match (0..MAX_CONTACTS).find(|s| self.contacts.iter().all(|c| c.slot != *s)) {
+                        Some(slot) => slot,
+                        None => return true, // too many fingers, ignore this one
+                    };
+                self.contacts.push(Contact {
+                    id,
+                    slot,
+                    position: position.unwrap_or_default(),
+                });
+                self.contacts.len() - 1
+            }
+            None => return true, // the start was ignored
+        };
+        if let Some(position) = position {
+            self.contacts[index].position = position;
+        }
+        let flags = match phase {
+            TouchPhase::Start => POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+            TouchPhase::Move => POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+            TouchPhase::End => POINTER_FLAG_UP,
+            TouchPhase::Cancel => POINTER_FLAG_UP | POINTER_FLAG_CANCELED,
+        };
+        // every frame lists all touches in contact
+        let frame: Vec<POINTER_TOUCH_INFO> = self
+            .contacts
+            .iter()
+            .enumerate()
+            .map(|(i, contact)| {
+                let flags = if i == index {
+                    flags
+                } else {
+                    POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT
+                };
+                touch_info(contact, flags)
+            })
+            .collect();
+        if !unsafe { InjectTouchInput(&frame) }.as_bool() {
+            warn!(
+                "Failed to inject touch: {}",
+                windows::core::Error::from_win32()
+            );
+        }
+        if matches!(phase, TouchPhase::End | TouchPhase::Cancel) {
+            self.contacts.remove(index);
+        }
+        true
+    }
+
+    pub fn pen(&mut self, phase: PenPhase, (x, y): (i32, i32), pressure: f32) -> bool {
+        let device = match self.pen {
+            Some(device) => device,
+            None => return false,
+        };
+        let flags = match phase {
+            PenPhase::Hover => POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE,
+            PenPhase::Down => POINTER_FLAG_DOWN | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+            PenPhase::Move => POINTER_FLAG_UPDATE | POINTER_FLAG_INRANGE | POINTER_FLAG_INCONTACT,
+            PenPhase::Up => POINTER_FLAG_UP | POINTER_FLAG_INRANGE,
+        };
+        let info = POINTER_TYPE_INFO {
+            r#type: PT_PEN,
+            Anonymous: POINTER_TYPE_INFO_0 {
+                penInfo: POINTER_PEN_INFO {
+                    pointerInfo: POINTER_INFO {
+                        pointerType: PT_PEN,
+                        pointerFlags: flags,
+                        ptPixelLocation: POINT { x, y },
+                        ..Default::default()
+                    },
+                    penFlags: PEN_FLAG_NONE,
+                    penMask: PEN_MASK_PRESSURE,
+                    // from 0 to 1024
+                    pressure: (pressure * 1024.) as u32,
+                    ..Default::default()
+                },
+            },
+        };
+        if !unsafe { InjectSyntheticPointerInput(device, &[info]) }.as_bool() {
+            warn!(
+                "Failed to inject pen input: {}",
+                windows::core::Error::from_win32()
+            );
+        }
+        true
+    }
+}
+
+impl Drop for Injector {
+    fn drop(&mut self) {
+        if let Some(device) = self.pen.take() {
+            unsafe { DestroySyntheticPointerDevice(device) };
+        }
+    }
+}
+
+fn touch_info(contact: &Contact, flags: POINTER_FLAGS) -> POINTER_TOUCH_INFO {
+    let (x, y) = contact.position;
+    POINTER_TOUCH_INFO {
+        pointerInfo: POINTER_INFO {
+            pointerType: PT_TOUCH,
+            pointerId: contact.slot,
+            pointerFlags: flags,
+            ptPixelLocation: POINT { x, y },
+            ..Default::default()
+        },
+        touchFlags: TOUCH_FLAG_NONE,
+        touchMask: TOUCH_MASK_CONTACTAREA | TOUCH_MASK_PRESSURE,
+        rcContact: RECT {
+            left: x - CONTACT_RADIUS,
+            top: y - CONTACT_RADIUS,
+            right: x + CONTACT_RADIUS,
+            bottom: y + CONTACT_RADIUS,
+        },
+        pressure: 512,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+
+use crate::output::ChannelHandler;
+
+/// How often viewers are pinged.
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// Pings waiting for their pong, older ones are considered lost.
+const MAX_PENDING_PINGS: usize = 8;
+/// Frame markers remembered to match the ids echoed by viewers.
+const MAX_MARKERS: usize = 64;
+/// Weight of a new sample in the smoothed latencies.
+const SMOOTHING: f64 = 0.2;
+
+/// Messages of the `latency` data channel.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LatencyMessage {
+    /// Sent by the sharer, the viewer answers with a `Pong` of the same id.
+    Ping {
+        id: u32,
+    },
+    Pong {
+        id: u32,
+    },
+    /// Sent by the sharer right after a frame, once per ping interval and after
+    /// input of the viewer. The viewer echoes the id in a `FrameShown` when it next
+    /// displays a frame. The marker goes over SCTP and the video over RTP, so the
+    /// frame displayed may be a little older or newer than the one marked.
+    Frame {
+        id: u64,
+    },
+    FrameShown {
+        id: u64,
+    },
+}
+
+/// Latencies of a viewer, smoothed over the last samples.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ViewerLatency {
+    /// Network round-trip time.
+    pub rtt: Option<Duration>,
+    /// From sending a frame marker to the viewer displaying a frame after receiving
+    /// it, which approximates the frame latency, see `LatencyMessage::Frame`.
+    pub frame_marker: Option<Duration>,
+    /// From the viewer sending input to it displaying a frame after receiving the
+    /// marker of the first frame sent after the input was received.
+    pub input_to_photon: Option<Duration>,
+}
+
+impl ViewerLatency {
+    /// The highest of each latency.
+    fn max(self, other: ViewerLatency) -> ViewerLatency {
+        ViewerLatency {
+            rtt: self.rtt.max(other.rtt),
+            frame_marker: self.frame_marker.max(other.frame_marker),
+            input_to_photon: self.input_to_photon.max(other.input_to_photon),
+        }
+    }
+}
+
+fn smooth(value: Option<Duration>, sample: Duration) -> Option<Duration> {
+    Some(match value {
+        Some(value) => value.mul_f64(1. - SMOOTHING) + sample.mul_f64(SMOOTHING),
+        None => sample,
+    })
+}
+
+struct Peer {
+    sender: Sender<String>,
+    pings: VecDeque<(u32, Instant)>,
+    /// Whether the next frame is marked to measure the frame latency.
+    marker_due: bool,
+    /// When the oldest input not shown yet was received, and the marker of the first
+    /// frame sent after it.
+    input: Option<(Instant, Option<u64>)>,
+    latency: ViewerLatency,
+}
+
+#[derive(Default)]
+struct LatencyState {
+    peers: HashMap<String, Peer>,
+    /// Ids of the last frame markers and when they were sent.
+    markers: VecDeque<(u64, Instant)>,
+    next_marker: u64,
+    next_ping: u32,
+}
+
+/// Measures the round-trip time, frame latency and input-to-photon latency of each
+/// viewer over the `latency` data channel.
+#[derive(Default)]
+pub struct LatencyManager {
+    state: Mutex<LatencyState>,
+}
+
+impl LatencyManager {
+    /// Ping viewers until the session ends.
+    pub fn start(self: &Arc<Self>, cancel: CancellationToken) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(PING_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => manager.ping(),
+                    _ = cancel.cancelled() => break,
+                }
+            }
+        });
+    }
+
+    /// The latencies of every connected viewer, by uuid.
+    pub fn latencies(&self) -> HashMap<String, ViewerLatency> {
+        let state = self.state.lock().unwrap();
+        state
+            .peers
+            .iter()
+            .map(|(uuid, peer)| (uuid.clone(), peer.latency))
+            .collect()
+    }
+
+    /// The highest latencies among viewers.
+    pub fn worst(&self) -> ViewerLatency {
+        let state = self.state.lock().unwrap();
+        state
+            .peers
+            .values()
+            .fold(ViewerLatency::default(), |worst, peer| {
+                worst.max(peer.latency)
+            })
+    }
+
+    /// The viewer sent input, the next frame is the first that may show its effect.
+    pub fn input_received(&self, uuid: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(peer) = state.peers.get_mut(uuid) {
+            if peer.input.is_none() {
+                peer.input = Some((Instant::now(), None));
+            }
+        }
+    }
+
+    /// A frame was sent to viewers, mark it for those measuring a latency.
+    pub fn frame_sent(&self) {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let waiting_input = |peer: &Peer| matches!(peer.input, Some((_, None)));
+        if !state
+            .peers
+            .values()
+            .any(|peer| peer.marker_due || waiting_input(peer))
+        {
+            return;
+        }
+        let id = state.next_marker;
+        state.next_marker += 1;
+        if state.markers.len() == MAX_MARKERS {
+            state.markers.pop_front();
+        }
+        state.markers.push_back((id, Instant::now()));
+        let message = serde_json::to_string(&LatencyMessage::Frame { id }).unwrap();
+        for peer in state.peers.values_mut() {
+            if !peer.marker_due && !waiting_input(peer) {
+                continue;
+            }
+            if let Some((received, None)) = peer.input {
+                peer.input = Some((received, Some(id)));
+            }
+            peer.marker_due = false;
+            peer.sender.try_send(message.clone()).ok();
+        }
+    }
+
+    fn ping(&self) {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_ping;
+        state.next_ping = state.next_ping.wrapping_add(1);
+        let message = serde_json::to_string(&LatencyMessage::Ping { id }).unwrap();
+        let now = Instant::now();
+        for peer in state.peers.values_mut() {
+            if peer.pings.len() == MAX_PENDING_PINGS {
+                peer.pings.pop_front();
+            }
+            peer.pings.push_back((id, now));
+            peer.marker_due = true;
+            peer.sender.try_send(message.clone()).ok();
+        }
+    }
+}
+
+#[async_trait]
+impl ChannelHandler for LatencyManager {
+    type Message = String;
+
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        self.state.lock().unwrap().peers.insert(
+            uuid,
+            Peer {
+                sender,
+                pings: VecDeque::new(),
+                marker_due: false,
+                input: None,
+                latency: ViewerLatency::default(),
+            },
+        );
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        let message = match serde_json::from_slice::<LatencyMessage>(&message.data) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Failed to parse latency message: {}", e);
+                return;
+            }
+        };
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let peer = match state.peers.get_mut(uuid) {
+            Some(peer) => peer,
+            None => return,
+        };
+        match message {
+            LatencyMessage::Pong { id } => {
+                if let Some(i) = peer.pings.iter().position(|(ping, _)| *ping == id) {
+                    let (_, sent) = peer.pings.remove(i).unwrap();
+                    peer.latency.rtt = smooth(peer.latency.rtt, now - sent);
+                    debug!("RTT of {}: {:?}", uuid, now - sent);
+                }
+            }
+            LatencyMessage::FrameShown { id } => {
+                if let Some((_, sent)) = state.markers.iter().find(|(marker, _)| *marker == id) {
+                    // the echo took half a round trip to come back
+                    let half_rtt = peer.latency.rtt.unwrap_or_default() / 2;
+                    let sample = (now - *sent).saturating_sub(half_rtt);
+                    peer.latency.frame_marker = smooth(peer.latency.frame_marker, sample);
+                }
+                // the one-way delays of the input and the echo cancel out
+                if let Some((received, Some(frame))) = peer.input {
+                    if id >= frame {
+                        peer.input = None;
+                        peer.latency.input_to_photon =
+                            smooth(peer.latency.input_to_photon, now - received);
+                        debug!("Input-to-photon latency of {}: {:?}", uuid, now - received);
+                    }
+                }
+            }
+            LatencyMessage::Ping { .. } | LatencyMessage::Frame { .. } => {
+                warn!("Unexpected latency message from {}: {:?}", uuid, message);
+            }
+        }
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.state.lock().unwrap().peers.remove(uuid);
+    }
+
+    async fn clear(&self) {
+        *self.state.lock().unwrap() = LatencyState::default();
+    }
+}
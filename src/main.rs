@@ -11,16 +11,26 @@ use crate::gui::app::App;
 use crate::output::OutputSink;
 use crate::result::Result;
 
+mod annotation;
 mod auth;
 mod capture;
+mod chat;
+mod clipboard;
 mod config;
+mod cursor;
 mod encoder;
+mod file_transfer;
+mod frame_trace;
 mod gui;
 mod inputs;
+mod latency;
+mod metrics;
 mod output;
 mod performance_profiler;
 mod result;
+mod session_report;
 mod signaller;
+mod stats;
 
 #[tokio::main]
 async fn main() {
@@ -39,6 +49,14 @@ async fn main() {
         .level_for("wgpu_hal", log::LevelFilter::Off)
         .level_for("iced_wgpu", log::LevelFilter::Warn)
         .chain(std::io::stdout())
+        // kept for the report of the session
+        .chain(
+            fern::Dispatch::new()
+                .level(log::LevelFilter::Error)
+                .chain(fern::Output::call(|record| {
+                    session_report::log_error(record.args().to_string())
+                })),
+        )
         .apply()
         .unwrap_or_else(|_| {
             eprintln!("Failed to initialize logger");
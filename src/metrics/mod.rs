@@ -0,0 +1,413 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::auth::ViewerManager;
+use crate::latency::LatencyManager;
+use crate::performance_profiler::PerformanceStats;
+use crate::signaller::Signaller;
+use crate::stats::StatsManager;
+use crate::Result;
+
+/// Upper bounds of the frame time histogram buckets, in seconds.
+const FRAME_TIME_BUCKETS: [f64; 11] = [
+    0.001, 0.002, 0.005, 0.008, 0.012, 0.016, 0.025, 0.033, 0.05, 0.1, 0.25,
+];
+/// Stages of processing a frame, as `stage` labels.
+const STAGES: [&str; 4] = ["preprocess", "encode", "send", "total"];
+/// Largest request accepted, metrics requests are a single short GET.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+struct Histogram {
+    buckets: [u64; FRAME_TIME_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: [0; FRAME_TIME_BUCKETS.len()],
+            count: 0,
+            sum: 0.,
+        }
+    }
+
+    fn observe(&mut self, value: Duration) {
+        let value = value.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(FRAME_TIME_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += value;
+    }
+
+    fn write(&self, out: &mut String, name: &str, labels: &str) {
+        for (count, bound) in self.buckets.iter().zip(FRAME_TIME_BUCKETS) {
+            writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, count
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        )
+        .unwrap();
+        writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum).unwrap();
+        writeln!(out, "{}_count{{{}}} {}", name, labels, self.count).unwrap();
+    }
+}
+
+/// The time spent in each stage of processing a frame.
+pub struct FrameTimes {
+    pub preprocess: Duration,
+    pub encode: Duration,
+    pub send: Duration,
+    pub total: Duration,
+}
+
+struct FrameMetrics {
+    stages: [Histogram; STAGES.len()],
+    frames: u64,
+    bytes: u64,
+}
+
+impl Default for FrameMetrics {
+    fn default() -> Self {
+        Self {
+            stages: [(); STAGES.len()].map(|_| Histogram::new()),
+            frames: 0,
+            bytes: 0,
+        }
+    }
+}
+
+/// Metrics of the sharer in the Prometheus text format, served on localhost when
+/// enabled in the `[metrics]` section.
+pub struct Metrics {
+    frames: Mutex<FrameMetrics>,
+    performance_stats: Arc<PerformanceStats>,
+    viewer_manager: Arc<ViewerManager>,
+    signaller: Arc<tokio::sync::Mutex<Option<Arc<dyn Signaller + Send + Sync>>>>,
+    stats_manager: Arc<StatsManager>,
+    latency_manager: Arc<LatencyManager>,
+}
+
+impl Metrics {
+    pub fn new(
+        performance_stats: Arc<PerformanceStats>,
+        viewer_manager: Arc<ViewerManager>,
+        signaller: Arc<tokio::sync::Mutex<Option<Arc<dyn Signaller + Send + Sync>>>>,
+        stats_manager: Arc<StatsManager>,
+        latency_manager: Arc<LatencyManager>,
+    ) -> Self {
+        Self {
+            frames: Mutex::new(FrameMetrics::default()),
+            performance_stats,
+            viewer_manager,
+            signaller,
+            stats_manager,
+            latency_manager,
+        }
+    }
+
+    /// A frame was encoded and sent.
+    pub fn frame_processed(&self, times: &FrameTimes, size: usize) {
+        let mut frames = self.frames.lock().unwrap();
+        let [preprocess, encode, send, total] = &mut frames.stages;
+        preprocess.observe(times.preprocess);
+        encode.observe(times.encode);
+        send.observe(times.send);
+        total.observe(times.total);
+        frames.frames += 1;
+        frames.bytes += size as u64;
+    }
+
+    /// Serve the metrics at `http://127.0.0.1:<port>/metrics` for as long as the
+    /// process runs.
+    pub fn serve(self: &Arc<Self>, port: u16) {
+        let metrics = self.clone();
+        tokio::spawn(async move {
+            let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("Failed to serve metrics on port {}: {}", port, e);
+                    return;
+                }
+            };
+            info!("Serving metrics at http://127.0.0.1:{}/metrics", port);
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(e) => {
+                        warn!("Failed to accept metrics connection: {}", e);
+                        continue;
+                    }
+                };
+                let metrics = metrics.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = metrics.handle_connection(stream).await {
+                        debug!("Metrics connection failed: {}", e);
+                    }
+                });
+            }
+        });
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<()> {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 1024];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+            let read = stream.read(&mut buf).await?;
+            if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+                return Ok(());
+            }
+            request.extend_from_slice(&buf[..read]);
+        }
+        let request = String::from_utf8_lossy(&request);
+        let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+        let response = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some("/metrics")) => {
+                let body = self.render().await;
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            }
+            _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        };
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    async fn render(&self) -> String {
+        let mut out = String::new();
+
+        {
+            let frames = self.frames.lock().unwrap();
+            let name = "mira_frame_stage_seconds";
+            header(
+                &mut out,
+                name,
+                "histogram",
+                "Time spent processing a frame, by stage.",
+            );
+            for (stage, histogram) in STAGES.iter().zip(&frames.stages) {
+                histogram.write(&mut out, name, &format!("stage=\"{}\"", stage));
+            }
+            let name = "mira_frames_total";
+            header(&mut out, name, "counter", "Frames encoded and sent.");
+            writeln!(out, "{} {}", name, frames.frames).unwrap();
+            let name = "mira_encoded_bytes_total";
+            header(&mut out, name, "counter", "Bytes of encoded video.");
+            writeln!(out, "{} {}", name, frames.bytes).unwrap();
+        }
+
+        let snapshot = self.performance_stats.snapshot();
+        let name = "mira_frame_stage_window_seconds";
+        header(
+            &mut out,
+            name,
+            "summary",
+            "Percentiles of the time spent processing frames over the last seconds, by stage.",
+        );
+        let stages = [
+            snapshot.preprocess,
+            snapshot.encode,
+            snapshot.send,
+            snapshot.total,
+        ];
+        for (stage, times) in STAGES.iter().zip(stages) {
+            for (quantile, time) in [("0.5", times.p50), ("0.95", times.p95), ("0.99", times.p99)] {
+                writeln!(
+                    out,
+                    "{}{{stage=\"{}\",quantile=\"{}\"}} {}",
+                    name,
+                    stage,
+                    quantile,
+                    time.as_secs_f64()
+                )
+                .unwrap();
+            }
+            writeln!(
+                out,
+                "{}_sum{{stage=\"{}\"}} {}",
+                name,
+                stage,
+                times.sum.as_secs_f64()
+            )
+            .unwrap();
+            writeln!(out, "{}_count{{stage=\"{}\"}} {}", name, stage, times.count).unwrap();
+        }
+        let name = "mira_dropped_frames_total";
+        header(
+            &mut out,
+            name,
+            "counter",
+            "Frames captured but dropped while the previous one was processed.",
+        );
+        writeln!(out, "{} {}", name, snapshot.dropped).unwrap();
+        let name = "mira_late_frames_total";
+        header(
+            &mut out,
+            name,
+            "counter",
+            "Frames which took longer than a frame interval to process.",
+        );
+        writeln!(out, "{} {}", name, snapshot.late).unwrap();
+        let name = "mira_fps";
+        header(&mut out, name, "gauge", "Frames sent over the last second.");
+        writeln!(out, "{} {}", name, snapshot.fps).unwrap();
+        let name = "mira_bitrate_bits_per_second";
+        header(
+            &mut out,
+            name,
+            "gauge",
+            "Encoded video bitrate over the last second.",
+        );
+        writeln!(out, "{} {}", name, snapshot.bitrate * 1000.).unwrap();
+
+        let pending = self.viewer_manager.get_pending_viewers().await.len();
+        let viewing = self.viewer_manager.get_viewing_viewers().await.len();
+        let name = "mira_viewers";
+        header(&mut out, name, "gauge", "Viewers of the session, by state.");
+        writeln!(out, "{}{{state=\"pending\"}} {}", name, pending).unwrap();
+        writeln!(out, "{}{{state=\"viewing\"}} {}", name, viewing).unwrap();
+
+        let (sharing, connected) = match self.signaller.lock().await.as_ref() {
+            Some(signaller) => (true, signaller.is_connected()),
+            None => (false, false),
+        };
+        let name = "mira_sharing";
+        header(&mut out, name, "gauge", "Whether a session is in progress.");
+        writeln!(out, "{} {}", name, sharing as u8).unwrap();
+        let name = "mira_signaller_connected";
+        header(
+            &mut out,
+            name,
+            "gauge",
+            "Whether the connection to the signaller is open.",
+        );
+        writeln!(out, "{} {}", name, connected as u8).unwrap();
+
+        self.render_peers(&mut out);
+        out
+    }
+
+    fn render_peers(&self, out: &mut String) {
+        let stats = self.stats_manager.stats();
+        let latencies = self.latency_manager.latencies();
+        // one sample for each viewer
+        let mut metric =
+            |name: &str, kind: &str, help: &str, value: &dyn Fn(&str) -> Option<f64>| {
+                header(out, name, kind, help);
+                for uuid in stats.keys() {
+                    if let Some(value) = value(uuid) {
+                        let viewer = escape_label(uuid);
+                        writeln!(out, "{}{{viewer=\"{}\"}} {}", name, viewer, value).unwrap();
+                    }
+                }
+            };
+        metric(
+            "mira_peer_bitrate_bits_per_second",
+            "gauge",
+            "Media sent to the viewer.",
+            &|uuid| Some(stats[uuid].bitrate * 1000.),
+        );
+        metric(
+            "mira_peer_sent_bytes_total",
+            "counter",
+            "Bytes of media sent to the viewer.",
+            &|uuid| Some(stats[uuid].bytes_sent as f64),
+        );
+        metric(
+            "mira_peer_lost_packets_total",
+            "counter",
+            "Video packets lost, as reported by the viewer.",
+            &|uuid| Some(stats[uuid].packets_lost as f64),
+        );
+        metric(
+            "mira_peer_loss_ratio",
+            "gauge",
+            "Fraction of video packets lost since the previous receiver report.",
+            &|uuid| Some(stats[uuid].fraction_lost),
+        );
+        metric(
+            "mira_peer_jitter_seconds",
+            "gauge",
+            "Video jitter, as reported by the viewer.",
+            &|uuid| stats[uuid].jitter.map(|jitter| jitter.as_secs_f64()),
+        );
+        metric(
+            "mira_peer_rtt_seconds",
+            "gauge",
+            "ICE round-trip time of the selected candidate pair.",
+            &|uuid| stats[uuid].rtt.map(|rtt| rtt.as_secs_f64()),
+        );
+        metric(
+            "mira_peer_nacks_total",
+            "counter",
+            "NACKs received from the viewer.",
+            &|uuid| Some(stats[uuid].nack_count as f64),
+        );
+        metric(
+            "mira_peer_plis_total",
+            "counter",
+            "Picture loss indications received from the viewer.",
+            &|uuid| Some(stats[uuid].pli_count as f64),
+        );
+        metric(
+            "mira_peer_relayed",
+            "gauge",
+            "Whether the media is relayed through a TURN server.",
+            &|uuid| Some(stats[uuid].is_relayed() as u8 as f64),
+        );
+        metric(
+            "mira_peer_input_to_photon_seconds",
+            "gauge",
+            "From the viewer sending input to it displaying the resulting frame.",
+            &|uuid| {
+                latencies
+                    .get(uuid)
+                    .and_then(|latency| latency.input_to_photon)
+                    .map(|latency| latency.as_secs_f64())
+            },
+        );
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+/// Escape a label value, in which backslashes, double quotes and line feeds must be.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_label_values() {
+        assert_eq!(escape_label("viewer-1"), "viewer-1");
+        assert_eq!(escape_label("a\\b\"c\nd"), "a\\\\b\\\"c\\nd");
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc::{channel, Sender};
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
+use webrtc::data_channel::RTCDataChannel;
+use webrtc::peer_connection::RTCPeerConnection;
+
+use crate::Result;
+
+/// Messages queued for a peer before they are sent on its channel.
+const CHANNEL_CAPACITY: usize = 32;
+
+/// A session service exchanging messages with every peer over a data channel of its
+/// own, registered with [`register_channel`].
+#[async_trait]
+pub trait ChannelHandler: Send + Sync + 'static {
+    /// What is sent to peers on the channel.
+    type Message: ChannelMessage;
+
+    /// The channel of `uuid` was opened, messages sent to `sender` are delivered to it.
+    async fn peer_connected(&self, uuid: String, sender: Sender<Self::Message>);
+
+    /// Handle a message received on the channel of `uuid`.
+    async fn handle_message(&self, _uuid: &str, _message: DataChannelMessage) {}
+
+    async fn peer_disconnected(&self, uuid: &str);
+
+    /// Forget all peers at the end of a session.
+    async fn clear(&self);
+}
+
+/// A message that can be sent on a data channel.
+#[async_trait]
+pub trait ChannelMessage: Send + 'static {
+    async fn send_on(self, channel: &RTCDataChannel) -> Result<()>;
+}
+
+#[async_trait]
+impl ChannelMessage for String {
+    async fn send_on(self, channel: &RTCDataChannel) -> Result<()> {
+        channel.send_text(self).await?;
+        Ok(())
+    }
+}
+
+/// Create the data channel `label` of the peer `uuid` and wire it to `handler`.
+pub async fn register_channel<H: ChannelHandler>(
+    peer_connection: &RTCPeerConnection,
+    label: &str,
+    uuid: &str,
+    handler: Arc<H>,
+) -> Result<Arc<RTCDataChannel>> {
+    let data_channel = peer_connection.create_data_channel(label, None).await?;
+
+    // handlers may send to the peer as soon as it is registered, so wait until the
+    // messages can be delivered
+    let channel_send = data_channel.clone();
+    let handler_open = handler.clone();
+    let open_uuid = uuid.to_string();
+    let open_label = label.to_string();
+    data_channel.on_open(Box::new(move || {
+        Box::pin(async move {
+            let (sender, mut receiver) = channel::<H::Message>(CHANNEL_CAPACITY);
+            handler_open.peer_connected(open_uuid, sender).await;
+            tokio::spawn(async move {
+                while let Some(message) = receiver.recv().await {
+                    if let Err(e) = message.send_on(&channel_send).await {
+                        warn!("Failed to send {} message: {}", open_label, e);
+                    }
+                }
+            });
+        })
+    }));
+
+    let handler_message = handler.clone();
+    let message_uuid = uuid.to_string();
+    data_channel.on_message(Box::new(move |msg| {
+        let handler = handler_message.clone();
+        let uuid = message_uuid.clone();
+        Box::pin(async move {
+            handler.handle_message(&uuid, msg).await;
+        })
+    }));
+
+    let close_uuid = uuid.to_string();
+    data_channel.on_close(Box::new(move || {
+        let handler = handler.clone();
+        let uuid = close_uuid.clone();
+        Box::pin(async move {
+            handler.peer_disconnected(&uuid).await;
+        })
+    }));
+
+    Ok(data_channel)
+}
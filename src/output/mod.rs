@@ -10,13 +10,17 @@ pub trait OutputSink: Send + Sync + 'static {
     async fn write_audio(&mut self, input: Bytes, duration: Duration) -> Result<()>;
 }
 
+mod data_channel;
 mod file_output;
 mod noop_output;
+mod session_services;
 mod webrtc_output;
 mod webrtc_peer;
 
+pub use data_channel::{ChannelHandler, ChannelMessage};
 pub use file_output::FileOutput;
 #[allow(unused_imports)]
 pub use noop_output::NoOpOutput;
+pub use session_services::SessionServices;
 pub use webrtc_output::WebRTCOutput;
 pub use webrtc_peer::WebRTCPeer;
@@ -0,0 +1,23 @@
+use std::sync::Arc;
+
+use crate::annotation::AnnotationManager;
+use crate::chat::ChatManager;
+use crate::clipboard::ClipboardHandler;
+use crate::cursor::CursorManager;
+use crate::file_transfer::FileTransferManager;
+use crate::inputs::InputHandler;
+use crate::latency::LatencyManager;
+use crate::stats::StatsManager;
+
+/// The handlers of a session the data channels and stats of every peer are wired to.
+#[derive(Clone)]
+pub struct SessionServices {
+    pub input_handler: Arc<InputHandler>,
+    pub clipboard_handler: Arc<ClipboardHandler>,
+    pub file_transfer_manager: Arc<FileTransferManager>,
+    pub chat_manager: Arc<ChatManager>,
+    pub annotation_manager: Arc<AnnotationManager>,
+    pub cursor_manager: Arc<CursorManager>,
+    pub latency_manager: Arc<LatencyManager>,
+    pub stats_manager: Arc<StatsManager>,
+}
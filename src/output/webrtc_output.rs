@@ -17,8 +17,8 @@ use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSampl
 
 use crate::auth::Authenticator;
 use crate::config::Config;
-use crate::inputs::InputHandler;
-use crate::output::WebRTCPeer;
+use crate::latency::LatencyManager;
+use crate::output::{SessionServices, WebRTCPeer};
 use crate::signaller::Signaller;
 use crate::OutputSink;
 use crate::Result;
@@ -30,6 +30,7 @@ pub struct WebRTCOutput {
     video_track: Arc<TrackLocalStaticSample>,
     audio_track: Arc<TrackLocalStaticSample>,
     frame_rate: u32,
+    latency_manager: Arc<LatencyManager>,
 }
 
 impl WebRTCOutput {
@@ -58,7 +59,7 @@ impl WebRTCOutput {
         signaller: Arc<dyn Signaller + Send + Sync>,
         authenticator: Arc<dyn Authenticator>,
         encoder_force_idr: &mut Arc<AtomicBool>,
-        input_handler: Arc<InputHandler>,
+        services: SessionServices,
         config: &Config,
     ) -> Result<Arc<Mutex<WebRTCOutput>>> {
         info!("Initializing WebRTC");
@@ -110,6 +111,7 @@ impl WebRTCOutput {
             video_track: video_track.clone(),
             audio_track: audio_track.clone(),
             frame_rate: config.max_fps,
+            latency_manager: services.latency_manager.clone(),
         }));
 
         let api_clone = api.clone();
@@ -165,14 +167,14 @@ impl WebRTCOutput {
                 let video_track_clone = video_track_clone.clone();
                 let audio_track_clone = audio_track_clone.clone();
                 let webrtc_config = webrtc_config.clone();
-                let input_handler = input_handler.clone();
+                let services = services.clone();
                 let ice_servers = ice_servers.clone();
                 tokio::spawn(async move {
                     let peer = WebRTCPeer::new(
                         Arc::new(api_clone.new_peer_connection(webrtc_config).await.unwrap()),
                         peer,
                         encoder_force_idr,
-                        input_handler,
+                        services,
                         video_track_clone,
                         audio_track_clone,
                         ice_servers,
@@ -202,6 +204,7 @@ impl OutputSink for WebRTCOutput {
             })
             .await
             .expect("TODO: panic message");
+        self.latency_manager.frame_sent();
         Ok(())
     }
     async fn write_audio(&mut self, input: Bytes, duration: Duration) -> Result<()> {
@@ -1,26 +1,63 @@
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use log::{debug, info};
 use rtcp::packet::unmarshal;
 use rtcp::payload_feedbacks::full_intra_request::FullIntraRequest;
 use rtcp::payload_feedbacks::picture_loss_indication::PictureLossIndication;
 use rtcp::payload_feedbacks::receiver_estimated_maximum_bitrate::ReceiverEstimatedMaximumBitrate;
 use rtcp::receiver_report::ReceiverReport;
+use rtcp::transport_feedbacks::transport_layer_nack::TransportLayerNack;
+use tokio::sync::mpsc::Sender;
+use webrtc::data_channel::data_channel_message::DataChannelMessage;
 use webrtc::ice_transport::ice_candidate::RTCIceCandidate;
 use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
 use webrtc::peer_connection::RTCPeerConnection;
 use webrtc::track::track_local::track_local_static_sample::TrackLocalStaticSample;
 
 use crate::inputs::InputHandler;
+use crate::latency::LatencyManager;
+use crate::output::data_channel::register_channel;
+use crate::output::{ChannelHandler, SessionServices};
 use crate::signaller::SignallerPeer;
 
 use crate::config::IceServer;
 use crate::Result;
 
+/// The `control` channel, input received on it also starts measuring the
+/// input-to-photon latency.
+struct ControlChannel {
+    input_handler: Arc<InputHandler>,
+    latency_manager: Arc<LatencyManager>,
+}
+
+#[async_trait]
+impl ChannelHandler for ControlChannel {
+    type Message = String;
+
+    async fn peer_connected(&self, uuid: String, sender: Sender<String>) {
+        self.input_handler.peer_connected(uuid, sender).await;
+    }
+
+    async fn handle_message(&self, uuid: &str, message: DataChannelMessage) {
+        self.latency_manager.input_received(uuid);
+        self.input_handler.handle_message(uuid, message).await;
+    }
+
+    async fn peer_disconnected(&self, uuid: &str) {
+        self.input_handler.peer_disconnected(uuid).await;
+    }
+
+    async fn clear(&self) {
+        self.input_handler.clear().await;
+    }
+}
+
 pub struct WebRTCPeer {
     uuid: String,
     peer_connection: Arc<RTCPeerConnection>,
+    input_handler: Arc<InputHandler>,
 }
 
 impl WebRTCPeer {
@@ -28,12 +65,22 @@ impl WebRTCPeer {
         peer_connection: Arc<RTCPeerConnection>,
         signaller_peer: Box<dyn SignallerPeer>,
         encoder_force_idr: Arc<AtomicBool>,
-        input_handler: Arc<InputHandler>,
+        services: SessionServices,
         video_track: Arc<TrackLocalStaticSample>,
         audio_track: Arc<TrackLocalStaticSample>,
         ice_servers: Vec<IceServer>,
     ) -> Result<Self> {
         debug!("Initializing a new WebRTC peer");
+        let SessionServices {
+            input_handler,
+            clipboard_handler,
+            file_transfer_manager,
+            chat_manager,
+            annotation_manager,
+            cursor_manager,
+            latency_manager,
+            stats_manager,
+        } = services;
 
         let uuid = signaller_peer.get_uuid();
         let rtp_sender = peer_connection.add_track(video_track).await?;
@@ -41,6 +88,8 @@ impl WebRTCPeer {
         peer_connection.add_track(audio_track).await?;
 
         let encoder_force_idr_clone = encoder_force_idr.clone();
+        let stats_manager_rtcp = stats_manager.clone();
+        let rtcp_uuid = uuid.clone();
         tokio::spawn(async move {
             let mut rtcp_buf = vec![0u8; 1500];
             while let Ok((size, _)) = rtp_sender.read(&mut rtcp_buf).await {
@@ -49,18 +98,22 @@ impl WebRTCPeer {
                 for pkt in pkts {
                     if let Some(_pli) = pkt.as_any().downcast_ref::<PictureLossIndication>() {
                         info!("PLI received");
+                        stats_manager_rtcp.pli_received(&rtcp_uuid);
                         encoder_force_idr_clone.store(true, std::sync::atomic::Ordering::Relaxed);
                     } else if let Some(_fir) = pkt.as_any().downcast_ref::<FullIntraRequest>() {
                         info!("FIR received");
                         encoder_force_idr_clone.store(true, std::sync::atomic::Ordering::Relaxed);
                     } else if let Some(report) = pkt.as_any().downcast_ref::<ReceiverReport>() {
                         let report = report.reports.first().unwrap();
+                        stats_manager_rtcp.report_received(&rtcp_uuid, report);
                         trace!(
                             "RR: jitter: {:.2} ms, lost: {}, delay: {:.2} ms",
                             (report.jitter as f64 / 90_000. * 1000.),
                             report.total_lost,
                             (report.delay as f64 / 65536. * 1000.)
                         );
+                    } else if pkt.as_any().downcast_ref::<TransportLayerNack>().is_some() {
+                        stats_manager_rtcp.nack_received(&rtcp_uuid);
                     } else if let Some(bitrate) = pkt
                         .as_any()
                         .downcast_ref::<ReceiverEstimatedMaximumBitrate>()
@@ -74,14 +127,24 @@ impl WebRTCPeer {
             Result::<()>::Ok(())
         });
 
-        let data_channel = peer_connection.create_data_channel("control", None).await?;
-        let input_handler = input_handler.clone();
-        data_channel.on_message(Box::new(move |msg| {
-            let input_handler = input_handler.clone();
-            Box::pin(async move {
-                input_handler.sender.send(msg.data).await.unwrap();
-            })
-        }));
+        let control_channel = ControlChannel {
+            input_handler: input_handler.clone(),
+            latency_manager: latency_manager.clone(),
+        };
+        register_channel(
+            &peer_connection,
+            "control",
+            &uuid,
+            Arc::new(control_channel),
+        )
+        .await?;
+
+        register_channel(&peer_connection, "clipboard", &uuid, clipboard_handler).await?;
+        register_channel(&peer_connection, "file", &uuid, file_transfer_manager).await?;
+        register_channel(&peer_connection, "chat", &uuid, chat_manager).await?;
+        register_channel(&peer_connection, "annotation", &uuid, annotation_manager).await?;
+        register_channel(&peer_connection, "cursor", &uuid, cursor_manager).await?;
+        register_channel(&peer_connection, "latency", &uuid, latency_manager).await?;
 
         // Set the handler for Peer connection state
         // This will notify you when the peer has connected/disconnected
@@ -136,10 +199,13 @@ impl WebRTCPeer {
             }
         });
 
+        stats_manager.start(uuid.clone(), peer_connection.clone());
+
         info!("WebRTC peer initialized");
         Ok(Self {
             uuid,
             peer_connection,
+            input_handler,
         })
     }
 
@@ -148,6 +214,8 @@ impl WebRTCPeer {
     }
 
     pub async fn kick(&self) {
+        // don't wait for the channel to close, the viewer may hold keys until then
+        self.input_handler.release(&self.uuid).await;
         self.peer_connection.close().await.unwrap_or_else(|e| {
             error!("Failed to close peer connection: {}", e);
         });
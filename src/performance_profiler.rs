@@ -1,42 +1,230 @@
-use chrono::{Timelike, Utc};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
 use howlong::HighResolutionTimer;
 
+use crate::frame_trace::{FrameTiming, FrameTrace};
+use crate::latency::LatencyManager;
+use crate::metrics::{FrameTimes, Metrics};
+
+/// Frames percentiles are computed over.
+const PERCENTILE_WINDOW: Duration = Duration::from_secs(5);
+/// Frames the frame rate and bitrate are computed over.
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// How often the profiler logs when enabled.
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Percentiles of the time spent in a stage over the last few seconds.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct StageSnapshot {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+    /// Total time of the frames in the window.
+    pub sum: Duration,
+    /// Frames in the window.
+    pub count: usize,
+}
+
+/// The nearest-rank percentile `p`, from 0 to 1, of values sorted in ascending
+/// order, `None` if there are none.
+pub fn nearest_rank<T: Copy>(sorted: &[T], p: f64) -> Option<T> {
+    match sorted.len() {
+        0 => None,
+        n => Some(sorted[((p * n as f64).ceil() as usize).clamp(1, n) - 1]),
+    }
+}
+
+impl StageSnapshot {
+    fn of(mut times: Vec<Duration>) -> Self {
+        times.sort_unstable();
+        let percentile = |p: f64| nearest_rank(&times, p).unwrap_or_default();
+        Self {
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+            p99: percentile(0.99),
+            max: times.last().copied().unwrap_or_default(),
+            sum: times.iter().sum(),
+            count: times.len(),
+        }
+    }
+}
+
+/// The performance of the stream at a point in time.
+#[derive(Debug, Clone, Default)]
+pub struct PerformanceSnapshot {
+    /// Frames sent over the last second.
+    pub fps: f64,
+    /// Video sent over the last second, in kbps.
+    pub bitrate: f64,
+    /// Frames sent since the session started.
+    pub frames: u64,
+    /// Bytes of video encoded since the session started.
+    pub bytes: u64,
+    /// Frames captured but dropped because the previous one was still being processed.
+    pub dropped: u64,
+    /// Frames which took longer than a frame interval to process.
+    pub late: u64,
+    pub preprocess: StageSnapshot,
+    pub encode: StageSnapshot,
+    pub send: StageSnapshot,
+    pub total: StageSnapshot,
+}
+
+struct FrameSample {
+    sent: Instant,
+    times: FrameTimes,
+    size: usize,
+}
+
+#[derive(Default)]
+struct Window {
+    samples: VecDeque<FrameSample>,
+    frames: u64,
+    bytes: u64,
+    late: u64,
+}
+
+impl Window {
+    /// Drop the samples older than `PERCENTILE_WINDOW` at `now`.
+    fn prune(&mut self, now: Instant) {
+        while let Some(oldest) = self.samples.front() {
+            if now.saturating_duration_since(oldest.sent) <= PERCENTILE_WINDOW {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+}
+
+/// The frames of the last few seconds, shared by the profiler of the capture loop
+/// with the GUI and the metrics.
+#[derive(Default)]
+pub struct PerformanceStats {
+    window: Mutex<Window>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl PerformanceStats {
+    /// Forget the frames of the previous session.
+    pub fn reset(&self) {
+        *self.window.lock().unwrap() = Window::default();
+        self.dropped.store(0, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> PerformanceSnapshot {
+        let now = Instant::now();
+        let mut window = self.window.lock().unwrap();
+        // no frames are pushed while the capture is paused, so the window only moves
+        // on here
+        window.prune(now);
+        let (frames, bytes) = window
+            .samples
+            .iter()
+            .filter(|sample| now.duration_since(sample.sent) <= RATE_WINDOW)
+            .fold((0, 0), |(frames, bytes), sample| {
+                (frames + 1, bytes + sample.size)
+            });
+        let stage = |time: fn(&FrameTimes) -> Duration| {
+            StageSnapshot::of(window.samples.iter().map(|s| time(&s.times)).collect())
+        };
+        PerformanceSnapshot {
+            fps: frames as f64 / RATE_WINDOW.as_secs_f64(),
+            bitrate: bytes as f64 * 8. / 1000. / RATE_WINDOW.as_secs_f64(),
+            frames: window.frames,
+            bytes: window.bytes,
+            dropped: self.dropped.load(Ordering::Relaxed),
+            late: window.late,
+            preprocess: stage(|times| times.preprocess),
+            encode: stage(|times| times.encode),
+            send: stage(|times| times.send),
+            total: stage(|times| times.total),
+        }
+    }
+
+    /// Counter of the frames dropped by the capturer, incremented by it directly.
+    pub fn dropped_frames(&self) -> Arc<AtomicU64> {
+        self.dropped.clone()
+    }
+
+    fn push(&self, sample: FrameSample, late: bool) {
+        let mut window = self.window.lock().unwrap();
+        window.prune(sample.sent);
+        window.frames += 1;
+        window.bytes += sample.size as u64;
+        window.samples.push_back(sample);
+        if late {
+            window.late += 1;
+        }
+    }
+}
+
+/// Times the stages of each frame in the capture loop.
 pub struct PerformanceProfiler {
     frame_time: u128,
     pre_processing_time: u128,
     encoding_time: u128,
     total_time: u128,
-    counter_conv_to_s: u128,
-    last_second: u8,
-    last_second_frame_count: u8,
-    current_second_frame_count: u8,
     log_enabled: bool,
-    bytes_encoded: usize,
-    last_bitrate: f64, // in kbps
     max_fps: u32,
     timer: HighResolutionTimer,
+    stats: Arc<PerformanceStats>,
+    last_report: Instant,
+    latency_manager: Option<Arc<LatencyManager>>,
+    metrics: Option<Arc<Metrics>>,
+    trace: Option<FrameTrace>,
+    frame_index: u64,
+    /// When the previous frame was done, and waiting for this one started.
+    wait_start_time: u128,
+    keyframe: bool,
 }
 
 impl PerformanceProfiler {
-    pub fn new(log_enabled: bool, max_fps: u32) -> Self {
+    pub fn new(log_enabled: bool, max_fps: u32, stats: Arc<PerformanceStats>) -> Self {
         Self {
             frame_time: 0,
             pre_processing_time: 0,
             encoding_time: 0,
             total_time: 0,
-            counter_conv_to_s: 1_000_000,
-            last_second: 0,
-            last_second_frame_count: 0,
-            current_second_frame_count: 0,
-            bytes_encoded: 0,
             log_enabled,
-            last_bitrate: 0.0,
             max_fps,
             timer: HighResolutionTimer::new(),
+            stats,
+            last_report: Instant::now(),
+            latency_manager: None,
+            metrics: None,
+            trace: None,
+            frame_index: 0,
+            wait_start_time: 0,
+            keyframe: false,
         }
     }
 
+    /// Report the latencies of the slowest viewer along with the frame times.
+    pub fn set_latency_manager(&mut self, latency_manager: Arc<LatencyManager>) {
+        self.latency_manager = Some(latency_manager);
+    }
+
+    /// Record the frame times in the metrics served to Prometheus.
+    pub fn set_metrics(&mut self, metrics: Arc<Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Write the timings of every frame to `trace`.
+    pub fn set_trace(&mut self, trace: FrameTrace) {
+        self.trace = Some(trace);
+    }
+
+    /// Counter the capturer increments when it drops a frame.
+    pub fn dropped_frames(&self) -> Arc<AtomicU64> {
+        self.stats.dropped_frames()
+    }
+
     pub fn accept_frame(&mut self, _frame_time: i64) {
+        self.wait_start_time = self.total_time;
         self.frame_time = self.current_time(); // frame_time is not accurate
     }
 
@@ -44,58 +232,150 @@ impl PerformanceProfiler {
         self.pre_processing_time = self.current_time();
     }
 
-    pub fn done_encoding(&mut self) {
+    pub fn done_encoding(&mut self, keyframe: bool) {
         self.encoding_time = self.current_time();
+        self.keyframe = keyframe;
     }
 
     pub fn done_processing(&mut self, size: usize) {
         self.total_time = self.current_time();
-        let current_second = Utc::now().second() as u8;
-        if current_second != self.last_second {
-            self.last_second = current_second;
-            self.last_second_frame_count = self.current_second_frame_count;
-            self.last_bitrate = self.bytes_encoded as f64 * 8.0 / 1000.0;
-            self.current_second_frame_count = 1;
-            self.bytes_encoded = size;
-        } else {
-            self.current_second_frame_count += 1;
-            self.bytes_encoded += size;
+        let times = FrameTimes {
+            preprocess: elapsed(self.frame_time, self.pre_processing_time),
+            encode: elapsed(self.pre_processing_time, self.encoding_time),
+            send: elapsed(self.encoding_time, self.total_time),
+            total: elapsed(self.frame_time, self.total_time),
+        };
+        if self.log_enabled && times.send > Duration::from_millis(8) {
+            warn!("send time abnormal: {:?}", times.send);
+        }
+        let late = times.total > Duration::from_secs(1) / self.max_fps;
+
+        if let Some(metrics) = &self.metrics {
+            metrics.frame_processed(&times, size);
+        }
+        if let Some(trace) = &mut self.trace {
+            let timing = FrameTiming {
+                index: self.frame_index,
+                wait_start: self.wait_start_time,
+                received: self.frame_time,
+                converted: self.pre_processing_time,
+                encoded: self.encoding_time,
+                sent: self.total_time,
+                size,
+                keyframe: self.keyframe,
+            };
+            if let Err(e) = trace.record(&timing) {
+                error!("Failed to write frame trace, stopping it: {}", e);
+                self.trace = None;
+            }
         }
-        if self.log_enabled {
+        self.frame_index += 1;
+        self.stats.push(
+            FrameSample {
+                sent: Instant::now(),
+                times,
+                size,
+            },
+            late,
+        );
+
+        if self.log_enabled && self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
             self.report();
         }
     }
 
     fn report(&self) {
-        let pre_processing_time = (self.pre_processing_time - self.frame_time) as f64
-            / self.counter_conv_to_s as f64
-            * 1000.0;
-        let encoding_time = (self.encoding_time - self.pre_processing_time) as f64
-            / self.counter_conv_to_s as f64
-            * 1000.0;
-        let webrtc_time =
-            (self.total_time - self.encoding_time) as f64 / self.counter_conv_to_s as f64 * 1000.0;
-        let total_time =
-            (self.total_time - self.frame_time) as f64 / self.counter_conv_to_s as f64 * 1000.0;
-        if webrtc_time > 8. {
-            warn!("send time abnormal: {}", webrtc_time);
-        }
-
+        let snapshot = self.stats.snapshot();
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
         info!(
-            "Total time {:.1}ms ({:.1} p, {:.1} e, {:.1} s) {:.1}% at {} FPS. Current FPS: {}/{:.1}. {:.1} kbps",
-            total_time,
-            pre_processing_time,
-            encoding_time,
-            webrtc_time,
-            (total_time / (1.0 / self.max_fps as f64 * 1000.0)) * 100.0,
+            "{:.0}/{} FPS, {:.1} kbps, {} dropped, {} late. Total {:.1}/{:.1}/{:.1}ms (p50/p95/p99), p95 {:.1} p, {:.1} e, {:.1} s",
+            snapshot.fps,
             self.max_fps,
-            self.last_second_frame_count,
-            1000.0/total_time,
-            self.last_bitrate
+            snapshot.bitrate,
+            snapshot.dropped,
+            snapshot.late,
+            ms(snapshot.total.p50),
+            ms(snapshot.total.p95),
+            ms(snapshot.total.p99),
+            ms(snapshot.preprocess.p95),
+            ms(snapshot.encode.p95),
+            ms(snapshot.send.p95)
         );
+        if let Some(latency_manager) = &self.latency_manager {
+            let latency = latency_manager.worst();
+            if let Some(rtt) = latency.rtt {
+                info!(
+                    "Worst viewer latency: RTT {:.1}ms, frame marker {:.1}ms, input-to-photon {:.1}ms",
+                    ms(rtt),
+                    ms(latency.frame_marker.unwrap_or_default()),
+                    ms(latency.input_to_photon.unwrap_or_default())
+                );
+            }
+        }
     }
 
     fn current_time(&self) -> u128 {
         self.timer.elapsed().as_micros()
     }
 }
+
+/// The time between two timer readings.
+fn elapsed(from: u128, to: u128) -> Duration {
+    Duration::from_micros(to.saturating_sub(from) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sent: Instant, total_ms: u64) -> FrameSample {
+        let total = Duration::from_millis(total_ms);
+        FrameSample {
+            sent,
+            times: FrameTimes {
+                preprocess: Duration::ZERO,
+                encode: total,
+                send: Duration::ZERO,
+                total,
+            },
+            size: 1000,
+        }
+    }
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let values: Vec<u32> = (1..=10).collect();
+        assert_eq!(nearest_rank(&values, 0.), Some(1));
+        assert_eq!(nearest_rank(&values, 0.05), Some(1));
+        assert_eq!(nearest_rank(&values, 0.5), Some(5));
+        assert_eq!(nearest_rank(&values, 0.51), Some(6));
+        assert_eq!(nearest_rank(&values, 0.95), Some(10));
+        assert_eq!(nearest_rank(&values, 1.), Some(10));
+        assert_eq!(nearest_rank(&[7.5], 0.99), Some(7.5));
+        assert_eq!(nearest_rank::<u32>(&[], 0.5), None);
+    }
+
+    #[test]
+    fn snapshot_drops_old_frames() {
+        let stats = PerformanceStats::default();
+        let now = Instant::now();
+        stats.push(sample(now - PERCENTILE_WINDOW * 2, 100), false);
+        stats.push(sample(now, 10), false);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total.count, 1);
+        assert_eq!(snapshot.total.max, Duration::from_millis(10));
+        assert_eq!(snapshot.frames, 2);
+    }
+
+    #[test]
+    fn snapshot_is_empty_once_frames_stop() {
+        let stats = PerformanceStats::default();
+        stats.push(sample(Instant::now() - PERCENTILE_WINDOW * 2, 100), true);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.total.count, 0);
+        assert_eq!(snapshot.total.p99, Duration::ZERO);
+        assert_eq!(snapshot.fps, 0.);
+        assert_eq!(snapshot.late, 1);
+    }
+}
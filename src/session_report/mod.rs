@@ -0,0 +1,412 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::ViewerIdentifier;
+use crate::config::EncoderConfig;
+use crate::performance_profiler::{nearest_rank, PerformanceSnapshot, PerformanceStats};
+use crate::stats::StatsManager;
+use crate::Result;
+
+/// How often the frame rate and bitrate are sampled for the summary.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Errors kept for a report, the rest are only counted.
+const MAX_ERRORS: usize = 100;
+
+/// Errors logged since the current session started.
+static ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+/// Errors logged beyond `MAX_ERRORS`.
+static MORE_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+/// Keep an error for the report of the current session, called by the logger.
+pub fn log_error(message: String) {
+    let mut errors = ERRORS.lock().unwrap();
+    if errors.len() < MAX_ERRORS {
+        errors.push(message);
+    } else {
+        MORE_ERRORS.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ViewerSession {
+    pub uuid: String,
+    pub name: String,
+    pub joined: String,
+    pub left: Option<String>,
+    /// Media sent to the viewer, as last collected while it was connected.
+    pub bytes_sent: u64,
+}
+
+/// The distribution of a value sampled every second.
+#[derive(Debug, Default, Serialize)]
+pub struct Distribution {
+    pub mean: f64,
+    pub p5: f64,
+    pub p50: f64,
+    pub p95: f64,
+}
+
+impl Distribution {
+    fn of(mut samples: Vec<f64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_by(f64::total_cmp);
+        let percentile = |p: f64| nearest_rank(&samples, p).unwrap_or_default();
+        Self {
+            mean: samples.iter().sum::<f64>() / samples.len() as f64,
+            p5: percentile(0.05),
+            p50: percentile(0.5),
+            p95: percentile(0.95),
+        }
+    }
+}
+
+/// A summary of a sharing session, saved as JSON and Markdown when it ends.
+#[derive(Debug, Serialize)]
+pub struct SessionReport {
+    pub started: String,
+    pub ended: String,
+    pub duration_secs: u64,
+    pub encoder: String,
+    pub encoding: String,
+    pub viewers: Vec<ViewerSession>,
+    pub frames: u64,
+    pub dropped_frames: u64,
+    pub late_frames: u64,
+    /// Size of the encoded video, which is sent to each viewer.
+    pub video_bytes_encoded: u64,
+    /// Media sent to all viewers.
+    pub bytes_sent: u64,
+    pub fps: Distribution,
+    pub bitrate_kbps: Distribution,
+    pub keyframes_forced: u64,
+    pub errors: Vec<String>,
+    /// Errors logged beyond those listed.
+    pub more_errors: usize,
+    #[serde(skip)]
+    file_name: String,
+}
+
+struct Session {
+    started: DateTime<Local>,
+    start: Instant,
+    encoder: String,
+    encoding: String,
+    viewers: Vec<ViewerSession>,
+    fps: Vec<f64>,
+    bitrate: Vec<f64>,
+    stats: Arc<StatsManager>,
+}
+
+impl Session {
+    /// Update what was sent to the viewers still connected, stats are gone once a
+    /// viewer left.
+    fn collect_bytes_sent(&mut self) {
+        let stats = self.stats.stats();
+        for viewer in self.viewers.iter_mut().filter(|v| v.left.is_none()) {
+            if let Some(stats) = stats.get(&viewer.uuid) {
+                viewer.bytes_sent = stats.bytes_sent;
+            }
+        }
+    }
+}
+
+/// Records the events of a session for its report.
+#[derive(Default)]
+pub struct SessionRecorder {
+    session: Mutex<Option<Session>>,
+}
+
+impl SessionRecorder {
+    /// Start recording a session, sampling `performance` and what `stats` reports
+    /// was sent to viewers until it ends.
+    pub fn start(
+        self: &Arc<Self>,
+        encoder: &EncoderConfig,
+        performance: Arc<PerformanceStats>,
+        stats: Arc<StatsManager>,
+        cancel: CancellationToken,
+    ) {
+        ERRORS.lock().unwrap().clear();
+        MORE_ERRORS.store(0, Ordering::Relaxed);
+        self.session.lock().unwrap().replace(Session {
+            started: Local::now(),
+            start: Instant::now(),
+            encoder: encoder.encoder.clone(),
+            encoding: encoder.encoding.clone(),
+            viewers: Vec::new(),
+            fps: Vec::new(),
+            bitrate: Vec::new(),
+            stats,
+        });
+        let recorder = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => recorder.sample(&performance.snapshot()),
+                    _ = cancel.cancelled() => break,
+                }
+            }
+        });
+    }
+
+    pub fn viewer_joined(&self, viewer: &ViewerIdentifier) {
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.viewers.push(ViewerSession {
+                uuid: viewer.uuid.clone(),
+                name: viewer.name.clone(),
+                joined: Local::now().to_rfc3339(),
+                left: None,
+                bytes_sent: 0,
+            });
+        }
+    }
+
+    pub fn viewer_left(&self, uuid: &str) {
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            session.collect_bytes_sent();
+            for viewer in session.viewers.iter_mut() {
+                if viewer.uuid == uuid && viewer.left.is_none() {
+                    viewer.left = Some(Local::now().to_rfc3339());
+                }
+            }
+        }
+    }
+
+    /// End the session, `None` if none was started.
+    pub fn finish(
+        &self,
+        performance: &PerformanceSnapshot,
+        keyframes_forced: u64,
+    ) -> Option<SessionReport> {
+        let mut session = self.session.lock().unwrap().take()?;
+        session.collect_bytes_sent();
+        let ended = Local::now().to_rfc3339();
+        let mut viewers = session.viewers;
+        for viewer in viewers.iter_mut() {
+            if viewer.left.is_none() {
+                viewer.left = Some(ended.clone());
+            }
+        }
+        let bytes_sent = viewers.iter().map(|v| v.bytes_sent).sum();
+        let errors = std::mem::take(&mut *ERRORS.lock().unwrap());
+        let more_errors = MORE_ERRORS.swap(0, Ordering::Relaxed);
+        Some(SessionReport {
+            started: session.started.to_rfc3339(),
+            ended,
+            duration_secs: session.start.elapsed().as_secs(),
+            encoder: session.encoder,
+            encoding: session.encoding,
+            viewers,
+            frames: performance.frames,
+            dropped_frames: performance.dropped,
+            late_frames: performance.late,
+            video_bytes_encoded: performance.bytes,
+            bytes_sent,
+            fps: Distribution::of(session.fps),
+            bitrate_kbps: Distribution::of(session.bitrate),
+            keyframes_forced,
+            errors,
+            more_errors,
+            file_name: format!("session-{}", session.started.format("%Y-%m-%d_%H-%M-%S")),
+        })
+    }
+
+    fn sample(&self, performance: &PerformanceSnapshot) {
+        if let Some(session) = self.session.lock().unwrap().as_mut() {
+            // not streaming yet
+            if performance.frames == 0 {
+                return;
+            }
+            session.fps.push(performance.fps);
+            session.bitrate.push(performance.bitrate);
+            session.collect_bytes_sent();
+        }
+    }
+}
+
+impl SessionReport {
+    /// Save the report as `session-<start time>.json` and `.md` in `dir`, returns the
+    /// path of the Markdown file.
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir)?;
+        fs::write(
+            dir.join(format!("{}.json", self.file_name)),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        let path = dir.join(format!("{}.md", self.file_name));
+        fs::write(&path, self.to_markdown())?;
+        Ok(path)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        writeln!(md, "# Mira Sharer session\n").unwrap();
+        writeln!(md, "| | |").unwrap();
+        writeln!(md, "|---|---|").unwrap();
+        writeln!(md, "| Started | {} |", self.started).unwrap();
+        writeln!(md, "| Ended | {} |", self.ended).unwrap();
+        writeln!(
+            md,
+            "| Duration | {} |",
+            humantime::format_duration(Duration::from_secs(self.duration_secs))
+        )
+        .unwrap();
+        writeln!(md, "| Encoder | {} ({}) |", self.encoder, self.encoding).unwrap();
+        writeln!(md, "| Frames | {} |", self.frames).unwrap();
+        writeln!(md, "| Dropped frames | {} |", self.dropped_frames).unwrap();
+        writeln!(md, "| Late frames | {} |", self.late_frames).unwrap();
+        writeln!(
+            md,
+            "| Video encoded | {:.1} MB |",
+            self.video_bytes_encoded as f64 / 1e6
+        )
+        .unwrap();
+        writeln!(
+            md,
+            "| Sent to viewers | {:.1} MB |",
+            self.bytes_sent as f64 / 1e6
+        )
+        .unwrap();
+        writeln!(md, "| Keyframes forced | {} |", self.keyframes_forced).unwrap();
+
+        writeln!(md, "\n## Performance\n").unwrap();
+        writeln!(md, "| | Mean | p5 | p50 | p95 |").unwrap();
+        writeln!(md, "|---|---|---|---|---|").unwrap();
+        for (name, distribution) in [("FPS", &self.fps), ("Bitrate (kbps)", &self.bitrate_kbps)] {
+            writeln!(
+                md,
+                "| {} | {:.1} | {:.1} | {:.1} | {:.1} |",
+                name, distribution.mean, distribution.p5, distribution.p50, distribution.p95
+            )
+            .unwrap();
+        }
+
+        writeln!(md, "\n## Viewers\n").unwrap();
+        if self.viewers.is_empty() {
+            writeln!(md, "No viewer joined.").unwrap();
+        } else {
+            writeln!(md, "| Name | Joined | Left | Sent |").unwrap();
+            writeln!(md, "|---|---|---|---|").unwrap();
+            for viewer in &self.viewers {
+                writeln!(
+                    md,
+                    "| {} | {} | {} | {:.1} MB |",
+                    viewer.name.replace('|', "\\|"),
+                    viewer.joined,
+                    viewer.left.as_deref().unwrap_or_default(),
+                    viewer.bytes_sent as f64 / 1e6
+                )
+                .unwrap();
+            }
+        }
+
+        writeln!(md, "\n## Errors\n").unwrap();
+        if self.errors.is_empty() {
+            writeln!(md, "None.").unwrap();
+        } else {
+            writeln!(md, "```").unwrap();
+            for error in &self.errors {
+                writeln!(md, "{}", error).unwrap();
+            }
+            if self.more_errors > 0 {
+                writeln!(md, "... and {} more", self.more_errors).unwrap();
+            }
+            writeln!(md, "```").unwrap();
+        }
+        md
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(name: &str, bytes_sent: u64) -> ViewerSession {
+        ViewerSession {
+            uuid: name.to_string(),
+            name: name.to_string(),
+            joined: "2024-01-01T10:00:00+00:00".to_string(),
+            left: Some("2024-01-01T11:00:00+00:00".to_string()),
+            bytes_sent,
+        }
+    }
+
+    fn report(viewers: Vec<ViewerSession>, errors: Vec<String>) -> SessionReport {
+        SessionReport {
+            started: "2024-01-01T10:00:00+00:00".to_string(),
+            ended: "2024-01-01T11:00:00+00:00".to_string(),
+            duration_secs: 3600,
+            encoder: "libx264".to_string(),
+            encoding: "h264".to_string(),
+            bytes_sent: viewers.iter().map(|v| v.bytes_sent).sum(),
+            viewers,
+            frames: 108000,
+            dropped_frames: 12,
+            late_frames: 3,
+            video_bytes_encoded: 1_500_000_000,
+            fps: Distribution::of(vec![30.; 10]),
+            bitrate_kbps: Distribution::default(),
+            keyframes_forced: 2,
+            errors,
+            more_errors: 0,
+            file_name: "session".to_string(),
+        }
+    }
+
+    #[test]
+    fn distribution_of_samples() {
+        let samples = (1..=100).rev().map(f64::from).collect();
+        let distribution = Distribution::of(samples);
+        assert_eq!(distribution.mean, 50.5);
+        assert_eq!(distribution.p5, 5.);
+        assert_eq!(distribution.p50, 50.);
+        assert_eq!(distribution.p95, 95.);
+
+        let distribution = Distribution::of(vec![7.]);
+        assert_eq!((distribution.p5, distribution.p95), (7., 7.));
+    }
+
+    #[test]
+    fn distribution_of_nothing() {
+        let distribution = Distribution::of(Vec::new());
+        assert_eq!(distribution.mean, 0.);
+        assert_eq!(distribution.p50, 0.);
+    }
+
+    #[test]
+    fn markdown_lists_viewers_and_what_they_were_sent() {
+        let viewers = vec![viewer("Alice", 2_000_000), viewer("Bob | Carol", 500_000)];
+        let md = report(viewers, Vec::new()).to_markdown();
+        assert!(md.contains("| Duration | 1h |"));
+        assert!(md.contains("| Encoder | libx264 (h264) |"));
+        assert!(md.contains("| Video encoded | 1500.0 MB |"));
+        assert!(md.contains("| Sent to viewers | 2.5 MB |"));
+        assert!(md.contains("| FPS | 30.0 | 30.0 | 30.0 | 30.0 |"));
+        assert!(md.contains(
+            "| Alice | 2024-01-01T10:00:00+00:00 | 2024-01-01T11:00:00+00:00 | 2.0 MB |"
+        ));
+        // the pipe in the name doesn't split the row
+        assert!(md.contains("| Bob \\| Carol | "));
+        assert!(md.contains("## Errors\n\nNone."));
+    }
+
+    #[test]
+    fn markdown_without_viewers_lists_errors() {
+        let mut report = report(Vec::new(), vec!["Encoder failed".to_string()]);
+        report.more_errors = 4;
+        let md = report.to_markdown();
+        assert!(md.contains("No viewer joined."));
+        assert!(md.contains("```\nEncoder failed\n... and 4 more\n```"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -38,6 +39,7 @@ pub struct WebSocketSignaller {
     topics_tx: Arc<RwLock<HashMap<&'static str, broadcast::Sender<SignallerMessage>>>>,
     topics_rx: RwLock<HashMap<&'static str, Mutex<broadcast::Receiver<SignallerMessage>>>>,
     room_id: std::sync::Mutex<Option<String>>,
+    connected: Arc<AtomicBool>,
 
     notify_update: Arc<dyn Fn() + Send + Sync>,
     shutdown_token: CancellationToken,
@@ -47,6 +49,7 @@ impl WebSocketSignaller {
     async fn process_incoming_message(
         mut read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
         topics_tx: Arc<RwLock<HashMap<&'static str, broadcast::Sender<SignallerMessage>>>>,
+        connected: Arc<AtomicBool>,
         shutdown_token: CancellationToken,
     ) {
         loop {
@@ -85,6 +88,7 @@ impl WebSocketSignaller {
                     }
             }
         }
+        connected.store(false, Ordering::Relaxed);
     }
     async fn keepalive(sender: Sender<SignallerMessage>, shutdown_token: CancellationToken) {
         let mut ticker = tokio::time::interval(std::time::Duration::from_secs(30));
@@ -156,9 +160,11 @@ impl WebSocketSignaller {
         let (write, read) = ws_stream.split();
 
         // handle all incoming websocket messages
+        let connected = Arc::new(AtomicBool::new(true));
         tokio::spawn(Self::process_incoming_message(
             read,
             topics_tx.clone(),
+            connected.clone(),
             shutdown_token.clone(),
         ));
 
@@ -180,6 +186,7 @@ impl WebSocketSignaller {
             topics_tx,
             topics_rx,
             room_id: std::sync::Mutex::new(None),
+            connected,
             notify_update,
             shutdown_token,
         })
@@ -257,6 +264,9 @@ impl Signaller for WebSocketSignaller {
         let room = self.room_id.lock().unwrap();
         room.clone()
     }
+    fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
     async fn blocking_wait_leave_message(
         &self,
         shutdown_token: CancellationToken,
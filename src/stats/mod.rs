@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use rtcp::reception_report::ReceptionReport;
+use webrtc::ice::candidate::CandidateType;
+use webrtc::peer_connection::peer_connection_state::RTCPeerConnectionState;
+use webrtc::peer_connection::RTCPeerConnection;
+use webrtc::stats::StatsReportType;
+
+/// How often the stats of each peer connection are collected.
+const STATS_INTERVAL: Duration = Duration::from_secs(2);
+/// The RTP clock rate of video, used for the jitter in receiver reports.
+const VIDEO_CLOCK_RATE: f64 = 90_000.;
+
+/// The WebRTC statistics of a viewer's connection.
+#[derive(Debug, Clone, Default)]
+pub struct PeerStats {
+    /// Media sent over the last interval, in kbps.
+    pub bitrate: f64,
+    pub bytes_sent: u64,
+    pub packets_sent: u64,
+    /// Video packets lost in total, as reported by the viewer.
+    pub packets_lost: u32,
+    /// The fraction of video packets lost since the previous receiver report.
+    pub fraction_lost: f64,
+    /// ICE round-trip time of the selected candidate pair.
+    pub rtt: Option<Duration>,
+    /// Video jitter as reported by the viewer.
+    pub jitter: Option<Duration>,
+    /// Types of the local and remote candidates of the selected pair.
+    pub candidates: Option<(CandidateType, CandidateType)>,
+    pub nack_count: u64,
+    pub pli_count: u64,
+    last_collected: Option<Instant>,
+}
+
+impl PeerStats {
+    /// Whether the media is relayed through a TURN server.
+    pub fn is_relayed(&self) -> bool {
+        matches!(
+            self.candidates,
+            Some((CandidateType::Relay, _)) | Some((_, CandidateType::Relay))
+        )
+    }
+
+    /// The type of the selected candidate pair, `relay` if either side is relayed.
+    pub fn connection_type(&self) -> Option<CandidateType> {
+        match self.candidates {
+            Some(_) if self.is_relayed() => Some(CandidateType::Relay),
+            Some((local, remote)) if local == CandidateType::Host => Some(remote),
+            Some((local, _)) => Some(local),
+            None => None,
+        }
+    }
+}
+
+/// Collects the WebRTC statistics of every viewer, from `get_stats` and the RTCP
+/// packets received from them.
+#[derive(Default)]
+pub struct StatsManager {
+    peers: Mutex<HashMap<String, PeerStats>>,
+}
+
+impl StatsManager {
+    /// Collect the stats of the peer `uuid` until its connection closes.
+    pub fn start(self: &Arc<Self>, uuid: String, peer_connection: Arc<RTCPeerConnection>) {
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(uuid.clone(), PeerStats::default());
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(STATS_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if matches!(
+                    peer_connection.connection_state(),
+                    RTCPeerConnectionState::Closed | RTCPeerConnectionState::Failed
+                ) {
+                    break;
+                }
+                // removed when the peer disconnects or the session ends
+                if !manager.collect(&uuid, &peer_connection).await {
+                    return;
+                }
+            }
+            manager.peer_disconnected(&uuid).await;
+        });
+    }
+
+    pub async fn peer_disconnected(&self, uuid: &str) {
+        self.peers.lock().unwrap().remove(uuid);
+    }
+
+    pub async fn clear(&self) {
+        self.peers.lock().unwrap().clear();
+    }
+
+    /// The stats of every connected viewer, by uuid.
+    pub fn stats(&self) -> HashMap<String, PeerStats> {
+        self.peers.lock().unwrap().clone()
+    }
+
+    pub fn pli_received(&self, uuid: &str) {
+        if let Some(stats) = self.peers.lock().unwrap().get_mut(uuid) {
+            stats.pli_count += 1;
+        }
+    }
+
+    pub fn nack_received(&self, uuid: &str) {
+        if let Some(stats) = self.peers.lock().unwrap().get_mut(uuid) {
+            stats.nack_count += 1;
+        }
+    }
+
+    /// A receiver report of the video stream.
+    pub fn report_received(&self, uuid: &str, report: &ReceptionReport) {
+        if let Some(stats) = self.peers.lock().unwrap().get_mut(uuid) {
+            stats.packets_lost = report.total_lost;
+            stats.fraction_lost = report.fraction_lost as f64 / 256.;
+            stats.jitter = Some(Duration::from_secs_f64(
+                report.jitter as f64 / VIDEO_CLOCK_RATE,
+            ));
+        }
+    }
+
+    /// Query the stats of the connection, returns false if the peer is gone.
+    async fn collect(&self, uuid: &str, peer_connection: &RTCPeerConnection) -> bool {
+        let report = peer_connection.get_stats().await;
+        let now = Instant::now();
+
+        let mut bytes_sent = 0;
+        let mut packets_sent = 0;
+        let mut pair = None;
+        for stats in report.reports.values() {
+            match stats {
+                StatsReportType::OutboundRTP(rtp) => {
+                    bytes_sent += rtp.bytes_sent;
+                    packets_sent += rtp.packets_sent;
+                }
+                StatsReportType::CandidatePair(candidate_pair) if candidate_pair.nominated => {
+                    pair = Some(candidate_pair);
+                }
+                _ => {}
+            }
+        }
+        let candidate_type = |id: &String| match report.reports.get(id) {
+            Some(StatsReportType::LocalCandidate(candidate))
+            | Some(StatsReportType::RemoteCandidate(candidate)) => Some(candidate.candidate_type),
+            _ => None,
+        };
+        let candidates = pair.and_then(|pair| {
+            Some((
+                candidate_type(&pair.local_candidate_id)?,
+                candidate_type(&pair.remote_candidate_id)?,
+            ))
+        });
+        let rtt = pair
+            .map(|pair| pair.current_round_trip_time)
+            .filter(|rtt| *rtt > 0.)
+            .map(Duration::from_secs_f64);
+
+        let mut peers = self.peers.lock().unwrap();
+        let stats = match peers.get_mut(uuid) {
+            Some(stats) => stats,
+            None => return false,
+        };
+        if let Some(last_collected) = stats.last_collected {
+            let elapsed = now.duration_since(last_collected).as_secs_f64();
+            stats.bitrate =
+                bytes_sent.saturating_sub(stats.bytes_sent) as f64 * 8. / 1000. / elapsed;
+        }
+        if let Some((local, remote)) = candidates {
+            if stats.candidates != candidates {
+                info!(
+                    "Viewer {} connected from a {} candidate to a {} candidate",
+                    uuid, local, remote
+                );
+            }
+        }
+        stats.bytes_sent = bytes_sent;
+        stats.packets_sent = packets_sent;
+        stats.candidates = candidates.or(stats.candidates);
+        stats.rtt = rtt.or(stats.rtt);
+        stats.last_collected = Some(now);
+        debug!("Stats of {}: {:?}", uuid, stats);
+        true
+    }
+}